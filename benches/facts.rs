@@ -0,0 +1,77 @@
+//! Benchmark reading fields from large facts. Evaluating a rule should only
+//! copy the values it reads, not the facts those values are part of.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use reval::prelude::*;
+use std::collections::BTreeMap;
+
+/// An order with a customer and a large number of line items
+fn order(items: usize) -> Value {
+    let item = |index: usize| {
+        Value::Map(BTreeMap::from([
+            ("sku".to_string(), format!("SKU-{index:05}").into()),
+            ("price".to_string(), Value::Int(index as i128 % 250)),
+            ("quantity".to_string(), Value::Int(1 + index as i128 % 3)),
+        ]))
+    };
+
+    let order = Value::Map(BTreeMap::from([
+        ("id".to_string(), "order-1".into()),
+        (
+            "customer".to_string(),
+            Value::Map(BTreeMap::from([
+                ("name".to_string(), "Customer".into()),
+                ("country".to_string(), "NL".into()),
+            ])),
+        ),
+        (
+            "items".to_string(),
+            Value::Vec((0..items).map(item).collect()),
+        ),
+    ]));
+
+    Value::Map(BTreeMap::from([("order".to_string(), order)]))
+}
+
+fn rules() -> Vec<(&'static str, Rule)> {
+    [
+        ("field", "order.items.0.price"),
+        (
+            "compare",
+            r#"order.customer.country == "NL" and order.items.10.price > i5"#,
+        ),
+        ("filter", "for item in order.items filter item.price > i200"),
+    ]
+    .into_iter()
+    .map(|(name, source)| {
+        let rule = Rule::parse(&format!("// {name}\n{source}")).unwrap();
+        (name, rule)
+    })
+    .collect()
+}
+
+fn bench_facts(criterion: &mut Criterion) {
+    let facts = order(5000);
+    let ruleset = ruleset().build();
+
+    // What it costs to copy the facts once, reading a field should be a lot
+    // cheaper than this
+    criterion.bench_function("clone facts", |bench| {
+        bench.iter_batched(|| (), |_| facts.clone(), BatchSize::SmallInput)
+    });
+
+    for (name, rule) in rules() {
+        let expr = rule.expr().clone();
+        criterion.bench_function(&format!("evaluate {name}"), |bench| {
+            bench.iter(|| expr.evaluate_blocking(&facts).unwrap())
+        });
+
+        let compiled = rule.compile();
+        criterion.bench_function(&format!("compiled {name}"), |bench| {
+            bench.iter(|| compiled.evaluate_blocking(&ruleset, &facts).unwrap())
+        });
+    }
+}
+
+criterion_group!(benches, bench_facts);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+//! This example shows how to use plain Rust closures as user-functions without
+//! implementing the `UserFunction` trait.
+
+use reval::prelude::*;
+use serde::Serialize;
+
+#[tokio::main]
+async fn main() {
+    // The input data-type for the rules
+    #[derive(Serialize)]
+    struct Data {
+        age: u16,
+        country: String,
+    }
+
+    // Set up a rule that calls the `legal_age` and `lookup_limit` functions
+    let rule = r"
+// age check
+legal_age(age, country) and lookup_limit(country) > i100
+";
+
+    // Register a closure and an async closure as user-functions, the
+    // arguments are converted from `Value`s and the results back to `Value`s
+    // automatically
+    let ruleset = ruleset()
+        .with_rule(Rule::parse(rule).unwrap())
+        .unwrap()
+        .with_fn(
+            "legal_age",
+            |age: i64, country: String| -> anyhow::Result<bool> {
+                Ok(age >= if country == "US" { 21 } else { 18 })
+            },
+        )
+        .unwrap()
+        .with_async_fn("lookup_limit", |country: String| async move {
+            // Pretend to look up a limit in a remote service
+            Ok::<_, anyhow::Error>(if country == "NL" { 500 } else { 100 })
+        })
+        .unwrap()
+        .build();
+
+    let facts = Data {
+        age: 19,
+        country: "NL".to_string(),
+    };
+
+    for outcome in ruleset.evaluate(&facts).await.unwrap() {
+        assert_eq!(outcome.value.unwrap(), true.into());
+    }
+}
@@ -17,7 +17,7 @@ async fn main() {
     // bypass the age check
     struct FakeId;
     #[async_trait::async_trait]
-    impl UserFunction for FakeId {
+    impl SingleArgFunction for FakeId {
         async fn call(&self, param: Value) -> FunctionResult {
             let age: i128 = param.try_into()?;
             Ok((age * 2).into())
@@ -0,0 +1,73 @@
+//! Run async evaluation to completion on the current thread without an async
+//! runtime
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+/// Wakes the thread that is blocked on a future
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll a future on the current thread until it completes, parking the thread
+/// while the future is waiting to be woken
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod when_blocking_on_futures {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn should_return_ready_value() {
+        assert_eq!(block_on(async { 5 }), 5);
+    }
+
+    #[test]
+    fn should_wait_for_wake_from_other_thread() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut sender = Some(sender);
+
+        let future = std::future::poll_fn(move |context| match receiver.try_recv() {
+            Ok(value) => Poll::Ready(value),
+            Err(_) => {
+                // Send the value and wake the blocked thread from another thread
+                if let Some(sender) = sender.take() {
+                    let waker = context.waker().clone();
+                    thread::spawn(move || {
+                        thread::sleep(Duration::from_millis(10));
+                        sender.send(7).unwrap();
+                        waker.wake();
+                    });
+                }
+                Poll::Pending
+            }
+        });
+
+        assert_eq!(block_on(future), 7);
+    }
+}
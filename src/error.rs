@@ -1,6 +1,13 @@
 //! Reval error types
 
-use crate::value::Value;
+use crate::{
+    limits::Limit,
+    parse::Span,
+    value::{
+        ser::{SerializationError, SerializationErrorKind},
+        Value,
+    },
+};
 use std::{num::TryFromIntError, result};
 
 /// Result type for anything Reval
@@ -19,10 +26,16 @@ pub enum Error {
     DuplicateRuleName(String),
 
     #[error("An error occured serializing to a value; {0}")]
-    ValueSerializationError(String),
+    ValueSerializationError(SerializationError),
+
+    #[error("An error occured deserializing from a value; {0}")]
+    ValueDeserializationError(String),
 
-    #[error("Tried to perform an operation on a value with an invalid type")]
-    InvalidType,
+    #[error("Cannot apply `{operation}` to {}", types.join(" and "))]
+    InvalidType {
+        operation: String,
+        types: Vec<&'static str>,
+    },
 
     #[error("Cannot cast value type {0:?} to {1}")]
     InvalidCast(Value, String),
@@ -30,6 +43,12 @@ pub enum Error {
     #[error("Numeric overflow casting int types")]
     NumericOverflow(#[from] TryFromIntError),
 
+    #[error("Numeric overflow applying `{operation}` to {}", types.join(" and "))]
+    ArithmeticOverflow {
+        operation: String,
+        types: Vec<&'static str>,
+    },
+
     #[error("Unexpected value {0:?}, expected {1}")]
     UnexpectedValueType(Value, String),
 
@@ -48,6 +67,9 @@ pub enum Error {
         error: anyhow::Error,
     },
 
+    #[error("Invalid arguments for user function {function}; {reason}")]
+    InvalidFunctionArgs { function: String, reason: String },
+
     #[error("No user function with name {0}")]
     UnknownUserFunction(String),
 
@@ -59,11 +81,69 @@ pub enum Error {
 
     #[error("Invalid symbol; {0}")]
     InvalidSymbol(String),
+
+    #[error("Evaluation exceeded the limit of {max} {limit}")]
+    LimitExceeded { limit: Limit, max: usize },
+
+    #[error("Evaluation timed out")]
+    Timeout,
+
+    #[error("Evaluation was cancelled")]
+    Cancelled,
+
+    #[error("{error} at {span}")]
+    Located { span: Span, error: Box<Error> },
 }
 
 impl Error {
+    /// Span in the rule source text of the expression that caused the error
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Located { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// The error without the location it occured at
+    pub fn without_span(&self) -> &Error {
+        match self {
+            Error::Located { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// Error for an operation that can not be applied to the types of the
+    /// values passed to it
+    pub fn invalid_type<'a>(
+        operation: impl Into<String>,
+        values: impl IntoIterator<Item = &'a Value>,
+    ) -> Self {
+        Error::InvalidType {
+            operation: operation.into(),
+            types: values.into_iter().map(Value::type_name).collect(),
+        }
+    }
+
+    /// Error for an operation that produces a number outside of the range of
+    /// its result type
+    pub fn overflow<'a>(
+        operation: impl Into<String>,
+        values: impl IntoIterator<Item = &'a Value>,
+    ) -> Self {
+        Error::ArithmeticOverflow {
+            operation: operation.into(),
+            types: values.into_iter().map(Value::type_name).collect(),
+        }
+    }
+
     pub fn ser(error_msg: impl Into<String>) -> Self {
-        Error::ValueSerializationError(error_msg.into())
+        Error::ValueSerializationError(SerializationError::new(SerializationErrorKind::Custom(
+            error_msg.into(),
+        )))
+    }
+
+    pub fn de(error_msg: impl Into<String>) -> Self {
+        Error::ValueDeserializationError(error_msg.into())
     }
 
     /// Construct InvalidCast Error
@@ -14,9 +14,15 @@ impl Expr {
         Expr::Value(Value::None)
     }
 
-    /// Function expression constructor
+    /// Function expression constructor for a function with a single parameter
     pub fn func(name: impl Into<String>, param: Expr) -> Self {
-        Expr::Function(name.into(), Box::new(param))
+        Expr::Function(name.into(), vec![param])
+    }
+
+    /// Function expression constructor for a function with any number of
+    /// parameters
+    pub fn func_args(name: impl Into<String>, params: impl IntoIterator<Item = Expr>) -> Self {
+        Expr::Function(name.into(), params.into_iter().collect())
     }
 
     /// Reference an input value
@@ -244,4 +250,72 @@ impl Expr {
     pub fn for_filter(bind: impl ToString, list: Expr, predicate: Expr) -> Self {
         Expr::ForFilter(bind.to_string(), Box::new(list), Box::new(predicate))
     }
+
+    pub fn for_map_entries(
+        key: impl ToString,
+        bind: impl ToString,
+        list: Expr,
+        operation: Expr,
+    ) -> Self {
+        Expr::ForMapEntries(
+            key.to_string(),
+            bind.to_string(),
+            Box::new(list),
+            Box::new(operation),
+        )
+    }
+
+    pub fn for_filter_entries(
+        key: impl ToString,
+        bind: impl ToString,
+        list: Expr,
+        predicate: Expr,
+    ) -> Self {
+        Expr::ForFilterEntries(
+            key.to_string(),
+            bind.to_string(),
+            Box::new(list),
+            Box::new(predicate),
+        )
+    }
+
+    pub fn for_fold(
+        bind: impl ToString,
+        list: Expr,
+        acc: impl ToString,
+        init: Expr,
+        operation: Expr,
+    ) -> Self {
+        Expr::ForFold(
+            bind.to_string(),
+            Box::new(list),
+            acc.to_string(),
+            Box::new(init),
+            Box::new(operation),
+        )
+    }
+
+    pub fn for_sort_by(bind: impl ToString, list: Expr, key: Expr) -> Self {
+        Expr::ForSortBy(bind.to_string(), Box::new(list), Box::new(key))
+    }
+
+    /// Slice expression constructor, positions that are `None` are left open
+    pub fn slice(value: Expr, start: Option<Expr>, end: Option<Expr>) -> Self {
+        Expr::Slice(Box::new(value), start.map(Box::new), end.map(Box::new))
+    }
+
+    /// Coalesce expression constructor
+    pub fn coalesce(left: Expr, right: Expr) -> Self {
+        Expr::Coalesce(Box::new(left), Box::new(right))
+    }
+
+    /// Strict index expression constructor
+    pub fn strict_index(value: Expr, index: Index) -> Self {
+        Expr::StrictIndex(Box::new(value), index)
+    }
+
+    /// Let expression constructor
+    pub fn let_in(bind: impl ToString, value: Expr, body: Expr) -> Self {
+        Expr::Let(bind.to_string(), Box::new(value), Box::new(body))
+    }
 }
@@ -0,0 +1,337 @@
+//! Functions that can be called from every rule without registering them, a
+//! user function with the same name takes precedence over a builtin
+
+use super::{add, compare, sort_by_keys, sub};
+use crate::{
+    error::{Error, Result},
+    ruleset::RuleSet,
+    value::Value,
+};
+use rust_decimal::prelude::*;
+use std::{cmp::Ordering, collections::BTreeMap};
+
+/// A builtin function, gets the ruleset it is called from to apply its
+/// policies
+pub(crate) type Builtin = fn(&RuleSet, &[Value]) -> Result<Value>;
+
+/// Look up a builtin function by name, `None` when the ruleset has a user
+/// function with that name
+pub(crate) fn builtin(ruleset: &RuleSet, name: &str) -> Option<Builtin> {
+    if ruleset.has_function(name) {
+        return None;
+    }
+
+    match name {
+        "keys" => Some(keys),
+        "values" => Some(values),
+        "entries" => Some(entries),
+        "sum" => Some(sum),
+        "count" => Some(count),
+        "min" => Some(min),
+        "max" => Some(max),
+        "avg" => Some(avg),
+        "len" => Some(len),
+        "sort" => Some(sort),
+        "distinct" => Some(distinct),
+        "flatten" => Some(flatten),
+        "reverse" => Some(reverse),
+        "concat" => Some(concat),
+        "merge" => Some(merge),
+        "first" => Some(first),
+        "last" => Some(last),
+        _ => None,
+    }
+}
+
+/// The keys of a map or the positions of the items in a vec
+fn keys(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    map_entries("keys", args, |key, _| key)
+}
+
+/// The values of a map or the items of a vec
+fn values(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    map_entries("values", args, |_, item| item.clone())
+}
+
+/// The `[key, value]` pairs of a map or the `[position, item]` pairs of a vec
+fn entries(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    map_entries("entries", args, |key, item| {
+        Value::Vec(vec![key, item.clone()])
+    })
+}
+
+fn map_entries(
+    name: &str,
+    args: &[Value],
+    entry: impl Fn(Value, &Value) -> Value,
+) -> Result<Value> {
+    let [value] = expect_args(name, args)?;
+
+    Ok(match super::entries(name, value)? {
+        Some(entries) => Value::Vec(
+            entries
+                .into_iter()
+                .map(|(key, item)| entry(key, item))
+                .collect(),
+        ),
+        None => Value::None,
+    })
+}
+
+/// Add up the items of a list like `+` does
+fn sum(ruleset: &RuleSet, args: &[Value]) -> Result<Value> {
+    match items("sum", args)? {
+        Some(items) => total(ruleset, items.into_iter().cloned()),
+        None => Ok(Value::None),
+    }
+}
+
+/// The number of items in a list that are not `None`
+fn count(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    Ok(match items("count", args)? {
+        Some(items) => Value::Int(items.len() as i128),
+        None => Value::None,
+    })
+}
+
+/// The smallest item of a list, compared like `<` does
+fn min(ruleset: &RuleSet, args: &[Value]) -> Result<Value> {
+    extreme(ruleset, "min", args, Ordering::Less)
+}
+
+/// The largest item of a list, compared like `>` does
+fn max(ruleset: &RuleSet, args: &[Value]) -> Result<Value> {
+    extreme(ruleset, "max", args, Ordering::Greater)
+}
+
+/// The mean of the items of a list. The average of ints is a decimal, the
+/// average of dates is the first date plus the average offset of the dates
+/// from it
+fn avg(ruleset: &RuleSet, args: &[Value]) -> Result<Value> {
+    let Some(items) = items("avg", args)? else {
+        return Ok(Value::None);
+    };
+
+    match items.first() {
+        Some(Value::DateTime(start)) => {
+            let offsets = items
+                .iter()
+                .map(|item| sub((*item).clone(), Value::DateTime(*start)))
+                .collect::<Result<Vec<_>>>()?;
+            add(Value::DateTime(*start), average(ruleset, offsets)?)
+        }
+        Some(_) => average(ruleset, items.into_iter().cloned().collect()),
+        None => Ok(Value::None),
+    }
+}
+
+/// The number of characters in a string or items in a vec or map
+fn len(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    let [value] = expect_args("len", args)?;
+
+    Ok(match value {
+        Value::String(string) => Value::Int(string.chars().count() as i128),
+        Value::Vec(vec) => Value::Int(vec.len() as i128),
+        Value::Map(map) => Value::Int(map.len() as i128),
+        Value::None => Value::None,
+        value => return Err(Error::invalid_type("len", [value])),
+    })
+}
+
+/// The items of a vec or the values of a map in the order of
+/// `Value::total_cmp`
+fn sort(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    let Some(items) = list("sort", args)? else {
+        return Ok(Value::None);
+    };
+    let keyed = items.into_iter().map(|item| (item.clone(), item)).collect();
+
+    Ok(Value::Vec(sort_by_keys(keyed)))
+}
+
+/// The items of a vec or the values of a map without duplicates, keeps the
+/// first of equal items. Items are compared like `==` does, so numbers of
+/// different types are duplicates when the `NumericPolicy` promotes them
+fn distinct(ruleset: &RuleSet, args: &[Value]) -> Result<Value> {
+    let Some(items) = list("distinct", args)? else {
+        return Ok(Value::None);
+    };
+    let policy = ruleset.numeric_policy();
+
+    let mut result = Vec::<Value>::new();
+    for item in items {
+        if !result.iter().any(|kept| policy.equals(kept, &item)) {
+            result.push(item);
+        }
+    }
+
+    Ok(Value::Vec(result))
+}
+
+/// Replace the vecs in a vec or the values of a map by their items, other
+/// items are kept
+fn flatten(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    let Some(items) = list("flatten", args)? else {
+        return Ok(Value::None);
+    };
+
+    Ok(Value::Vec(
+        items
+            .into_iter()
+            .flat_map(|item| match item {
+                Value::Vec(vec) => vec,
+                item => vec![item],
+            })
+            .collect(),
+    ))
+}
+
+/// The items of a vec or the characters of a string in reverse order
+fn reverse(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    let [value] = expect_args("reverse", args)?;
+
+    Ok(match value {
+        Value::String(string) => Value::String(string.chars().rev().collect()),
+        Value::Vec(vec) => Value::Vec(vec.iter().rev().cloned().collect()),
+        Value::None => Value::None,
+        value => return Err(Error::invalid_type("reverse", [value])),
+    })
+}
+
+/// The items of all vecs one after the other, `None` when any of them is
+/// `None` like `+`
+fn concat(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    let mut result = Vec::new();
+
+    for arg in args {
+        match arg {
+            Value::Vec(vec) => result.extend(vec.iter().cloned()),
+            Value::None => return Ok(Value::None),
+            arg => return Err(Error::invalid_type("concat", [arg])),
+        }
+    }
+
+    Ok(Value::Vec(result))
+}
+
+/// The entries of all maps, later maps replace the values of earlier ones
+/// with the same key. `None` when any of them is `None`
+fn merge(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    let mut result = BTreeMap::new();
+
+    for arg in args {
+        match arg {
+            Value::Map(map) => result.extend(map.clone()),
+            Value::None => return Ok(Value::None),
+            arg => return Err(Error::invalid_type("merge", [arg])),
+        }
+    }
+
+    Ok(Value::Map(result))
+}
+
+/// The first item of a vec, `None` for an empty vec
+fn first(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    end("first", args, <[Value]>::first)
+}
+
+/// The last item of a vec, `None` for an empty vec
+fn last(_: &RuleSet, args: &[Value]) -> Result<Value> {
+    end("last", args, <[Value]>::last)
+}
+
+fn end(name: &str, args: &[Value], select: fn(&[Value]) -> Option<&Value>) -> Result<Value> {
+    let [value] = expect_args(name, args)?;
+
+    match value {
+        Value::Vec(vec) => Ok(select(vec).cloned().unwrap_or(Value::None)),
+        Value::None => Ok(Value::None),
+        value => Err(Error::invalid_type(name, [value])),
+    }
+}
+
+/// The items of a vec or the values of a map, `None` for `None`
+fn list(name: &str, args: &[Value]) -> Result<Option<Vec<Value>>> {
+    let [list] = expect_args(name, args)?;
+
+    match list {
+        Value::Vec(vec) => Ok(Some(vec.clone())),
+        Value::Map(map) => Ok(Some(map.values().cloned().collect())),
+        Value::None => Ok(None),
+        list => Err(Error::invalid_type(name, [list])),
+    }
+}
+
+/// The items of a vec or the values of a map that are not `None`, aggregates
+/// skip `None` items and are `None` when the list itself is `None`
+fn items<'a>(name: &str, args: &'a [Value]) -> Result<Option<Vec<&'a Value>>> {
+    let [list] = expect_args(name, args)?;
+    let is_some = |item: &&Value| !matches!(item, Value::None);
+
+    match list {
+        Value::Vec(vec) => Ok(Some(vec.iter().filter(is_some).collect())),
+        Value::Map(map) => Ok(Some(map.values().filter(is_some).collect())),
+        Value::None => Ok(None),
+        list => Err(Error::invalid_type(name, [list])),
+    }
+}
+
+/// Add up values, numbers of different types are promoted like they are for
+/// `+`. The total of no values is `None`
+fn total(ruleset: &RuleSet, mut items: impl Iterator<Item = Value>) -> Result<Value> {
+    let Some(first) = items.next() else {
+        return Ok(Value::None);
+    };
+
+    items.try_fold(first, |total, item| {
+        let (total, item) = ruleset.numeric_policy().promote("+", total, item)?;
+        add(total, item)
+    })
+}
+
+fn average(ruleset: &RuleSet, items: Vec<Value>) -> Result<Value> {
+    let count = items.len();
+    let total = total(ruleset, items.into_iter())?;
+
+    let result = match &total {
+        Value::Int(value) => Decimal::from_i128(*value)
+            .and_then(|value| value.checked_div(Decimal::from(count)))
+            .map(Value::Decimal),
+        Value::Float(value) => Some(Value::Float(value / count as f64)),
+        Value::Decimal(value) => value.checked_div(Decimal::from(count)).map(Value::Decimal),
+        Value::Duration(value) => i32::try_from(count)
+            .ok()
+            .and_then(|count| value.checked_div(count))
+            .map(Value::Duration),
+        Value::None => Some(Value::None),
+        value => return Err(Error::invalid_type("avg", [value])),
+    };
+    result.ok_or_else(|| Error::overflow("avg", [&total]))
+}
+
+/// The item of a list that is ordered before or after all other items
+fn extreme(ruleset: &RuleSet, name: &str, args: &[Value], keep: Ordering) -> Result<Value> {
+    let Some(items) = items(name, args)? else {
+        return Ok(Value::None);
+    };
+    let mut result: Option<&Value> = None;
+
+    for item in items {
+        result = match result {
+            Some(current) => match compare(ruleset.numeric_policy(), name, item, current)? {
+                Some(ordering) if ordering == keep => Some(item),
+                _ => Some(current),
+            },
+            None => Some(item),
+        };
+    }
+
+    Ok(result.cloned().unwrap_or(Value::None))
+}
+
+fn expect_args<'a, const N: usize>(name: &str, args: &'a [Value]) -> Result<&'a [Value; N]> {
+    args.try_into().map_err(|_| Error::InvalidFunctionArgs {
+        function: name.to_string(),
+        reason: format!("expected {N} arguments, found {}", args.len()),
+    })
+}
@@ -1,8 +1,15 @@
+use super::{builtin::builtin, fact};
 use crate::{
     error::{Error, Result},
+    expr::Expr,
+    interrupt::Interrupt,
+    limits::Budget,
     ruleset::RuleSet,
-    symbol::Symbols,
-    value::Value,
+    value::{logic::LogicPolicy, numeric::NumericPolicy, Value},
+};
+use std::{
+    ptr::null_mut,
+    sync::atomic::{AtomicPtr, Ordering},
 };
 
 #[derive(Clone)]
@@ -14,39 +21,101 @@ pub(super) struct EvalContext<'a> {
     facts: &'a Value,
 
     /// Scope adds symbols that are only valid at the current level of evaluation
-    scope: Symbols,
+    scope: Option<Binding<'a>>,
+
+    /// The first expression that failed to evaluate
+    failed: &'a AtomicPtr<Expr>,
+
+    /// The resources used so far, checked against the ruleset limits
+    budget: &'a Budget,
+
+    /// Stops evaluation when the deadline passes or it is cancelled
+    interrupt: Interrupt<'a>,
+}
+
+/// A variable bound by a `for` loop or `let`. Bindings link to the scope around them
+/// so starting a scope does not copy the variables that are already bound
+#[derive(Clone, Copy)]
+struct Binding<'a> {
+    name: &'a str,
+    value: &'a Value,
+    parent: Option<&'a Binding<'a>>,
+}
+
+impl<'a> Binding<'a> {
+    fn get(&self, name: &str) -> Option<&'a Value> {
+        let mut binding = self;
+        loop {
+            if binding.name == name {
+                return Some(binding.value);
+            }
+            binding = binding.parent?;
+        }
+    }
 }
 
 impl<'a> EvalContext<'a> {
-    pub(super) fn new(ruleset: &'a RuleSet, facts: &'a Value) -> Self {
+    pub(super) fn new(
+        ruleset: &'a RuleSet,
+        facts: &'a Value,
+        failed: &'a AtomicPtr<Expr>,
+        budget: &'a Budget,
+        interrupt: Interrupt<'a>,
+    ) -> Self {
         Self {
             ruleset,
             facts,
-            scope: Symbols::default(),
+            scope: None,
+            failed,
+            budget,
+            interrupt,
         }
     }
 }
 
 impl EvalContext<'_> {
-    pub(super) async fn call_function(&self, name: &str, params: Value) -> Result<Value> {
-        self.ruleset.call_function(name, params).await
+    pub(super) async fn call_function(&self, name: &str, params: &[Value]) -> Result<Value> {
+        if let Some(builtin) = builtin(self.ruleset, name) {
+            return builtin(self.ruleset, params);
+        }
+
+        self.budget.call()?;
+        self.interrupt
+            .run(self.ruleset.call_function(name, params))
+            .await
     }
 
-    pub(super) fn get_reference(&self, name: &str) -> Result<Value> {
-        self.scope
-            .get(name)
-            .cloned()
-            .ok_or_else(|| Error::UnknownRef(name.to_owned()))
-            .or_else(|_| {
-                match self.facts {
-                    value if name == "facts" => Ok(value),
-                    Value::Map(facts) => facts
-                        .get(name)
-                        .ok_or_else(|| Error::UnknownRef(name.to_owned())),
-                    _ => Err(Error::InvalidType),
-                }
-                .cloned()
-            })
+    /// Enter an expression, counts it against the limits and checks if
+    /// evaluation should stop. Every call has to be matched by a call to
+    /// `exit`
+    pub(super) fn enter(&self) -> Result<()> {
+        self.budget.enter()?;
+        self.interrupt.check()
+    }
+
+    pub(super) fn exit(&self) {
+        self.budget.exit()
+    }
+
+    pub(super) fn budget(&self) -> &Budget {
+        self.budget
+    }
+
+    /// Look up a reference in the scope first and then in the facts, the
+    /// value is borrowed so large facts are not copied
+    pub(super) fn get_reference(&self, name: &str) -> Result<&Value> {
+        match self.scope.as_ref().and_then(|scope| scope.get(name)) {
+            Some(value) => Ok(value),
+            None => fact(self.facts, name),
+        }
+    }
+
+    pub(super) fn numeric_policy(&self) -> NumericPolicy {
+        self.ruleset.numeric_policy()
+    }
+
+    pub(super) fn logic_policy(&self) -> LogicPolicy {
+        self.ruleset.logic_policy()
     }
 
     pub(super) fn get_symbol(&self, name: &str) -> Result<Value> {
@@ -56,17 +125,30 @@ impl EvalContext<'_> {
             .ok_or_else(|| Error::UnknownSymbol(name.to_owned()))
     }
 
-    pub(super) fn start_scope(
-        &self,
-        values: impl IntoIterator<Item = (impl ToString, Value)>,
-    ) -> EvalContext<'_> {
-        let mut new_scope = self.scope.clone();
-        new_scope.append(values);
-
+    /// Start a scope that binds a variable on top of the current scope
+    pub(super) fn start_scope<'b>(&'b self, name: &'b str, value: &'b Value) -> EvalContext<'b> {
         EvalContext {
             ruleset: self.ruleset,
             facts: self.facts,
-            scope: new_scope,
+            scope: Some(Binding {
+                name,
+                value,
+                parent: self.scope.as_ref(),
+            }),
+            failed: self.failed,
+            budget: self.budget,
+            interrupt: self.interrupt,
         }
     }
+
+    /// Remember the expression that failed, errors propagate up from the
+    /// innermost expression so only the first one is kept
+    pub(super) fn set_failed(&self, expr: &Expr) {
+        let _ = self.failed.compare_exchange(
+            null_mut(),
+            expr as *const Expr as *mut Expr,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
 }
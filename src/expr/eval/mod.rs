@@ -1,44 +1,106 @@
 //! Evaluate Expressions
 
+mod builtin;
 mod context;
+pub(crate) mod plan;
 
 use crate::{
+    blocking::block_on,
     error::{Error, Result},
     expr::{Expr, Index},
-    ruleset::RuleSet,
-    value::Value,
+    interrupt::Interrupt,
+    limits::{Budget, EvalLimits},
+    ruleset::{Rule, RuleSet},
+    value::{
+        logic::LogicPolicy,
+        numeric::{rank, NumericPolicy},
+        Value,
+    },
 };
 use async_recursion::async_recursion;
+use builtin::builtin;
 use chrono::{prelude::*, TimeDelta};
 use context::EvalContext;
+use plan::Plan;
 use rust_decimal::prelude::*;
-use std::collections::BTreeMap;
+use std::{
+    borrow::Cow, cmp::Ordering, collections::BTreeMap, ptr::null_mut, sync::atomic::AtomicPtr,
+};
 
 impl Expr {
     /// Evaluate the Expr, passing in a set of values
     pub async fn evaluate(&self, facts: &Value) -> Result<Value> {
-        self.eval_rec(&EvalContext::new(&EMPTY_RULES, facts)).await
+        let failed = AtomicPtr::new(null_mut());
+        let budget = Budget::new(EvalLimits::default());
+        let context = EvalContext::new(&EMPTY_RULES, facts, &failed, &budget, Interrupt::default());
+        self.eval_rec(&context).await
+    }
+
+    /// Evaluate the Expr without an async runtime, gives the same result as
+    /// `evaluate` using a compiled plan. Must not be called from inside an
+    /// async runtime, see `RuleSet::evaluate_blocking`
+    pub fn evaluate_blocking(&self, facts: &Value) -> Result<Value> {
+        block_on(Plan::compile(self).run(&EMPTY_RULES, facts, Interrupt::default()))
+            .map_err(|(error, _)| error)
     }
 
-    /// Evaluate the expression in the context of a rule
-    pub(crate) async fn eval_rule(&self, ruleset: &RuleSet, facts: &Value) -> Result<Value> {
-        self.eval_rec(&EvalContext::new(ruleset, facts)).await
+    /// Evaluate the expression of a rule in the context of a ruleset. When the
+    /// rule was parsed, errors are located at the expression that caused them
+    pub(crate) async fn eval_rule(
+        rule: &Rule,
+        ruleset: &RuleSet,
+        facts: &Value,
+        interrupt: Interrupt<'_>,
+    ) -> Result<Value> {
+        let failed = AtomicPtr::new(null_mut());
+        let budget = Budget::new(ruleset.limits());
+        let context = EvalContext::new(ruleset, facts, &failed, &budget, interrupt);
+        let result = rule.expr().eval_rec(&context).await;
+
+        result.map_err(|error| match rule.span_at(failed.into_inner()) {
+            Some(span) => Error::Located {
+                span,
+                error: Box::new(error),
+            },
+            None => error,
+        })
     }
 
-    /// Recursively evaluate an expression
+    /// Recursively evaluate an expression, remembers the first expression that
+    /// failed so errors can be located
     #[async_recursion]
     async fn eval_rec(&self, context: &EvalContext) -> Result<Value> {
+        let result = match context.enter() {
+            Ok(()) => self.eval_node(context).await.and_then(|value| {
+                if self.produces_value() {
+                    context.budget().check_size(&value)?;
+                }
+                Ok(value)
+            }),
+            Err(error) => Err(error),
+        };
+        context.exit();
+
+        if result.is_err() {
+            context.set_failed(self);
+        }
+
+        result
+    }
+
+    async fn eval_node(&self, context: &EvalContext<'_>) -> Result<Value> {
         match self {
             Expr::Value(value) => Ok(value.clone()),
-            Expr::Reference(name) => context.get_reference(name),
+            Expr::Reference(_) => self.eval_path(context).cloned(),
             Expr::Symbol(name) => context.get_symbol(name),
+            Expr::Index(_, _) if self.is_path() => self.eval_path(context).cloned(),
             Expr::Index(value, idx) => index(value.eval_rec(context).await?, idx),
-            Expr::Function(name, value) => {
-                let param = value.eval_rec(context).await?;
-                context.call_function(name, param).await
+            Expr::Function(name, params) => {
+                let params = eval_params(params, context).await?;
+                context.call_function(name, &params).await
             }
             Expr::If(switch, left, right) => iif(context, switch, left, right).await,
-            Expr::Not(value) => not(value.eval_rec(context).await?),
+            Expr::Not(value) => context.logic_policy().not(value.eval_rec(context).await?),
             Expr::Neg(value) => neg(value.eval_rec(context).await?),
             Expr::Some(value) => some(value.eval_rec(context).await?),
             Expr::None(value) => none(value.eval_rec(context).await?),
@@ -49,43 +111,32 @@ impl Expr {
             Expr::Int(value) => int(value.eval_rec(context).await?),
             Expr::Float(value) => float(value.eval_rec(context).await?),
             Expr::Dec(value) => dec(value.eval_rec(context).await?),
-            Expr::Mult(left, right) => mult(
-                left.eval_rec(context).await?,
-                right.eval_rec(context).await?,
-            ),
-            Expr::Div(left, right) => div(
-                left.eval_rec(context).await?,
-                right.eval_rec(context).await?,
-            ),
-            Expr::Rem(left, right) => rem(
-                left.eval_rec(context).await?,
-                right.eval_rec(context).await?,
-            ),
-            Expr::Add(left, right) => add(
-                left.eval_rec(context).await?,
-                right.eval_rec(context).await?,
-            ),
-            Expr::Sub(left, right) => sub(
-                left.eval_rec(context).await?,
-                right.eval_rec(context).await?,
-            ),
+            Expr::Mult(left, right) => numeric(context, "*", mult, left, right).await,
+            Expr::Div(left, right) => numeric(context, "/", div, left, right).await,
+            Expr::Rem(left, right) => numeric(context, "%", rem, left, right).await,
+            Expr::Add(left, right) => numeric(context, "+", add, left, right).await,
+            Expr::Sub(left, right) => numeric(context, "-", sub, left, right).await,
             Expr::Equals(left, right) => eq(context, left, right).await.map(Value::Bool),
             Expr::NotEquals(left, right) => {
                 eq(context, left, right).await.map(|val| Value::Bool(!val))
             }
             Expr::GreaterThan(left, right) => gt(
+                context.numeric_policy(),
                 left.eval_rec(context).await?,
                 right.eval_rec(context).await?,
             ),
             Expr::GreaterThanEquals(left, right) => gte(
+                context.numeric_policy(),
                 left.eval_rec(context).await?,
                 right.eval_rec(context).await?,
             ),
             Expr::LessThan(left, right) => lt(
+                context.numeric_policy(),
                 left.eval_rec(context).await?,
                 right.eval_rec(context).await?,
             ),
             Expr::LessThanEquals(left, right) => lte(
+                context.numeric_policy(),
                 left.eval_rec(context).await?,
                 right.eval_rec(context).await?,
             ),
@@ -99,13 +150,15 @@ impl Expr {
                 left.eval_rec(context).await?,
                 right.eval_rec(context).await?,
             ),
-            Expr::BitXor(left, right) => bitwise_xor(
+            Expr::BitXor(left, right) => context.logic_policy().xor(
                 left.eval_rec(context).await?,
                 right.eval_rec(context).await?,
             ),
-            Expr::Contains(coll, item) => {
-                contains(coll.eval_rec(context).await?, item.eval_rec(context).await?)
-            }
+            Expr::Contains(coll, item) => contains(
+                context.numeric_policy(),
+                coll.eval_rec(context).await?,
+                item.eval_rec(context).await?,
+            ),
             Expr::Starts(string, substr) => starts(
                 string.eval_rec(context).await?,
                 substr.eval_rec(context).await?,
@@ -130,11 +183,113 @@ impl Expr {
             Expr::Any(inner) => any(inner.eval_rec(context).await?),
             Expr::All(inner) => all(inner.eval_rec(context).await?),
             Expr::ForMap(bind, list_expr, operation) => {
-                for_map(context, bind, list_expr.eval_rec(context).await?, operation).await
+                for_map(context, bind, list_expr.eval_cow(context).await?, operation).await
             }
             Expr::ForFilter(bind, list_expr, predicate) => {
-                for_filter(context, bind, list_expr.eval_rec(context).await?, predicate).await
+                for_filter(context, bind, list_expr.eval_cow(context).await?, predicate).await
+            }
+            Expr::Coalesce(left, right) => match left.eval_rec(context).await? {
+                Value::None => right.eval_rec(context).await,
+                value => Ok(value),
+            },
+            Expr::StrictIndex(value, idx) => {
+                strict_index_ref(&*value.eval_cow(context).await?, idx).cloned()
+            }
+            Expr::Let(bind, value, body) => {
+                let value = value.eval_cow(context).await?;
+                body.eval_rec(&context.start_scope(bind, &value)).await
+            }
+            Expr::ForMapEntries(key, bind, list_expr, operation) => {
+                let list = list_expr.eval_cow(context).await?;
+                for_map_entries(context, key, bind, &list, operation).await
+            }
+            Expr::ForFilterEntries(key, bind, list_expr, predicate) => {
+                let list = list_expr.eval_cow(context).await?;
+                for_filter_entries(context, key, bind, &list, predicate).await
+            }
+            Expr::ForFold(bind, list_expr, acc, init, operation) => {
+                let list = list_expr.eval_cow(context).await?;
+                for_fold(context, bind, &list, acc, init, operation).await
+            }
+            Expr::ForSortBy(bind, list_expr, key) => {
+                let list = list_expr.eval_cow(context).await?;
+                for_sort_by(context, bind, &list, key).await
+            }
+            Expr::Slice(value, start, end) => {
+                let value = value.eval_rec(context).await?;
+                let start = match start {
+                    Some(start) => Some(start.eval_rec(context).await?),
+                    None => None,
+                };
+                let end = match end {
+                    Some(end) => Some(end.eval_rec(context).await?),
+                    None => None,
+                };
+                slice(value, start, end)
+            }
+        }
+    }
+
+    /// Evaluate an expression, borrowing the result from the facts or scope
+    /// when the expression is a reference with optional indexes
+    async fn eval_cow<'c>(&self, context: &'c EvalContext<'_>) -> Result<Cow<'c, Value>> {
+        if !self.is_path() {
+            return self.eval_rec(context).await.map(Cow::Owned);
+        }
+
+        let result = match context.enter() {
+            Ok(()) => self.eval_path(context).map(Cow::Borrowed),
+            Err(error) => {
+                context.set_failed(self);
+                Err(error)
             }
+        };
+        context.exit();
+
+        result
+    }
+
+    /// Evaluate a reference and the indexes applied to it without copying any
+    /// values, see `is_path`
+    fn eval_path<'c>(&self, context: &'c EvalContext<'_>) -> Result<&'c Value> {
+        let result = match self {
+            Expr::Reference(name) => context.get_reference(name),
+            Expr::Index(value, idx) => value
+                .eval_path(context)
+                .and_then(|value| index_ref(value, idx)),
+            _ => unreachable!("only references and indexes are paths"),
+        };
+
+        if result.is_err() {
+            context.set_failed(self);
+        }
+
+        result
+    }
+
+    /// Does evaluating the expression produce a new value that is checked
+    /// against the size limits. Constants, symbols and values read from the
+    /// facts are not produced by the rule
+    fn produces_value(&self) -> bool {
+        !matches!(
+            self,
+            Expr::Value(_)
+                | Expr::Reference(_)
+                | Expr::Index(_, _)
+                | Expr::Symbol(_)
+                | Expr::If(..)
+                | Expr::Coalesce(..)
+                | Expr::StrictIndex(..)
+                | Expr::Let(..)
+        )
+    }
+
+    /// A path is a reference followed by any number of indexes, like `a.b.0`
+    fn is_path(&self) -> bool {
+        match self {
+            Expr::Reference(_) => true,
+            Expr::Index(value, _) => value.is_path(),
+            _ => false,
         }
     }
 }
@@ -145,43 +300,106 @@ lazy_static::lazy_static!(
     };
 );
 
-fn index(value: Value, index: &Index) -> Result<Value> {
-    match (&value, index) {
-        (Value::Map(map), Index::Map(field)) => Ok(map.get(field).cloned().unwrap_or(Value::None)),
-        (Value::Vec(vec), Index::Vec(index)) => Ok(vec.get(*index).cloned().unwrap_or(Value::None)),
-        (Value::None, _) => Ok(Value::None),
-        (_, _) => Err(Error::InvalidType),
+/// Look up a reference in the facts, `facts` refers to the facts themselves
+fn fact<'a>(facts: &'a Value, name: &str) -> Result<&'a Value> {
+    match facts {
+        value if name == "facts" => Ok(value),
+        Value::Map(facts) => facts
+            .get(name)
+            .ok_or_else(|| Error::UnknownRef(name.to_owned())),
+        facts => Err(Error::invalid_type("field reference", [facts])),
     }
 }
 
-async fn iif(context: &EvalContext<'_>, switch: &Expr, left: &Expr, right: &Expr) -> Result<Value> {
-    match switch.eval_rec(context).await? {
-        Value::Bool(true) => left.eval_rec(context).await,
-        Value::Bool(false) => right.eval_rec(context).await,
-        _ => Err(Error::InvalidType),
+/// Index a value, moves the selected item out of the collection
+fn index(value: Value, index: &Index) -> Result<Value> {
+    match (value, index) {
+        (Value::Map(mut map), Index::Map(field)) => Ok(map.remove(field).unwrap_or(Value::None)),
+        (Value::Vec(mut vec), Index::Vec(index)) if *index < vec.len() => {
+            Ok(vec.swap_remove(*index))
+        }
+        (Value::Vec(_), Index::Vec(_)) | (Value::None, _) => Ok(Value::None),
+        (value, _) => Err(Error::invalid_type(format!(".{index}"), [&value])),
     }
 }
 
-fn not(value: Value) -> Result<Value> {
-    match value {
-        Value::Bool(value) => Ok(Value::Bool(!value)),
+/// Take the items of a vec or the characters of a string from a start
+/// position up to an end position. Negative positions count from the end,
+/// positions that are missing or `None` select from the start or up to the end
+fn slice(value: Value, start: Option<Value>, end: Option<Value>) -> Result<Value> {
+    let position = |bound: Option<Value>, len: usize, default: usize| match bound {
+        None | Some(Value::None) => Ok(default),
+        Some(Value::Int(position)) if position < 0 => Ok(usize::try_from(position.unsigned_abs())
+            .map_or(0, |position| len.saturating_sub(position))),
+        Some(Value::Int(position)) => {
+            Ok(usize::try_from(position).map_or(len, |position| position.min(len)))
+        }
+        Some(bound) => Err(Error::invalid_type("[..]", [&bound])),
+    };
+    let range = |len: usize| -> Result<std::ops::Range<usize>> {
+        let start = position(start, len, 0)?;
+        let end = position(end, len, len)?;
+        Ok(start..end.max(start))
+    };
 
+    match value {
+        Value::Vec(mut vec) => {
+            let range = range(vec.len())?;
+            Ok(Value::Vec(vec.drain(range).collect()))
+        }
+        Value::String(string) => {
+            let chars = string.chars().collect::<Vec<_>>();
+            let range = range(chars.len())?;
+            Ok(Value::String(chars[range].iter().collect()))
+        }
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("[..]", [&value])),
     }
 }
 
-fn neg(value: Value) -> Result<Value> {
-    match value {
-        Value::Int(value) => Ok(Value::Int(-value)),
-        Value::Float(value) => Ok(Value::Float(-value)),
-        Value::Decimal(value) => Ok(Value::Decimal(-value)),
+static NONE: Value = Value::None;
 
-        Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+/// Index a borrowed value without copying the selected item
+fn index_ref<'a>(value: &'a Value, index: &Index) -> Result<&'a Value> {
+    match (value, index) {
+        (Value::Map(map), Index::Map(field)) => Ok(map.get(field).unwrap_or(&NONE)),
+        (Value::Vec(vec), Index::Vec(index)) => Ok(vec.get(*index).unwrap_or(&NONE)),
+        (Value::None, _) => Ok(&NONE),
+        (_, _) => Err(Error::invalid_type(format!(".{index}"), [value])),
+    }
+}
+
+/// Index a borrowed value, fails when the key or position does not exist
+fn strict_index_ref<'a>(value: &'a Value, index: &Index) -> Result<&'a Value> {
+    match (value, index) {
+        (Value::Map(map), Index::Map(field)) => map.get(field),
+        (Value::Vec(vec), Index::Vec(index)) => vec.get(*index),
+        (Value::None, _) => None,
+        (_, _) => return Err(Error::invalid_type(format!("!.{index}"), [value])),
+    }
+    .ok_or_else(|| Error::UnknownIndex(index.to_string()))
+}
+
+async fn iif(context: &EvalContext<'_>, switch: &Expr, left: &Expr, right: &Expr) -> Result<Value> {
+    match switch.eval_rec(context).await? {
+        Value::Bool(true) => left.eval_rec(context).await,
+        Value::Bool(false) => right.eval_rec(context).await,
+        switch => Err(Error::invalid_type("if", [&switch])),
     }
 }
 
+fn neg(value: Value) -> Result<Value> {
+    let result = match &value {
+        Value::Int(inner) => inner.checked_neg().map(Value::Int),
+        Value::Float(inner) => Some(Value::Float(-inner)),
+        Value::Decimal(inner) => Some(Value::Decimal(-inner)),
+
+        Value::None => Some(Value::None),
+        _ => return Err(Error::invalid_type("-", [&value])),
+    };
+    result.ok_or_else(|| Error::overflow("-", [&value]))
+}
+
 fn some(value: Value) -> Result<Value> {
     match value {
         Value::None => Ok(Value::Bool(false)),
@@ -206,18 +424,27 @@ async fn eval_map(map: &BTreeMap<String, Expr>, context: &EvalContext<'_>) -> Re
     Ok(result.into())
 }
 
-async fn eval_vec(vec: &Vec<Expr>, context: &EvalContext<'_>) -> Result<Value> {
-    let mut result = Vec::<Value>::new();
-    for expr in vec {
+async fn eval_vec(vec: &[Expr], context: &EvalContext<'_>) -> Result<Value> {
+    Ok(eval_params(vec, context).await?.into())
+}
+
+/// Evaluate a list of expressions in order
+async fn eval_params(exprs: &[Expr], context: &EvalContext<'_>) -> Result<Vec<Value>> {
+    let mut result = Vec::<Value>::with_capacity(exprs.len());
+    for expr in exprs {
         result.push(expr.eval_rec(context).await?)
     }
-    Ok(result.into())
+    Ok(result)
 }
 
 fn int(value: Value) -> Result<Value> {
     match value.clone() {
         Value::Int(_) => Ok(value),
-        Value::Float(val) => Ok((val as i128).into()),
+        Value::Float(val) if val.is_nan() => Err(Error::invalid_cast(value, "Value::Int")),
+        Value::Float(val) => val
+            .to_i128()
+            .ok_or_else(|| Error::overflow("int", [&value]))
+            .map(Value::Int),
         Value::Decimal(val) => val
             .to_i128()
             .ok_or_else(|| Error::invalid_cast(value, "Value::Int"))
@@ -227,7 +454,7 @@ fn int(value: Value) -> Result<Value> {
             .map_err(|_| Error::invalid_cast(value, "Value::Int")),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("int", [&value])),
     }
 }
 
@@ -244,13 +471,15 @@ fn float(value: Value) -> Result<Value> {
             .map_err(|_| Error::invalid_cast(value, "Value::Float")),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("float", [&value])),
     }
 }
 
 fn dec(value: Value) -> Result<Value> {
     match value.clone() {
-        Value::Int(val) => Ok(Value::Decimal(val.into())),
+        Value::Int(val) => Decimal::from_i128(val)
+            .map(Value::Decimal)
+            .ok_or_else(|| Error::overflow("dec", [&value])),
         Value::Float(val) => Decimal::try_from(val)
             .map(Value::Decimal)
             .map_err(|_| Error::invalid_cast(value, "Value::Float")),
@@ -260,7 +489,7 @@ fn dec(value: Value) -> Result<Value> {
             .map_err(|_| Error::invalid_cast(value, "Value::Decimal")),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("dec", [&value])),
     }
 }
 
@@ -270,189 +499,224 @@ fn datetime(value: Value) -> Result<Value> {
             .parse::<DateTime<Utc>>()
             .map(Value::DateTime)
             .map_err(|_| Error::invalid_cast(value, "Value::DateTime")),
-        Value::Int(val) => DateTime::from_timestamp(val as i64, 0)
+        Value::Int(val) => i64::try_from(val)
+            .ok()
+            .and_then(|val| DateTime::from_timestamp(val, 0))
             .map(Value::DateTime)
             .ok_or(Error::invalid_cast(value, "Value::DateTime")),
         Value::DateTime(_) => Ok(value),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("datetime", [&value])),
     }
 }
 
 fn duration(value: Value) -> Result<Value> {
     match value.clone() {
-        Value::Int(val) => TimeDelta::try_seconds(val as i64)
+        Value::Int(val) => i64::try_from(val)
+            .ok()
+            .and_then(TimeDelta::try_seconds)
             .map(Value::Duration)
             .ok_or(Error::invalid_cast(value, "Value::Duration")),
         Value::Duration(_) => Ok(value),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("duration", [&value])),
     }
 }
 
 fn mult(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => Ok(Value::Int(left * right)),
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Float(left * right)),
-        (Value::Decimal(left), Value::Decimal(right)) => Ok(Value::Decimal(left * right)),
+    let result = match (&left, &right) {
+        (Value::Int(l), Value::Int(r)) => l.checked_mul(*r).map(Value::Int),
+        (Value::Float(l), Value::Float(r)) => Some(Value::Float(l * r)),
+        (Value::Decimal(l), Value::Decimal(r)) => l.checked_mul(*r).map(Value::Decimal),
 
-        (Value::None, _) | (_, Value::None) => Ok(Value::None),
-        _ => Err(Error::InvalidType),
-    }
+        (Value::None, _) | (_, Value::None) => Some(Value::None),
+        _ => return Err(Error::invalid_type("*", [&left, &right])),
+    };
+    result.ok_or_else(|| Error::overflow("*", [&left, &right]))
 }
 
 fn div(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => match left.checked_div(right) {
-            Some(result) => Ok(Value::Int(result)),
-            None => Err(Error::DivisionByZero),
-        },
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Float(left / right)),
-        (Value::Decimal(left), Value::Decimal(right)) => match left.checked_div(right) {
-            Some(result) => Ok(Value::Decimal(result)),
-            None => Err(Error::DivisionByZero),
-        },
-        (Value::None, _) | (_, Value::None) => Ok(Value::None),
-        _ => Err(Error::InvalidType),
-    }
+    let result = match (&left, &right) {
+        (Value::Int(_), Value::Int(0)) => return Err(Error::DivisionByZero),
+        (Value::Int(l), Value::Int(r)) => l.checked_div(*r).map(Value::Int),
+        (Value::Float(l), Value::Float(r)) => Some(Value::Float(l / r)),
+        (Value::Decimal(_), Value::Decimal(r)) if r.is_zero() => return Err(Error::DivisionByZero),
+        (Value::Decimal(l), Value::Decimal(r)) => l.checked_div(*r).map(Value::Decimal),
+
+        (Value::None, _) | (_, Value::None) => Some(Value::None),
+        _ => return Err(Error::invalid_type("/", [&left, &right])),
+    };
+    result.ok_or_else(|| Error::overflow("/", [&left, &right]))
 }
 
 fn rem(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => match left.checked_rem(right) {
-            Some(result) => Ok(Value::Int(result)),
-            None => Err(Error::DivisionByZero),
-        },
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Float(left % right)),
-        (Value::Decimal(left), Value::Decimal(right)) => match left.checked_rem(right) {
-            Some(result) => Ok(Value::Decimal(result)),
-            None => Err(Error::DivisionByZero),
-        },
-        (Value::None, _) | (_, Value::None) => Ok(Value::None),
-        _ => Err(Error::InvalidType),
-    }
+    let result = match (&left, &right) {
+        (Value::Int(_), Value::Int(0)) => return Err(Error::DivisionByZero),
+        (Value::Int(l), Value::Int(r)) => l.checked_rem(*r).map(Value::Int),
+        (Value::Float(l), Value::Float(r)) => Some(Value::Float(l % r)),
+        (Value::Decimal(_), Value::Decimal(r)) if r.is_zero() => return Err(Error::DivisionByZero),
+        (Value::Decimal(l), Value::Decimal(r)) => l.checked_rem(*r).map(Value::Decimal),
+
+        (Value::None, _) | (_, Value::None) => Some(Value::None),
+        _ => return Err(Error::invalid_type("%", [&left, &right])),
+    };
+    result.ok_or_else(|| Error::overflow("%", [&left, &right]))
 }
 
 fn add(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => Ok(Value::Int(left + right)),
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Float(left + right)),
-        (Value::Decimal(left), Value::Decimal(right)) => Ok(Value::Decimal(left + right)),
-        (Value::DateTime(left), Value::Duration(right)) => Ok(Value::DateTime(left + right)),
-
-        (Value::None, _) | (_, Value::None) => Ok(Value::None),
-        _ => Err(Error::InvalidType),
-    }
+    let result = match (&left, &right) {
+        (Value::Int(l), Value::Int(r)) => l.checked_add(*r).map(Value::Int),
+        (Value::Float(l), Value::Float(r)) => Some(Value::Float(l + r)),
+        (Value::Decimal(l), Value::Decimal(r)) => l.checked_add(*r).map(Value::Decimal),
+        (Value::DateTime(l), Value::Duration(r)) => l.checked_add_signed(*r).map(Value::DateTime),
+        (Value::Duration(l), Value::Duration(r)) => l.checked_add(r).map(Value::Duration),
+        (Value::Vec(l), Value::Vec(r)) => Some(Value::Vec(l.iter().chain(r).cloned().collect())),
+
+        (Value::None, _) | (_, Value::None) => Some(Value::None),
+        _ => return Err(Error::invalid_type("+", [&left, &right])),
+    };
+    result.ok_or_else(|| Error::overflow("+", [&left, &right]))
 }
 
 fn sub(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => Ok(Value::Int(left - right)),
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Float(left - right)),
-        (Value::Decimal(left), Value::Decimal(right)) => Ok(Value::Decimal(left - right)),
-        (Value::DateTime(left), Value::DateTime(right)) => Ok(Value::Duration(left - right)),
-        (Value::DateTime(left), Value::Duration(right)) => Ok(Value::DateTime(left - right)),
-        (Value::Duration(left), Value::Duration(right)) => Ok(Value::Duration(left - right)),
+    let result = match (&left, &right) {
+        (Value::Int(l), Value::Int(r)) => l.checked_sub(*r).map(Value::Int),
+        (Value::Float(l), Value::Float(r)) => Some(Value::Float(l - r)),
+        (Value::Decimal(l), Value::Decimal(r)) => l.checked_sub(*r).map(Value::Decimal),
+        (Value::DateTime(l), Value::DateTime(r)) => Some(Value::Duration(*l - *r)),
+        (Value::DateTime(l), Value::Duration(r)) => l.checked_sub_signed(*r).map(Value::DateTime),
+        (Value::Duration(l), Value::Duration(r)) => l.checked_sub(r).map(Value::Duration),
+
+        (Value::None, _) | (_, Value::None) => Some(Value::None),
+        _ => return Err(Error::invalid_type("-", [&left, &right])),
+    };
+    result.ok_or_else(|| Error::overflow("-", [&left, &right]))
+}
 
-        (Value::None, _) | (_, Value::None) => Ok(Value::None),
-        _ => Err(Error::InvalidType),
-    }
+/// Evaluate an arithmetic or comparison operator, numbers of different types
+/// are promoted to a common type first
+async fn numeric(
+    context: &EvalContext<'_>,
+    operation: &str,
+    function: fn(Value, Value) -> Result<Value>,
+    left: &Expr,
+    right: &Expr,
+) -> Result<Value> {
+    let left = left.eval_rec(context).await?;
+    let right = right.eval_rec(context).await?;
+    let (left, right) = context.numeric_policy().promote(operation, left, right)?;
+
+    function(left, right)
 }
 
 async fn eq(context: &EvalContext<'_>, left: &Expr, right: &Expr) -> Result<bool> {
-    let left = left.eval_rec(context).await?;
+    let left = left.eval_cow(context).await?;
 
-    if left == Value::None {
+    if *left == Value::None {
         // Nothing equals Value::None, not even Value::None, so early return
         return Ok(false);
     }
 
-    let right = right.eval_rec(context).await?;
+    let right = right.eval_cow(context).await?;
 
-    Ok(left == right)
+    Ok(context.numeric_policy().equals(&left, &right))
 }
 
-fn gt(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => Ok(Value::Bool(left > right)),
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Bool(left > right)),
-        (Value::Decimal(left), Value::Decimal(right)) => Ok(Value::Bool(left > right)),
-        (Value::DateTime(left), Value::DateTime(right)) => Ok(Value::Bool(left > right)),
-        (Value::Duration(left), Value::Duration(right)) => Ok(Value::Bool(left > right)),
-
-        (Value::None, _) | (_, Value::None) => Ok(false.into()),
-        _ => Err(Error::InvalidType),
-    }
+fn gt(policy: NumericPolicy, left: Value, right: Value) -> Result<Value> {
+    Ok(compare(policy, ">", &left, &right)?
+        .is_some_and(Ordering::is_gt)
+        .into())
 }
 
-fn gte(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => Ok(Value::Bool(left >= right)),
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Bool(left >= right)),
-        (Value::Decimal(left), Value::Decimal(right)) => Ok(Value::Bool(left >= right)),
-        (Value::DateTime(left), Value::DateTime(right)) => Ok(Value::Bool(left >= right)),
-        (Value::Duration(left), Value::Duration(right)) => Ok(Value::Bool(left >= right)),
+fn gte(policy: NumericPolicy, left: Value, right: Value) -> Result<Value> {
+    Ok(compare(policy, ">=", &left, &right)?
+        .is_some_and(Ordering::is_ge)
+        .into())
+}
 
-        (Value::None, _) | (_, Value::None) => Ok(false.into()),
-        _ => Err(Error::InvalidType),
-    }
+fn lt(policy: NumericPolicy, left: Value, right: Value) -> Result<Value> {
+    Ok(compare(policy, "<", &left, &right)?
+        .is_some_and(Ordering::is_lt)
+        .into())
 }
 
-fn lt(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => Ok(Value::Bool(left < right)),
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Bool(left < right)),
-        (Value::Decimal(left), Value::Decimal(right)) => Ok(Value::Bool(left < right)),
-        (Value::DateTime(left), Value::DateTime(right)) => Ok(Value::Bool(left < right)),
-        (Value::Duration(left), Value::Duration(right)) => Ok(Value::Bool(left < right)),
+fn lte(policy: NumericPolicy, left: Value, right: Value) -> Result<Value> {
+    Ok(compare(policy, "<=", &left, &right)?
+        .is_some_and(Ordering::is_le)
+        .into())
+}
 
-        (Value::None, _) | (_, Value::None) => Ok(false.into()),
-        _ => Err(Error::InvalidType),
+/// Order two values for a comparison operator, values that can not be ordered
+/// like `none` and NaN floats give `None` so the comparison is false. Numbers
+/// of different types are promoted first, strings are ordered
+/// lexicographically, `false` is less than `true` and vecs are compared element
+/// by element
+fn compare(
+    policy: NumericPolicy,
+    operation: &str,
+    left: &Value,
+    right: &Value,
+) -> Result<Option<Ordering>> {
+    if let (Some(left_rank), Some(right_rank)) = (rank(left), rank(right)) {
+        if left_rank != right_rank && policy == NumericPolicy::Promote {
+            let (left, right) = policy.promote(operation, left.clone(), right.clone())?;
+            return compare(policy, operation, &left, &right);
+        }
     }
-}
 
-fn lte(left: Value, right: Value) -> Result<Value> {
     match (left, right) {
-        (Value::Int(left), Value::Int(right)) => Ok(Value::Bool(left <= right)),
-        (Value::Float(left), Value::Float(right)) => Ok(Value::Bool(left <= right)),
-        (Value::Decimal(left), Value::Decimal(right)) => Ok(Value::Bool(left <= right)),
-        (Value::DateTime(left), Value::DateTime(right)) => Ok(Value::Bool(left <= right)),
-        (Value::Duration(left), Value::Duration(right)) => Ok(Value::Bool(left <= right)),
+        (Value::Int(left), Value::Int(right)) => Ok(left.partial_cmp(right)),
+        (Value::Float(left), Value::Float(right)) => Ok(left.partial_cmp(right)),
+        (Value::Decimal(left), Value::Decimal(right)) => Ok(left.partial_cmp(right)),
+        (Value::DateTime(left), Value::DateTime(right)) => Ok(left.partial_cmp(right)),
+        (Value::Duration(left), Value::Duration(right)) => Ok(left.partial_cmp(right)),
+        (Value::String(left), Value::String(right)) => Ok(left.partial_cmp(right)),
+        (Value::Bool(left), Value::Bool(right)) => Ok(left.partial_cmp(right)),
+        (Value::Vec(left), Value::Vec(right)) => {
+            for (left, right) in left.iter().zip(right) {
+                match compare(policy, operation, left, right)? {
+                    Some(Ordering::Equal) => continue,
+                    ordering => return Ok(ordering),
+                }
+            }
+            Ok(left.len().partial_cmp(&right.len()))
+        }
 
-        (Value::None, _) | (_, Value::None) => Ok(false.into()),
-        _ => Err(Error::InvalidType),
+        (Value::None, _) | (_, Value::None) => Ok(None),
+        (left, right) => Err(Error::invalid_type(operation, [left, right])),
     }
 }
 
 /// Lazilly evaluate an and expression
 async fn and(context: &EvalContext<'_>, left: &Expr, right: &Expr) -> Result<Value> {
-    Ok(if !eval_to_bool(context, left).await? {
-        // If left evaluates to false bypass right and return false immediately
-        false
-    } else {
-        // If left evaluates to true return the result of evaluating right
-        eval_to_bool(context, right).await?
-    }
-    .into())
+    logic(context, "and", false, left, right).await
 }
 
 /// Lazilly evaluate an or expression
 async fn or(context: &EvalContext<'_>, left: &Expr, right: &Expr) -> Result<Value> {
-    Ok(if eval_to_bool(context, left).await? {
-        // If left evaluates to true bypass right and return true immediately
-        true
-    } else {
-        // If left evaluates to false return the result of evaluating right
-        eval_to_bool(context, right).await?
-    }
-    .into())
+    logic(context, "or", true, left, right).await
 }
 
-/// Helper function that evaluates an expression and checks if its a boolean
-async fn eval_to_bool(context: &EvalContext<'_>, expr: &Expr) -> Result<bool> {
-    TryInto::<bool>::try_into(expr.eval_rec(context).await?).map_err(|_| Error::InvalidType)
+/// Evaluate the left side of an `and` or `or` and bypass the right side when
+/// the left side evaluates to the `decisive` value that decides the result
+async fn logic(
+    context: &EvalContext<'_>,
+    operation: &str,
+    decisive: bool,
+    left: &Expr,
+    right: &Expr,
+) -> Result<Value> {
+    let policy = context.logic_policy();
+
+    let left = policy.truth(operation, &left.eval_rec(context).await?)?;
+    if left == Some(decisive) {
+        return Ok(Value::Bool(decisive));
+    }
+
+    let right = policy.truth(operation, &right.eval_rec(context).await?)?;
+    Ok(LogicPolicy::connect(decisive, left, right))
 }
 
 fn bitwise_and(left: Value, right: Value) -> Result<Value> {
@@ -461,7 +725,7 @@ fn bitwise_and(left: Value, right: Value) -> Result<Value> {
         (Value::Bool(left), Value::Bool(right)) => Ok(Value::Bool(left & right)),
 
         (Value::None, _) | (_, Value::None) => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        (left, right) => Err(Error::invalid_type("&", [&left, &right])),
     }
 }
 
@@ -471,29 +735,21 @@ fn bitwise_or(left: Value, right: Value) -> Result<Value> {
         (Value::Bool(left), Value::Bool(right)) => Ok(Value::Bool(left | right)),
 
         (Value::None, _) | (_, Value::None) => Ok(Value::None),
-        _ => Err(Error::InvalidType),
-    }
-}
-
-fn bitwise_xor(left: Value, right: Value) -> Result<Value> {
-    match (left, right) {
-        (Value::Int(left), Value::Int(right)) => Ok(Value::Int(left ^ right)),
-        (Value::Bool(left), Value::Bool(right)) => Ok(Value::Bool(left ^ right)),
-
-        (Value::None, _) | (_, Value::None) => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        (left, right) => Err(Error::invalid_type("|", [&left, &right])),
     }
 }
 
-fn contains(coll: Value, item: Value) -> Result<Value> {
+fn contains(policy: NumericPolicy, coll: Value, item: Value) -> Result<Value> {
     match (coll, item) {
         (Value::Map(map), Value::String(key)) => Ok(Value::Bool(map.contains_key(&key))),
-        (Value::Vec(vec), item) => Ok(Value::Bool(vec.contains(&item))),
+        (Value::Vec(vec), item) => Ok(Value::Bool(
+            vec.iter().any(|value| policy.equals(value, &item)),
+        )),
         (Value::String(coll), Value::String(item)) => Ok(Value::Bool(coll.contains(&item))),
         (Value::Int(flags), Value::Int(flag)) => Ok(Value::Bool((flags & flag) != 0)),
 
         (Value::None, _) => Ok(Value::Bool(false)),
-        _ => Err(Error::InvalidType),
+        (coll, item) => Err(Error::invalid_type("contains", [&coll, &item])),
     }
 }
 
@@ -504,7 +760,7 @@ fn starts(string: Value, substr: Value) -> Result<Value> {
         }
 
         (Value::None, _) => Ok(Value::Bool(false)),
-        _ => Err(Error::InvalidType),
+        (string, substr) => Err(Error::invalid_type("starts", [&string, &substr])),
     }
 }
 
@@ -515,7 +771,7 @@ fn ends(string: Value, substr: Value) -> Result<Value> {
         }
 
         (Value::None, _) => Ok(Value::Bool(false)),
-        _ => Err(Error::InvalidType),
+        (string, substr) => Err(Error::invalid_type("ends", [&string, &substr])),
     }
 }
 
@@ -524,7 +780,7 @@ fn uppercase(value: Value) -> Result<Value> {
         Value::String(value) => Ok(Value::String(value.to_uppercase())),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("uppercase", [&value])),
     }
 }
 
@@ -533,7 +789,7 @@ fn lowercase(value: Value) -> Result<Value> {
         Value::String(value) => Ok(Value::String(value.to_lowercase())),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("lowercase", [&value])),
     }
 }
 
@@ -541,7 +797,7 @@ fn trim(value: Value) -> Result<Value> {
     match value {
         Value::String(inner) => Ok(Value::String(inner.trim().to_string())),
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("trim", [&value])),
     }
 }
 
@@ -551,7 +807,7 @@ fn floor(value: Value) -> Result<Value> {
         Value::Decimal(inner) => Ok(Value::Decimal(inner.floor())),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("floor", [&value])),
     }
 }
 
@@ -561,7 +817,7 @@ fn round(value: Value) -> Result<Value> {
         Value::Decimal(inner) => Ok(Value::Decimal(inner.round())),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("round", [&value])),
     }
 }
 
@@ -571,7 +827,7 @@ fn fract(value: Value) -> Result<Value> {
         Value::Decimal(inner) => Ok(Value::Decimal(inner.fract())),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("fract", [&value])),
     }
 }
 
@@ -580,7 +836,7 @@ fn year(value: Value) -> Result<Value> {
         Value::DateTime(value) => Ok(Value::Int(value.year() as i128)),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("year", [&value])),
     }
 }
 
@@ -589,71 +845,81 @@ fn month(value: Value) -> Result<Value> {
         Value::DateTime(inner) => Ok(Value::Int(inner.month() as i128)),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("month", [&value])),
     }
 }
 
 fn week(value: Value) -> Result<Value> {
     match &value {
-        Value::Int(inner) => TimeDelta::try_weeks(*inner as i64)
+        Value::Int(inner) => i64::try_from(*inner)
+            .ok()
+            .and_then(TimeDelta::try_weeks)
             .map(Value::Duration)
             .ok_or(Error::value_out_of_bounds(value, "week")),
         Value::Duration(value) => Ok(Value::Int(value.num_weeks() as i128)),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("week", [&value])),
     }
 }
 
 fn day(value: Value) -> Result<Value> {
     match &value {
-        Value::Int(inner) => TimeDelta::try_days(*inner as i64)
+        Value::Int(inner) => i64::try_from(*inner)
+            .ok()
+            .and_then(TimeDelta::try_days)
             .map(Value::Duration)
             .ok_or(Error::value_out_of_bounds(value, "day")),
         Value::DateTime(inner) => Ok(Value::Int(inner.day() as i128)),
         Value::Duration(inner) => Ok(Value::Int(inner.num_days() as i128)),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("day", [&value])),
     }
 }
 
 fn hour(value: Value) -> Result<Value> {
     match &value {
-        Value::Int(inner) => TimeDelta::try_hours(*inner as i64)
+        Value::Int(inner) => i64::try_from(*inner)
+            .ok()
+            .and_then(TimeDelta::try_hours)
             .map(Value::Duration)
             .ok_or(Error::value_out_of_bounds(value, "hour")),
         Value::DateTime(inner) => Ok(Value::Int(inner.hour() as i128)),
         Value::Duration(inner) => Ok(Value::Int(inner.num_hours() as i128)),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("hour", [&value])),
     }
 }
 
 fn minute(value: Value) -> Result<Value> {
     match &value {
-        Value::Int(inner) => TimeDelta::try_minutes(*inner as i64)
+        Value::Int(inner) => i64::try_from(*inner)
+            .ok()
+            .and_then(TimeDelta::try_minutes)
             .map(Value::Duration)
             .ok_or(Error::value_out_of_bounds(value, "minute")),
         Value::DateTime(inner) => Ok(Value::Int(inner.minute() as i128)),
         Value::Duration(inner) => Ok(Value::Int(inner.num_minutes() as i128)),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("minute", [&value])),
     }
 }
 
 fn second(value: Value) -> Result<Value> {
     match &value {
-        Value::Int(inner) => TimeDelta::try_seconds(*inner as i64)
+        Value::Int(inner) => i64::try_from(*inner)
+            .ok()
+            .and_then(TimeDelta::try_seconds)
             .map(Value::Duration)
             .ok_or(Error::value_out_of_bounds(value, "second")),
         Value::DateTime(inner) => Ok(Value::Int(inner.second() as i128)),
         Value::Duration(inner) => Ok(Value::Int(inner.num_seconds() as i128)),
 
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        _ => Err(Error::invalid_type("second", [&value])),
     }
 }
 
@@ -664,13 +930,13 @@ fn any(value: Value) -> Result<Value> {
                 match item {
                     Value::Bool(true) => return Ok(Value::Bool(true)),
                     Value::Bool(false) => continue,
-                    _ => return Err(Error::InvalidType),
+                    item => return Err(Error::invalid_type("any", [&item])),
                 }
             }
             Ok(Value::Bool(false))
         }
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("any", [&value])),
     }
 }
 
@@ -681,67 +947,71 @@ fn all(value: Value) -> Result<Value> {
                 match item {
                     Value::Bool(true) => continue,
                     Value::Bool(false) => return Ok(Value::Bool(false)),
-                    _ => return Err(Error::InvalidType),
+                    item => return Err(Error::invalid_type("all", [&item])),
                 }
             }
             Ok(Value::Bool(true))
         }
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        value => Err(Error::invalid_type("all", [&value])),
     }
 }
 
 async fn for_map(
     context: &EvalContext<'_>,
     bind: &str,
-    list: Value,
+    list: Cow<'_, Value>,
     operation: &Expr,
 ) -> Result<Value> {
-    match list {
+    match list.as_ref() {
         Value::Vec(vec) => {
-            let mut result = Vec::<Value>::new();
+            let mut result = Vec::<Value>::with_capacity(vec.len());
+            let mut len = 0;
 
             for item in vec {
-                let scope = context.start_scope([(&bind, item.clone())]);
-
-                let mapped_value = operation.eval_rec(&scope).await?;
-                result.push(mapped_value);
+                let scope = context.start_scope(bind, item);
+                let value = operation.eval_rec(&scope).await?;
+                context.budget().collect(&mut len, &value)?;
+                result.push(value);
             }
 
             Ok(Value::Vec(result))
         }
         Value::Map(map) => {
             let mut result = BTreeMap::<String, Value>::new();
+            let mut len = 0;
 
             for (key, item) in map {
-                let scope = context.start_scope([(&bind, item.clone())]);
-
-                let mapped_value = operation.eval_rec(&scope).await?;
-                result.insert(key.clone(), mapped_value);
+                let scope = context.start_scope(bind, item);
+                let value = operation.eval_rec(&scope).await?;
+                context.budget().collect(&mut len, &value)?;
+                result.insert(key.clone(), value);
             }
 
             Ok(Value::Map(result))
         }
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        list => Err(Error::invalid_type("for .. map", [list])),
     }
 }
 
 async fn for_filter(
     context: &EvalContext<'_>,
     bind: &str,
-    list: Value,
+    list: Cow<'_, Value>,
     predicate: &Expr,
 ) -> Result<Value> {
-    match list {
+    match list.as_ref() {
         Value::Vec(vec) => {
             let mut result = Vec::<Value>::new();
+            let mut len = 0;
 
             for item in vec {
-                let scope = context.start_scope([(&bind, item.clone())]);
+                let scope = context.start_scope(bind, item);
 
                 let pred_value = predicate.eval_rec(&scope).await?;
                 if let Value::Bool(true) = pred_value {
+                    context.budget().collect(&mut len, item)?;
                     result.push(item.clone());
                 }
             }
@@ -750,12 +1020,14 @@ async fn for_filter(
         }
         Value::Map(map) => {
             let mut result = BTreeMap::<String, Value>::new();
+            let mut len = 0;
 
             for (key, item) in map {
-                let scope = context.start_scope([(&bind, item.clone())]);
+                let scope = context.start_scope(bind, item);
 
                 let pred_value = predicate.eval_rec(&scope).await?;
                 if let Value::Bool(true) = pred_value {
+                    context.budget().collect(&mut len, item)?;
                     result.insert(key.clone(), item.clone());
                 }
             }
@@ -763,6 +1035,141 @@ async fn for_filter(
             Ok(Value::Map(result))
         }
         Value::None => Ok(Value::None),
-        _ => Err(Error::InvalidType),
+        list => Err(Error::invalid_type("for .. filter", [list])),
+    }
+}
+
+async fn for_fold(
+    context: &EvalContext<'_>,
+    bind: &str,
+    list: &Value,
+    acc: &str,
+    init: &Expr,
+    operation: &Expr,
+) -> Result<Value> {
+    let items: Vec<&Value> = match list {
+        Value::Vec(vec) => vec.iter().collect(),
+        Value::Map(map) => map.values().collect(),
+        Value::None => return Ok(Value::None),
+        list => return Err(Error::invalid_type("for .. fold", [list])),
+    };
+    let mut result = init.eval_rec(context).await?;
+
+    for item in items {
+        let scope = context.start_scope(acc, &result);
+        let scope = scope.start_scope(bind, item);
+        let next = operation.eval_rec(&scope).await?;
+        result = next;
+    }
+
+    Ok(result)
+}
+
+async fn for_sort_by(
+    context: &EvalContext<'_>,
+    bind: &str,
+    list: &Value,
+    key: &Expr,
+) -> Result<Value> {
+    let items: Vec<&Value> = match list {
+        Value::Vec(vec) => vec.iter().collect(),
+        Value::Map(map) => map.values().collect(),
+        Value::None => return Ok(Value::None),
+        list => return Err(Error::invalid_type("for .. sort_by", [list])),
+    };
+    let mut keyed = Vec::with_capacity(items.len());
+    let mut len = 0;
+
+    for item in items {
+        let scope = context.start_scope(bind, item);
+        keyed.push((key.eval_rec(&scope).await?, item.clone()));
+        context.budget().collect(&mut len, item)?;
+    }
+
+    Ok(Value::Vec(sort_by_keys(keyed)))
+}
+
+/// Sort items by their keys, items with equal keys keep their order
+fn sort_by_keys(mut keyed: Vec<(Value, Value)>) -> Vec<Value> {
+    keyed.sort_by(|(left, _), (right, _)| left.total_cmp(right));
+    keyed.into_iter().map(|(_, item)| item).collect()
+}
+
+/// The keys and values of a map or the positions and items of a vec, `None`
+/// for `Value::None`
+fn entries<'v>(operation: &str, list: &'v Value) -> Result<Option<Vec<(Value, &'v Value)>>> {
+    match list {
+        Value::Vec(vec) => Ok(Some(
+            vec.iter()
+                .enumerate()
+                .map(|(index, item)| (Value::Int(index as i128), item))
+                .collect(),
+        )),
+        Value::Map(map) => Ok(Some(
+            map.iter()
+                .map(|(key, item)| (Value::String(key.clone()), item))
+                .collect(),
+        )),
+        Value::None => Ok(None),
+        list => Err(Error::invalid_type(operation, [list])),
+    }
+}
+
+async fn for_map_entries(
+    context: &EvalContext<'_>,
+    key: &str,
+    bind: &str,
+    list: &Value,
+    operation: &Expr,
+) -> Result<Value> {
+    let Some(entries) = entries("for .. map", list)? else {
+        return Ok(Value::None);
+    };
+    let mut result = Vec::<Value>::with_capacity(entries.len());
+    let mut len = 0;
+
+    for (key_value, item) in entries {
+        let scope = context.start_scope(key, &key_value);
+        let scope = scope.start_scope(bind, item);
+        let value = operation.eval_rec(&scope).await?;
+        context.budget().collect(&mut len, &value)?;
+        result.push(value);
+    }
+
+    Ok(Value::Vec(result))
+}
+
+async fn for_filter_entries(
+    context: &EvalContext<'_>,
+    key: &str,
+    bind: &str,
+    list: &Value,
+    predicate: &Expr,
+) -> Result<Value> {
+    let Some(entries) = entries("for .. filter", list)? else {
+        return Ok(Value::None);
+    };
+    let mut vec = Vec::<Value>::new();
+    let mut map = BTreeMap::<String, Value>::new();
+    let mut len = 0;
+
+    for (key_value, item) in entries {
+        let scope = context.start_scope(key, &key_value);
+        let scope = scope.start_scope(bind, item);
+
+        if let Value::Bool(true) = predicate.eval_rec(&scope).await? {
+            context.budget().collect(&mut len, item)?;
+            match key_value {
+                Value::String(key) => {
+                    map.insert(key, item.clone());
+                }
+                _ => vec.push(item.clone()),
+            }
+        }
     }
+
+    Ok(match list {
+        Value::Map(_) => Value::Map(map),
+        _ => Value::Vec(vec),
+    })
 }
@@ -0,0 +1,1188 @@
+//! Compile expressions into a flat evaluation plan
+//!
+//! The plan is a sequence of instructions for a small stack machine. Fact
+//! references and the index expressions applied to them are resolved to paths
+//! once at compile time and walked by reference at run time so only the value
+//! that is finally selected gets cloned. Variables bound by `for` loops and
+//! `let` are stored in numbered slots instead of a scope map. Evaluating a
+//! plan is a single loop that only awaits when it calls a user-function.
+//!
+//! Every instruction that can fail remembers the position of the expression
+//! it was compiled from in `Expr::preorder` order so errors can be located at
+//! the same expression as the tree walking evaluator would.
+//!
+//! Each instruction also records the expressions the tree walking evaluator
+//! would enter before it runs, with their nesting depth. These are only
+//! counted when the ruleset limits evaluation steps or depth, or when the
+//! evaluation has a deadline that is checked at the same expressions.
+
+use super::*;
+use crate::limits::Budget;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// A compiled expression
+#[derive(Debug, Clone)]
+pub(crate) struct Plan {
+    ops: Vec<Op>,
+    /// Expressions entered before each instruction with their depth
+    entries: Vec<Vec<(Node, usize)>>,
+    slots: usize,
+}
+
+/// Position of an expression in `Expr::preorder` order
+type Node = usize;
+
+/// Position of an instruction in the plan
+type Target = usize;
+
+/// Indexes to apply to a reference with the expression each one came from
+type Path = Vec<(Index, Node)>;
+
+#[derive(Debug, Clone)]
+enum Op {
+    /// Push a constant
+    Const(Value),
+
+    /// Look up a reference and walk an index path from it, pushes a clone of
+    /// the value that was found
+    Load {
+        root: Root,
+        node: Node,
+        path: Path,
+    },
+
+    /// Index the value on top of the stack
+    Index(Index, Node),
+
+    /// Index the value on top of the stack, fails when the index is missing
+    StrictIndex(Index, Node),
+
+    /// Push the value of a symbol
+    Symbol(String, Node),
+
+    /// Call a user-function with the arguments on top of the stack
+    Call {
+        name: String,
+        args: usize,
+        node: Node,
+    },
+
+    /// Collect values from the top of the stack into a `Value::Vec`
+    Vec(usize, Node),
+
+    /// Collect values from the top of the stack into a `Value::Map` with the
+    /// given keys
+    Map(Vec<String>, Node),
+
+    Unary(fn(Value) -> Result<Value>, Node),
+
+    Binary(fn(Value, Value) -> Result<Value>, Node),
+
+    /// A binary operator that depends on the `NumericPolicy`
+    Policy(fn(NumericPolicy, Value, Value) -> Result<Value>, Node),
+
+    /// A binary operator on numbers, numbers of different types are promoted
+    /// to a common type first
+    Numeric(fn(Value, Value) -> Result<Value>, &'static str, Node),
+
+    Jump(Target),
+
+    /// Pop a boolean, continue when it is true and jump when it is false
+    Branch {
+        otherwise: Target,
+        node: Node,
+    },
+
+    /// Pop the left side of an `and` or `or`, when it is the decisive value
+    /// push it back and jump to skip the right side, otherwise push its truth
+    /// value
+    ShortCircuit {
+        operation: &'static str,
+        decisive: bool,
+        target: Target,
+        node: Node,
+    },
+
+    /// Pop both sides of an `and` or `or` and push their combined truth value
+    Connect {
+        operation: &'static str,
+        decisive: bool,
+        node: Node,
+    },
+
+    /// Logic or bitwise operators, these depend on the `LogicPolicy`
+    Not(Node),
+    Xor(Node),
+
+    /// Pop the value of a `let` into a slot
+    Bind(usize),
+
+    /// Keep the left side of a `??` and jump to skip the right side when it
+    /// is not `None`, otherwise pop it
+    Coalesce {
+        target: Target,
+    },
+
+    /// Nothing equals `Value::None`, when the left side of a comparison is
+    /// `None` replace it with the result and skip the right side
+    EqualsNone {
+        result: bool,
+        target: Target,
+    },
+
+    Equals {
+        negate: bool,
+    },
+
+    /// Pop a collection and start iterating over it, jumps to the target for
+    /// `None`. Collections in the facts are iterated in place instead
+    IterStart {
+        operation: &'static str,
+        collect: Collect,
+        /// Bind the key or position of each item as well, mapping a map then
+        /// gives a vec
+        keyed: bool,
+        target: Target,
+        node: Node,
+        facts: Option<FactPath>,
+    },
+
+    /// Bind the next item to a slot and its key to the key slot for keyed
+    /// loops, or push the result of the loop and jump to the target when all
+    /// items are done
+    IterNext {
+        slot: usize,
+        key_slot: Option<usize>,
+        target: Target,
+    },
+
+    /// Pop the result of the loop body for the current item and jump back to
+    /// the matching `IterNext`
+    IterCollect {
+        next: Target,
+        node: Node,
+    },
+
+    /// Pop the end and start positions when they are given and the value to
+    /// slice
+    Slice {
+        start: bool,
+        end: bool,
+        node: Node,
+    },
+
+    /// Bind the next item of a `fold` to a slot, or push the accumulator and
+    /// jump to the target when all items are done
+    FoldNext {
+        slot: usize,
+        acc: usize,
+        target: Target,
+        node: Node,
+    },
+
+    /// Pop the result of the `fold` body into the accumulator and jump back
+    /// to the matching `FoldNext`
+    FoldCollect {
+        acc: usize,
+        next: Target,
+    },
+}
+
+/// A reference to a field in the facts with its expression and indexes
+#[derive(Debug, Clone, PartialEq)]
+struct FactPath {
+    name: String,
+    node: Node,
+    path: Path,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Root {
+    /// A variable bound by a `for` loop
+    Slot(usize),
+
+    /// A field in the facts, or the facts themselves for `facts`
+    Fact(String),
+}
+
+impl Plan {
+    pub(crate) fn compile(expr: &Expr) -> Self {
+        let nodes = expr
+            .preorder()
+            .into_iter()
+            .enumerate()
+            .map(|(position, node)| (node as *const Expr, position))
+            .collect();
+
+        let mut compiler = Compiler {
+            ops: Vec::new(),
+            entries: Vec::new(),
+            pending: Vec::new(),
+            depth: 0,
+            nodes,
+            scope: Vec::new(),
+            slots: 0,
+        };
+        compiler.compile(expr);
+
+        Self {
+            ops: compiler.ops,
+            entries: compiler.entries,
+            slots: compiler.slots,
+        }
+    }
+
+    /// Run the plan, errors are returned together with the position of the
+    /// expression that failed
+    pub(crate) async fn run(
+        &self,
+        ruleset: &RuleSet,
+        facts: &Value,
+        interrupt: Interrupt<'_>,
+    ) -> std::result::Result<Value, (Error, Node)> {
+        let mut stack = Vec::<Value>::new();
+        let mut slots = vec![Value::None; self.slots];
+        let mut loops = Vec::<Loop>::new();
+        let mut pc = 0;
+
+        let budget = Budget::new(ruleset.limits());
+        let numeric_policy = ruleset.numeric_policy();
+        let logic_policy = ruleset.logic_policy();
+        let check_entries = ruleset.limits().limits_steps() || interrupt.is_active();
+        let checked = |result: Result<Value>, node: Node| {
+            result
+                .and_then(|value| budget.check_size(&value).map(|_| value))
+                .map_err(|error| (error, node))
+        };
+
+        while let Some(op) = self.ops.get(pc) {
+            if check_entries {
+                for (node, depth) in &self.entries[pc] {
+                    budget
+                        .step(*depth)
+                        .and_then(|_| interrupt.check())
+                        .map_err(|error| (error, *node))?;
+                }
+            }
+            pc += 1;
+
+            match op {
+                Op::Const(value) => stack.push(value.clone()),
+                Op::Load { root, node, path } => {
+                    let value = match root {
+                        Root::Slot(slot) => &slots[*slot],
+                        Root::Fact(name) => fact(facts, name).map_err(|error| (error, *node))?,
+                    };
+                    stack.push(walk(value, path)?.clone());
+                }
+                Op::Index(idx, node) => {
+                    let value = pop(&mut stack);
+                    stack.push(index(value, idx).map_err(|error| (error, *node))?);
+                }
+                Op::StrictIndex(idx, node) => {
+                    let value = pop(&mut stack);
+                    let item = strict_index_ref(&value, idx).map_err(|error| (error, *node))?;
+                    stack.push(item.clone());
+                }
+                Op::Symbol(name, node) => stack.push(
+                    ruleset
+                        .get_symbol(name)
+                        .cloned()
+                        .ok_or_else(|| (Error::UnknownSymbol(name.to_owned()), *node))?,
+                ),
+                Op::Call { name, args, node } => {
+                    let args = stack.split_off(stack.len() - args);
+                    let result = match builtin(ruleset, name) {
+                        Some(builtin) => builtin(ruleset, &args),
+                        None => {
+                            budget.call().map_err(|error| (error, *node))?;
+                            interrupt.run(ruleset.call_function(name, &args)).await
+                        }
+                    };
+                    stack.push(checked(result, *node)?);
+                }
+                Op::Vec(len, node) => {
+                    let items = stack.split_off(stack.len() - len);
+                    stack.push(checked(Ok(Value::Vec(items)), *node)?);
+                }
+                Op::Map(keys, node) => {
+                    let items = stack.split_off(stack.len() - keys.len());
+                    let map = Value::Map(keys.iter().cloned().zip(items).collect());
+                    stack.push(checked(Ok(map), *node)?);
+                }
+                Op::Unary(function, node) => {
+                    let value = pop(&mut stack);
+                    stack.push(checked(function(value), *node)?);
+                }
+                Op::Binary(function, node) => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    stack.push(checked(function(left, right), *node)?);
+                }
+                Op::Policy(function, node) => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    stack.push(checked(function(numeric_policy, left, right), *node)?);
+                }
+                Op::Numeric(function, operation, node) => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    let result = numeric_policy
+                        .promote(operation, left, right)
+                        .and_then(|(left, right)| function(left, right));
+                    stack.push(checked(result, *node)?);
+                }
+                Op::Jump(target) => pc = *target,
+                Op::Branch { otherwise, node } => match pop(&mut stack) {
+                    Value::Bool(true) => (),
+                    Value::Bool(false) => pc = *otherwise,
+                    switch => return Err((Error::invalid_type("if", [&switch]), *node)),
+                },
+                Op::ShortCircuit {
+                    operation,
+                    decisive,
+                    target,
+                    node,
+                } => {
+                    let value = pop(&mut stack);
+                    match logic_policy.truth(operation, &value) {
+                        Ok(Some(truth)) if truth == *decisive => {
+                            stack.push(Value::Bool(truth));
+                            pc = *target;
+                        }
+                        Ok(truth) => stack.push(truth.map(Value::Bool).unwrap_or(Value::None)),
+                        Err(error) => return Err((error, *node)),
+                    }
+                }
+                Op::Connect {
+                    operation,
+                    decisive,
+                    node,
+                } => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    let result = logic_policy
+                        .truth(operation, &left)
+                        .and_then(|left| Ok((left, logic_policy.truth(operation, &right)?)))
+                        .map(|(left, right)| LogicPolicy::connect(*decisive, left, right));
+                    stack.push(result.map_err(|error| (error, *node))?);
+                }
+                Op::Not(node) => {
+                    let value = pop(&mut stack);
+                    stack.push(checked(logic_policy.not(value), *node)?);
+                }
+                Op::Xor(node) => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    stack.push(checked(logic_policy.xor(left, right), *node)?);
+                }
+                Op::Bind(slot) => slots[*slot] = pop(&mut stack),
+                Op::Coalesce { target } => {
+                    if stack.last() == Some(&Value::None) {
+                        pop(&mut stack);
+                    } else {
+                        pc = *target;
+                    }
+                }
+                Op::EqualsNone { result, target } => {
+                    if stack.last() == Some(&Value::None) {
+                        pop(&mut stack);
+                        stack.push(Value::Bool(*result));
+                        pc = *target;
+                    }
+                }
+                Op::Equals { negate } => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    stack.push(Value::Bool(numeric_policy.equals(&left, &right) != *negate));
+                }
+                Op::IterStart {
+                    operation,
+                    collect,
+                    keyed,
+                    target,
+                    node,
+                    facts: Some(list),
+                } => match list.resolve(facts)? {
+                    Value::Vec(vec) => loops.push(Loop::new(*collect, *keyed, vec.iter().into())),
+                    Value::Map(map) => loops.push(Loop::new(*collect, *keyed, map.iter().into())),
+                    Value::None => {
+                        stack.push(Value::None);
+                        pc = *target;
+                    }
+                    list => return Err((Error::invalid_type(*operation, [list]), *node)),
+                },
+                Op::IterStart {
+                    operation,
+                    collect,
+                    keyed,
+                    target,
+                    node,
+                    facts: None,
+                } => match pop(&mut stack) {
+                    Value::Vec(vec) => {
+                        loops.push(Loop::new(*collect, *keyed, vec.into_iter().into()))
+                    }
+                    Value::Map(map) => {
+                        loops.push(Loop::new(*collect, *keyed, map.into_iter().into()))
+                    }
+                    Value::None => {
+                        stack.push(Value::None);
+                        pc = *target;
+                    }
+                    list => return Err((Error::invalid_type(*operation, [&list]), *node)),
+                },
+                Op::IterNext {
+                    slot,
+                    key_slot,
+                    target,
+                } => {
+                    let current = loops.last_mut().expect("loop in progress");
+                    match current.next() {
+                        Some(item) => {
+                            slots[*slot] = item;
+                            if let Some(key_slot) = key_slot {
+                                slots[*key_slot] = current.key();
+                            }
+                        }
+                        None => {
+                            stack.push(loops.pop().expect("loop in progress").finish());
+                            pc = *target;
+                        }
+                    }
+                }
+                Op::IterCollect { next, node } => {
+                    let value = pop(&mut stack);
+                    let current = loops.last_mut().expect("loop in progress");
+                    current
+                        .collect(value, &budget)
+                        .map_err(|error| (error, *node))?;
+                    pc = *next;
+                }
+                Op::FoldNext {
+                    slot,
+                    acc,
+                    target,
+                    node,
+                } => {
+                    let current = loops.last_mut().expect("loop in progress");
+                    match current.next() {
+                        Some(item) => slots[*slot] = item,
+                        None => {
+                            loops.pop();
+                            let result = std::mem::replace(&mut slots[*acc], Value::None);
+                            stack.push(checked(Ok(result), *node)?);
+                            pc = *target;
+                        }
+                    }
+                }
+                Op::Slice { start, end, node } => {
+                    let end = end.then(|| pop(&mut stack));
+                    let start = start.then(|| pop(&mut stack));
+                    let value = pop(&mut stack);
+                    stack.push(checked(slice(value, start, end), *node)?);
+                }
+                Op::FoldCollect { acc, next } => {
+                    slots[*acc] = pop(&mut stack);
+                    pc = *next;
+                }
+            }
+        }
+
+        Ok(pop(&mut stack))
+    }
+}
+
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().expect("value on the evaluation stack")
+}
+
+/// Walk an index path from a value without cloning
+fn walk<'a>(mut value: &'a Value, path: &Path) -> std::result::Result<&'a Value, (Error, Node)> {
+    for (index, node) in path {
+        value = index_ref(value, index).map_err(|error| (error, *node))?;
+    }
+    Ok(value)
+}
+
+impl FactPath {
+    fn resolve<'a>(&self, facts: &'a Value) -> std::result::Result<&'a Value, (Error, Node)> {
+        walk(
+            fact(facts, &self.name).map_err(|error| (error, self.node))?,
+            &self.path,
+        )
+    }
+}
+
+/// What a `for` loop does with the results of its body
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Collect {
+    /// Collect the results
+    Map,
+    /// Keep the items the results are true for
+    Filter,
+    /// Sort the items by the results
+    SortBy,
+}
+
+/// State of a `for` loop that is being evaluated
+struct Loop<'f> {
+    collect: Collect,
+    items: Items<'f>,
+    /// The number of items that were bound so far
+    position: usize,
+    /// The item that is currently bound, kept for filters
+    current: Option<(Option<String>, Cow<'f, Value>)>,
+    result: Value,
+    /// The number of items in the result, including nested items
+    len: usize,
+    /// The sort keys of the items in the result
+    keys: Vec<Value>,
+}
+
+/// Items of a collection that was evaluated or that is borrowed from the facts
+enum Items<'f> {
+    Vec(std::vec::IntoIter<Value>),
+    Map(std::collections::btree_map::IntoIter<String, Value>),
+    VecRef(std::slice::Iter<'f, Value>),
+    MapRef(std::collections::btree_map::Iter<'f, String, Value>),
+}
+
+impl From<std::vec::IntoIter<Value>> for Items<'_> {
+    fn from(items: std::vec::IntoIter<Value>) -> Self {
+        Items::Vec(items)
+    }
+}
+
+impl From<std::collections::btree_map::IntoIter<String, Value>> for Items<'_> {
+    fn from(items: std::collections::btree_map::IntoIter<String, Value>) -> Self {
+        Items::Map(items)
+    }
+}
+
+impl<'f> From<std::slice::Iter<'f, Value>> for Items<'f> {
+    fn from(items: std::slice::Iter<'f, Value>) -> Self {
+        Items::VecRef(items)
+    }
+}
+
+impl<'f> From<std::collections::btree_map::Iter<'f, String, Value>> for Items<'f> {
+    fn from(items: std::collections::btree_map::Iter<'f, String, Value>) -> Self {
+        Items::MapRef(items)
+    }
+}
+
+impl<'f> Loop<'f> {
+    fn new(collect: Collect, keyed: bool, items: Items<'f>) -> Self {
+        let keeps_map = match collect {
+            Collect::Map => !keyed,
+            Collect::Filter => true,
+            Collect::SortBy => false,
+        };
+        let result = match items {
+            Items::Map(_) | Items::MapRef(_) if keeps_map => Value::Map(BTreeMap::new()),
+            _ => Value::Vec(Vec::new()),
+        };
+
+        Self {
+            collect,
+            items,
+            position: 0,
+            current: None,
+            result,
+            len: 0,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Advance to the next item and return the value to bind
+    fn next(&mut self) -> Option<Value> {
+        let (key, item) = match &mut self.items {
+            Items::Vec(items) => (None, Cow::Owned(items.next()?)),
+            Items::Map(items) => {
+                let (key, item) = items.next()?;
+                (Some(key), Cow::Owned(item))
+            }
+            Items::VecRef(items) => (None, Cow::Borrowed(items.next()?)),
+            Items::MapRef(items) => {
+                let (key, item) = items.next()?;
+                (Some(key.clone()), Cow::Borrowed(item))
+            }
+        };
+
+        let bound = item.as_ref().clone();
+        self.current = Some((key, item));
+        self.position += 1;
+        Some(bound)
+    }
+
+    /// The key of the current item in a map or its position in a vec
+    fn key(&self) -> Value {
+        match &self.current {
+            Some((Some(key), _)) => Value::String(key.clone()),
+            _ => Value::Int(self.position as i128 - 1),
+        }
+    }
+
+    /// Add the result of the loop body for the current item, counting it
+    /// against the collection limit
+    fn collect(&mut self, value: Value, budget: &Budget) -> Result<()> {
+        let (key, item) = self.current.take().expect("current loop item");
+
+        let value = match self.collect {
+            Collect::Filter if value == Value::Bool(true) => item.into_owned(),
+            Collect::Filter => return Ok(()),
+            Collect::Map => value,
+            Collect::SortBy => {
+                self.keys.push(value);
+                item.into_owned()
+            }
+        };
+        budget.collect(&mut self.len, &value)?;
+
+        match (&mut self.result, key) {
+            (Value::Vec(result), _) => result.push(value),
+            (Value::Map(result), Some(key)) => {
+                result.insert(key, value);
+            }
+            _ => unreachable!("loop results match the collection"),
+        }
+        Ok(())
+    }
+
+    /// The result of the loop after all items are done
+    fn finish(self) -> Value {
+        match (self.collect, self.result) {
+            (Collect::SortBy, Value::Vec(items)) => {
+                Value::Vec(sort_by_keys(self.keys.into_iter().zip(items).collect()))
+            }
+            (_, result) => result,
+        }
+    }
+}
+
+struct Compiler<'a> {
+    ops: Vec<Op>,
+    entries: Vec<Vec<(Node, usize)>>,
+    /// Expressions entered since the last instruction, they are recorded with
+    /// the next instruction
+    pending: Vec<(Node, usize)>,
+    /// Nesting depth of the expression that is being compiled
+    depth: usize,
+    nodes: HashMap<*const Expr, Node>,
+    /// Variables bound by the `for` loops around the expression that is being
+    /// compiled, innermost last. A variable is stored in the slot that matches
+    /// its position
+    scope: Vec<&'a str>,
+    slots: usize,
+}
+
+impl<'a> Compiler<'a> {
+    fn node(&self, expr: &Expr) -> Node {
+        self.nodes[&(expr as *const Expr)]
+    }
+
+    fn emit(&mut self, op: Op) -> Target {
+        self.ops.push(op);
+        self.entries.push(std::mem::take(&mut self.pending));
+        self.ops.len() - 1
+    }
+
+    /// Point the jump target of an instruction at the next instruction
+    fn patch(&mut self, at: Target) {
+        let next = self.ops.len();
+        match &mut self.ops[at] {
+            Op::Jump(target)
+            | Op::Branch {
+                otherwise: target, ..
+            }
+            | Op::ShortCircuit { target, .. }
+            | Op::Coalesce { target }
+            | Op::EqualsNone { target, .. }
+            | Op::IterStart { target, .. }
+            | Op::IterNext { target, .. }
+            | Op::FoldNext { target, .. } => *target = next,
+            op => unreachable!("{op:?} does not jump"),
+        }
+    }
+
+    fn unary(&mut self, expr: &'a Expr, value: &'a Expr, function: fn(Value) -> Result<Value>) {
+        self.compile(value);
+        self.emit(Op::Unary(function, self.node(expr)));
+    }
+
+    fn binary(
+        &mut self,
+        expr: &'a Expr,
+        left: &'a Expr,
+        right: &'a Expr,
+        function: fn(Value, Value) -> Result<Value>,
+    ) {
+        self.compile(left);
+        self.compile(right);
+        self.emit(Op::Binary(function, self.node(expr)));
+    }
+
+    fn policy(
+        &mut self,
+        expr: &'a Expr,
+        left: &'a Expr,
+        right: &'a Expr,
+        function: fn(NumericPolicy, Value, Value) -> Result<Value>,
+    ) {
+        self.compile(left);
+        self.compile(right);
+        self.emit(Op::Policy(function, self.node(expr)));
+    }
+
+    fn numeric(
+        &mut self,
+        expr: &'a Expr,
+        operation: &'static str,
+        left: &'a Expr,
+        right: &'a Expr,
+        function: fn(Value, Value) -> Result<Value>,
+    ) {
+        self.compile(left);
+        self.compile(right);
+        self.emit(Op::Numeric(function, operation, self.node(expr)));
+    }
+
+    fn compile(&mut self, expr: &'a Expr) {
+        let node = self.node(expr);
+        self.depth += 1;
+        self.pending.push((node, self.depth));
+
+        match expr {
+            Expr::Value(value) => {
+                self.emit(Op::Const(value.clone()));
+            }
+            Expr::Reference(_) | Expr::Index(_, _) => self.compile_path(expr),
+            Expr::Symbol(name) => {
+                self.emit(Op::Symbol(name.clone(), node));
+            }
+            Expr::Function(name, params) => {
+                params.iter().for_each(|param| self.compile(param));
+                self.emit(Op::Call {
+                    name: name.clone(),
+                    args: params.len(),
+                    node,
+                });
+            }
+            Expr::If(switch, left, right) => {
+                self.compile(switch);
+                let branch = self.emit(Op::Branch { otherwise: 0, node });
+                self.compile(left);
+                let jump = self.emit(Op::Jump(0));
+                self.patch(branch);
+                self.compile(right);
+                self.patch(jump);
+            }
+            Expr::Coalesce(left, right) => {
+                self.compile(left);
+                let coalesce = self.emit(Op::Coalesce { target: 0 });
+                self.compile(right);
+                self.patch(coalesce);
+            }
+            Expr::StrictIndex(value, index) => {
+                self.compile(value);
+                self.emit(Op::StrictIndex(index.clone(), node));
+            }
+            Expr::Let(bind, value, body) => {
+                self.compile(value);
+                let slot = self.scope.len();
+                self.slots = self.slots.max(slot + 1);
+                self.emit(Op::Bind(slot));
+
+                self.scope.push(bind);
+                self.compile(body);
+                self.scope.pop();
+            }
+            Expr::Not(value) => {
+                self.compile(value);
+                self.emit(Op::Not(node));
+            }
+            Expr::Neg(value) => self.unary(expr, value, neg),
+            Expr::Some(value) => self.unary(expr, value, some),
+            Expr::None(value) => self.unary(expr, value, none),
+            Expr::DateTime(value) => self.unary(expr, value, datetime),
+            Expr::Duration(value) => self.unary(expr, value, duration),
+            Expr::Map(map) => {
+                map.values().for_each(|value| self.compile(value));
+                self.emit(Op::Map(map.keys().cloned().collect(), node));
+            }
+            Expr::Vec(vec) => {
+                vec.iter().for_each(|item| self.compile(item));
+                self.emit(Op::Vec(vec.len(), node));
+            }
+            Expr::Int(value) => self.unary(expr, value, int),
+            Expr::Float(value) => self.unary(expr, value, float),
+            Expr::Dec(value) => self.unary(expr, value, dec),
+            Expr::Mult(left, right) => self.numeric(expr, "*", left, right, mult),
+            Expr::Div(left, right) => self.numeric(expr, "/", left, right, div),
+            Expr::Rem(left, right) => self.numeric(expr, "%", left, right, rem),
+            Expr::Add(left, right) => self.numeric(expr, "+", left, right, add),
+            Expr::Sub(left, right) => self.numeric(expr, "-", left, right, sub),
+            Expr::Equals(left, right) => self.compile_eq(left, right, false),
+            Expr::NotEquals(left, right) => self.compile_eq(left, right, true),
+            Expr::GreaterThan(left, right) => self.policy(expr, left, right, gt),
+            Expr::GreaterThanEquals(left, right) => self.policy(expr, left, right, gte),
+            Expr::LessThan(left, right) => self.policy(expr, left, right, lt),
+            Expr::LessThanEquals(left, right) => self.policy(expr, left, right, lte),
+            Expr::And(left, right) => self.compile_logic(node, "and", false, left, right),
+            Expr::Or(left, right) => self.compile_logic(node, "or", true, left, right),
+            Expr::BitAnd(left, right) => self.binary(expr, left, right, bitwise_and),
+            Expr::BitOr(left, right) => self.binary(expr, left, right, bitwise_or),
+            Expr::BitXor(left, right) => {
+                self.compile(left);
+                self.compile(right);
+                self.emit(Op::Xor(node));
+            }
+            Expr::Contains(coll, item) => self.policy(expr, coll, item, contains),
+            Expr::Starts(string, substr) => self.binary(expr, string, substr, starts),
+            Expr::Ends(string, substr) => self.binary(expr, string, substr, ends),
+            Expr::UpperCase(value) => self.unary(expr, value, uppercase),
+            Expr::LowerCase(value) => self.unary(expr, value, lowercase),
+            Expr::Trim(value) => self.unary(expr, value, trim),
+            Expr::Round(value) => self.unary(expr, value, round),
+            Expr::Floor(value) => self.unary(expr, value, floor),
+            Expr::Fract(value) => self.unary(expr, value, fract),
+            Expr::Year(value) => self.unary(expr, value, year),
+            Expr::Month(value) => self.unary(expr, value, month),
+            Expr::Week(value) => self.unary(expr, value, week),
+            Expr::Day(value) => self.unary(expr, value, day),
+            Expr::Hour(value) => self.unary(expr, value, hour),
+            Expr::Minute(value) => self.unary(expr, value, minute),
+            Expr::Second(value) => self.unary(expr, value, second),
+            Expr::Any(value) => self.unary(expr, value, any),
+            Expr::All(value) => self.unary(expr, value, all),
+            Expr::ForMap(bind, list, operation) => {
+                self.compile_for(node, Collect::Map, None, bind, list, operation)
+            }
+            Expr::ForFilter(bind, list, predicate) => {
+                self.compile_for(node, Collect::Filter, None, bind, list, predicate)
+            }
+            Expr::ForMapEntries(key, bind, list, operation) => {
+                self.compile_for(node, Collect::Map, Some(key), bind, list, operation)
+            }
+            Expr::ForFilterEntries(key, bind, list, predicate) => {
+                self.compile_for(node, Collect::Filter, Some(key), bind, list, predicate)
+            }
+            Expr::ForSortBy(bind, list, key) => {
+                self.compile_for(node, Collect::SortBy, None, bind, list, key)
+            }
+            Expr::Slice(value, start, end) => {
+                self.compile(value);
+                start.iter().for_each(|start| self.compile(start));
+                end.iter().for_each(|end| self.compile(end));
+                self.emit(Op::Slice {
+                    start: start.is_some(),
+                    end: end.is_some(),
+                    node,
+                });
+            }
+            Expr::ForFold(bind, list, acc, init, operation) => {
+                self.compile_fold(node, bind, list, acc, init, operation)
+            }
+        }
+        self.depth -= 1;
+    }
+
+    /// Compile a reference followed by any number of indexes into a single
+    /// load, indexes on other expressions are compiled separately
+    fn compile_path(&mut self, expr: &'a Expr) {
+        let mut path = Vec::new();
+        let mut base = expr;
+        while let Expr::Index(value, index) = base {
+            path.push((index.clone(), self.node(base)));
+            base = value;
+        }
+        path.reverse();
+
+        match base {
+            Expr::Reference(name) => {
+                let root = match self.scope.iter().rposition(|bound| bound == name) {
+                    Some(slot) => Root::Slot(slot),
+                    None => Root::Fact(name.clone()),
+                };
+                self.emit(Op::Load {
+                    root,
+                    node: self.node(base),
+                    path,
+                });
+            }
+            base => {
+                // Indexes on other expressions are entered one inside the
+                // other, the outermost one was entered already
+                let nested = path.len() - 1;
+                for (level, (_, node)) in path.iter().rev().skip(1).enumerate() {
+                    self.pending.push((*node, self.depth + level + 1));
+                }
+
+                self.depth += nested;
+                self.compile(base);
+                self.depth -= nested;
+
+                for (index, node) in path {
+                    self.emit(Op::Index(index, node));
+                }
+            }
+        }
+    }
+
+    /// Split a path that starts at a field in the facts into its parts,
+    /// returns `None` for other expressions
+    fn fact_path(&self, expr: &'a Expr) -> Option<FactPath> {
+        match expr {
+            Expr::Reference(name) if !self.scope.contains(&name.as_str()) => Some(FactPath {
+                name: name.clone(),
+                node: self.node(expr),
+                path: Vec::new(),
+            }),
+            Expr::Index(value, index) => {
+                let mut fact_path = self.fact_path(value)?;
+                fact_path.path.push((index.clone(), self.node(expr)));
+                Some(fact_path)
+            }
+            _ => None,
+        }
+    }
+
+    fn compile_eq(&mut self, left: &'a Expr, right: &'a Expr, negate: bool) {
+        self.compile(left);
+        let none = self.emit(Op::EqualsNone {
+            result: negate,
+            target: 0,
+        });
+        self.compile(right);
+        self.emit(Op::Equals { negate });
+        self.patch(none);
+    }
+
+    fn compile_logic(
+        &mut self,
+        node: Node,
+        operation: &'static str,
+        decisive: bool,
+        left: &'a Expr,
+        right: &'a Expr,
+    ) {
+        self.compile(left);
+        let short_circuit = self.emit(Op::ShortCircuit {
+            operation,
+            decisive,
+            target: 0,
+            node,
+        });
+        self.compile(right);
+        self.emit(Op::Connect {
+            operation,
+            decisive,
+            node,
+        });
+        self.patch(short_circuit);
+    }
+
+    fn compile_for(
+        &mut self,
+        node: Node,
+        collect: Collect,
+        key: Option<&'a str>,
+        bind: &'a str,
+        list: &'a Expr,
+        body: &'a Expr,
+    ) {
+        let operation = match collect {
+            Collect::Map => "for .. map",
+            Collect::Filter => "for .. filter",
+            Collect::SortBy => "for .. sort_by",
+        };
+        let start = self.compile_iter_start(node, operation, collect, key.is_some(), list);
+
+        // The key is bound in the slot before the item
+        let key_slot = key.map(|_| self.scope.len());
+        let slot = self.scope.len() + usize::from(key.is_some());
+        self.slots = self.slots.max(slot + 1);
+        let next = self.emit(Op::IterNext {
+            slot,
+            key_slot,
+            target: 0,
+        });
+
+        self.scope.extend(key);
+        self.scope.push(bind);
+        self.compile(body);
+        self.scope.pop();
+        if key.is_some() {
+            self.scope.pop();
+        }
+
+        self.emit(Op::IterCollect { next, node });
+        self.patch(start);
+        self.patch(next);
+    }
+
+    fn compile_fold(
+        &mut self,
+        node: Node,
+        bind: &'a str,
+        list: &'a Expr,
+        acc: &'a str,
+        init: &'a Expr,
+        operation: &'a Expr,
+    ) {
+        let start = self.compile_iter_start(node, "for .. fold", Collect::Map, false, list);
+        self.compile(init);
+
+        // The accumulator is bound in the slot before the item
+        let acc_slot = self.scope.len();
+        let slot = acc_slot + 1;
+        self.slots = self.slots.max(slot + 1);
+        self.emit(Op::Bind(acc_slot));
+        let next = self.emit(Op::FoldNext {
+            slot,
+            acc: acc_slot,
+            target: 0,
+            node,
+        });
+
+        self.scope.push(acc);
+        self.scope.push(bind);
+        self.compile(operation);
+        self.scope.truncate(acc_slot);
+
+        self.emit(Op::FoldCollect {
+            acc: acc_slot,
+            next,
+        });
+        self.patch(start);
+        self.patch(next);
+    }
+
+    /// Start iterating over a list, returns the instruction that jumps past
+    /// the loop when the list is `None`
+    fn compile_iter_start(
+        &mut self,
+        node: Node,
+        operation: &'static str,
+        collect: Collect,
+        keyed: bool,
+        list: &'a Expr,
+    ) -> Target {
+        // Collections in the facts are iterated in place, others are
+        // evaluated first
+        let facts = self.fact_path(list);
+        match facts {
+            Some(_) => self.pending.push((self.node(list), self.depth + 1)),
+            None => self.compile(list),
+        }
+
+        self.emit(Op::IterStart {
+            operation,
+            collect,
+            keyed,
+            target: 0,
+            node,
+            facts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod when_compiling_plans {
+    use super::*;
+
+    fn compile(expr: &str) -> Plan {
+        Plan::compile(&Expr::parse(expr).unwrap())
+    }
+
+    fn loads(plan: &Plan) -> Vec<(&Root, Node, &Path)> {
+        plan.ops
+            .iter()
+            .filter_map(|op| match op {
+                Op::Load { root, node, path } => Some((root, *node, path)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_compile_index_chain_into_single_load() {
+        let plan = compile("a.b.0");
+
+        assert_eq!(plan.ops.len(), 1);
+        assert_eq!(
+            loads(&plan),
+            [(
+                &Root::Fact("a".to_string()),
+                2,
+                &vec![(Index::Map("b".to_string()), 1), (Index::Vec(0), 0)]
+            )]
+        );
+    }
+
+    #[test]
+    fn should_resolve_loop_variables_to_slots() {
+        let plan = compile("for x in xs map for y in x map y + x");
+
+        assert_eq!(plan.slots, 2);
+        assert_eq!(
+            loads(&plan),
+            [
+                (&Root::Slot(0), 3, &Vec::new()),
+                (&Root::Slot(1), 5, &Vec::new()),
+                (&Root::Slot(0), 6, &Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_iterate_fact_collections_in_place() {
+        let plan = compile("for x in order.items map x");
+
+        assert!(loads(&plan)
+            .iter()
+            .all(|(root, _, _)| **root == Root::Slot(0)));
+        assert!(plan.ops.iter().any(|op| matches!(
+            op,
+            Op::IterStart {
+                facts: Some(FactPath { name, path, .. }),
+                ..
+            } if name == "order" && path.len() == 1
+        )));
+    }
+
+    #[test]
+    fn should_resolve_let_variables_to_slots() {
+        let plan = compile("let total = order.total in for x in xs map x * total");
+
+        assert_eq!(plan.slots, 2);
+        assert!(plan.ops.iter().any(|op| matches!(op, Op::Bind(0))));
+        assert_eq!(
+            loads(&plan).last().map(|(root, _, _)| *root),
+            Some(&Root::Slot(0))
+        );
+    }
+
+    #[test]
+    fn should_bind_fold_accumulator_before_item() {
+        let plan = compile("for x in xs fold total = i0 with total + x");
+
+        assert_eq!(plan.slots, 2);
+        assert_eq!(
+            loads(&plan)
+                .iter()
+                .map(|(root, _, _)| *root)
+                .collect::<Vec<_>>(),
+            [&Root::Slot(0), &Root::Slot(1)]
+        );
+    }
+
+    #[test]
+    fn should_reuse_slots_for_sibling_loops() {
+        let plan = compile("[for x in xs map x, for y in ys map y]");
+
+        assert_eq!(plan.slots, 1);
+    }
+}
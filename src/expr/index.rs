@@ -1,6 +1,10 @@
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
-#[derive(Clone, Debug, PartialEq)]
+/// Index into a map by key or into a vec by position
+///
+/// Serialized as `{"Map": "key"}` or `{"Vec": 0}`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Index {
     Map(String),
     Vec(usize),
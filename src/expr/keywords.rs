@@ -1,7 +1,7 @@
 use unicode_xid::UnicodeXID;
 
 /// Reserved keywords
-const KEYWORDS: [&str; 43] = [
+const KEYWORDS: [&str; 47] = [
     "and",
     "or",
     "if",
@@ -43,8 +43,12 @@ const KEYWORDS: [&str; 43] = [
     "key",
     "val",
     "for",
+    "let",
     "map",
     "filter",
+    "fold",
+    "with",
+    "sort_by",
 ];
 
 pub(crate) fn is_reserved_keyword(name: &str) -> bool {
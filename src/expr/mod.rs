@@ -1,15 +1,24 @@
 pub(crate) mod constructor;
 mod eval;
+
+pub(crate) use eval::plan::Plan;
 pub mod index;
 pub(crate) mod keywords;
 
 use crate::value::Value;
 pub use index::Index;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, fmt::Display};
 
 /// The Reval expression abstract syntax tree
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Expressions can be serialized and deserialized using serde. Every variant is
+/// represented as an externally tagged enum, so `i1 + a` looks like
+/// `{"Add": [{"Value": {"Int": 1}}, {"Reference": "a"}]}` in json. Formats
+/// like bincode encode variants by position, new variants are only ever added
+/// at the end to keep stored expressions readable.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Expr {
     /// A literal value
     Value(Value),
@@ -20,8 +29,8 @@ pub enum Expr {
     /// Insert a symbol from the loaded symbol table
     Symbol(String),
 
-    /// Evaluate a user functions by name
-    Function(String, Box<Expr>),
+    /// Evaluate a user functions by name, passing in zero or more arguments
+    Function(String, Vec<Expr>),
 
     /// Index a dictionary or an array value
     Index(Box<Expr>, Index),
@@ -169,6 +178,38 @@ pub enum Expr {
 
     /// Filter items in a list by evaluating a predicate expression for each item
     ForFilter(String, Box<Expr>, Box<Expr>),
+
+    /// Evaluates to the left expression, or to the right expression when the
+    /// left one is none
+    Coalesce(Box<Expr>, Box<Expr>),
+
+    /// Index a dictionary or an array value, fails when the key or position
+    /// does not exist instead of evaluating to none
+    StrictIndex(Box<Expr>, Index),
+
+    /// Bind the result of an expression to a name while evaluating the body
+    Let(String, Box<Expr>, Box<Expr>),
+
+    /// Map the keys and values of a map, or the positions and items of a
+    /// list, to a list by evaluating an expression for each entry
+    ForMapEntries(String, String, Box<Expr>, Box<Expr>),
+
+    /// Filter the entries of a map or a list by evaluating a predicate
+    /// expression for each key and value
+    ForFilterEntries(String, String, Box<Expr>, Box<Expr>),
+
+    /// Combine the items in a list into a single value by evaluating an
+    /// expression for each item with the accumulator bound to a name, the
+    /// accumulator starts with the value of the initial expression
+    ForFold(String, Box<Expr>, String, Box<Expr>, Box<Expr>),
+
+    /// Sort the items in a list by the value of a key expression that is
+    /// evaluated for each item
+    ForSortBy(String, Box<Expr>, Box<Expr>),
+
+    /// Take a part of a list or a string from a start position up to but not
+    /// including an end position, both positions are optional
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
 }
 
 impl From<Value> for Expr {
@@ -177,13 +218,102 @@ impl From<Value> for Expr {
     }
 }
 
+impl Expr {
+    /// The direct sub-expressions of this expression in evaluation order, map
+    /// items are returned in key order
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Value(_) | Expr::Reference(_) | Expr::Symbol(_) => Vec::new(),
+            Expr::Vec(items) | Expr::Function(_, items) => items.iter().collect(),
+            Expr::Map(items) => items.values().collect(),
+            Expr::If(check, true_case, false_case) => vec![check, true_case, false_case],
+            Expr::Index(inner, _)
+            | Expr::StrictIndex(inner, _)
+            | Expr::Not(inner)
+            | Expr::Neg(inner)
+            | Expr::Some(inner)
+            | Expr::None(inner)
+            | Expr::Int(inner)
+            | Expr::Float(inner)
+            | Expr::Dec(inner)
+            | Expr::DateTime(inner)
+            | Expr::Duration(inner)
+            | Expr::UpperCase(inner)
+            | Expr::LowerCase(inner)
+            | Expr::Trim(inner)
+            | Expr::Floor(inner)
+            | Expr::Round(inner)
+            | Expr::Fract(inner)
+            | Expr::Year(inner)
+            | Expr::Month(inner)
+            | Expr::Week(inner)
+            | Expr::Day(inner)
+            | Expr::Hour(inner)
+            | Expr::Minute(inner)
+            | Expr::Second(inner)
+            | Expr::Any(inner)
+            | Expr::All(inner) => vec![inner],
+            Expr::Mult(left, right)
+            | Expr::Div(left, right)
+            | Expr::Rem(left, right)
+            | Expr::Add(left, right)
+            | Expr::Sub(left, right)
+            | Expr::Equals(left, right)
+            | Expr::NotEquals(left, right)
+            | Expr::GreaterThan(left, right)
+            | Expr::GreaterThanEquals(left, right)
+            | Expr::LessThan(left, right)
+            | Expr::LessThanEquals(left, right)
+            | Expr::And(left, right)
+            | Expr::Or(left, right)
+            | Expr::BitAnd(left, right)
+            | Expr::BitOr(left, right)
+            | Expr::BitXor(left, right)
+            | Expr::Contains(left, right)
+            | Expr::Starts(left, right)
+            | Expr::Ends(left, right)
+            | Expr::ForMap(_, left, right)
+            | Expr::ForFilter(_, left, right)
+            | Expr::Coalesce(left, right)
+            | Expr::Let(_, left, right)
+            | Expr::ForMapEntries(_, _, left, right)
+            | Expr::ForFilterEntries(_, _, left, right)
+            | Expr::ForSortBy(_, left, right) => vec![left, right],
+            Expr::Slice(value, start, end) => std::iter::once(value)
+                .chain(start)
+                .chain(end)
+                .map(AsRef::as_ref)
+                .collect(),
+            Expr::ForFold(_, list, _, init, operation) => vec![list, init, operation],
+        }
+    }
+
+    /// All expressions in this expression tree, starting with this expression
+    /// followed by the sub-expressions of each child in turn
+    pub fn preorder(&self) -> Vec<&Expr> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![self];
+
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+            stack.extend(node.children().into_iter().rev());
+        }
+
+        nodes
+    }
+}
+
 impl Display for Expr {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Expr::Value(value) => write!(formatter, "{value}"),
             Expr::Reference(ident) => write!(formatter, "{ident}"),
             Expr::Symbol(ident) => write!(formatter, ":{ident}"),
-            Expr::Function(ident, param) => write!(formatter, "{ident}({param})"),
+            Expr::Function(ident, params) => write!(
+                formatter,
+                "{ident}({})",
+                params.iter().map(ToString::to_string).join(", ")
+            ),
             Expr::Index(left, right) => write!(formatter, "({left}.{right})"),
             Expr::If(check, true_case, false_case) => {
                 write!(formatter, "(if {check} then {true_case} else {false_case})")
@@ -249,6 +379,38 @@ impl Display for Expr {
                 write!(formatter, "for {bind} in {list} filter {pred}")
             }
             Expr::ForMap(bind, list, expr) => write!(formatter, "for {bind} in {list} map {expr}"),
+            Expr::Coalesce(left, right) => write!(formatter, "({left} ?? {right})"),
+            Expr::StrictIndex(left, right) => write!(formatter, "({left}!.{right})"),
+            Expr::Let(bind, value, body) => write!(formatter, "(let {bind} = {value} in {body})"),
+            Expr::ForMapEntries(key, bind, list, expr) => {
+                write!(formatter, "for {key}, {bind} in {list} map {expr}")
+            }
+            Expr::ForFilterEntries(key, bind, list, pred) => {
+                write!(formatter, "for {key}, {bind} in {list} filter {pred}")
+            }
+            Expr::ForFold(bind, list, acc, init, expr) => {
+                write!(
+                    formatter,
+                    "for {bind} in {list} fold {acc} = {init} with {expr}"
+                )
+            }
+            Expr::ForSortBy(bind, list, key) => {
+                write!(formatter, "for {bind} in {list} sort_by {key}")
+            }
+            Expr::Slice(value, start, end) => {
+                let position = |position: &Option<Box<Expr>>| {
+                    position
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default()
+                };
+                write!(
+                    formatter,
+                    "({value}[{}..{}])",
+                    position(start),
+                    position(end)
+                )
+            }
         }
     }
 }
@@ -1,4 +1,9 @@
 //! User functions
+mod typed;
+
+pub(crate) use typed::{AsyncFnFunction, FnFunction};
+pub use typed::{AsyncTypedFunction, TypedFunction, TypedFuture};
+
 use crate::{
     expr::keywords::{is_reserved_keyword, is_valid_identifier},
     value::Value,
@@ -8,13 +13,51 @@ use async_trait::async_trait;
 use std::collections::BTreeMap;
 
 /// User functions should implement this trait
+///
+/// `call_args` receives the arguments of a function call like `name(a, b, c)`
+/// as a slice. Functions that take a single parameter can implement
+/// `SingleArgFunction` instead.
 #[async_trait]
 pub trait UserFunction {
+    /// Call the userfunction with the arguments from a function call like
+    /// `name(a, b, c)`
+    async fn call_args(&self, args: &[Value]) -> FunctionResult;
+
+    /// Call the userfunction with a single argument
+    async fn call(&self, param: Value) -> FunctionResult {
+        self.call_args(&[param]).await
+    }
+
+    /// The name of the user-function
+    fn name(&self) -> &str;
+}
+
+/// User functions that take a single parameter can implement this trait
+/// instead of `UserFunction`
+///
+/// Calls with a single argument pass that argument to `call` as is, calls with
+/// any other number of arguments pass them to `call` packed in a `Value::Vec`.
+#[async_trait]
+pub trait SingleArgFunction {
     /// Call the userfunction, parameters are passed in as a Value
-    async fn call(&self, params: Value) -> FunctionResult;
+    async fn call(&self, param: Value) -> FunctionResult;
 
     /// The name of the user-function
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
+}
+
+#[async_trait]
+impl<F: SingleArgFunction + Sync> UserFunction for F {
+    async fn call_args(&self, args: &[Value]) -> FunctionResult {
+        match args {
+            [param] => SingleArgFunction::call(self, param.clone()).await,
+            args => SingleArgFunction::call(self, Value::Vec(args.to_vec())).await,
+        }
+    }
+
+    fn name(&self) -> &str {
+        SingleArgFunction::name(self)
+    }
 }
 
 /// Result type returned from UserFunction
@@ -23,7 +66,7 @@ pub type FunctionResult = std::result::Result<Value, anyhow::Error>;
 /// Stores user-functions so they can be easilly called
 #[derive(Default)]
 pub(crate) struct UserFunctions {
-    functions: BTreeMap<&'static str, BoxedFunction>,
+    functions: BTreeMap<String, BoxedFunction>,
 }
 
 impl UserFunctions {
@@ -34,6 +77,11 @@ impl UserFunctions {
             .ok_or_else(|| Error::UnknownUserFunction(name.to_owned()))
     }
 
+    /// Is there a user-function with this name
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
     /// Add a user-function to the collection
     pub(crate) fn add_function(
         &mut self,
@@ -43,18 +91,18 @@ impl UserFunctions {
     }
 
     pub(crate) fn add_boxed_function(&mut self, function: BoxedFunction) -> Result<()> {
-        let name = function.name();
+        let name = function.name().to_string();
 
-        if is_reserved_keyword(name) {
-            return Err(Error::InvalidFunctionName(name.to_string()));
+        if is_reserved_keyword(&name) {
+            return Err(Error::InvalidFunctionName(name));
         }
 
-        if !is_valid_identifier(name) {
-            return Err(Error::InvalidFunctionName(name.to_string()));
+        if !is_valid_identifier(&name) {
+            return Err(Error::InvalidFunctionName(name));
         }
 
-        if self.functions.contains_key(name) {
-            return Err(Error::DuplicateFunctionName(name.to_string()));
+        if self.functions.contains_key(&name) {
+            return Err(Error::DuplicateFunctionName(name));
         }
 
         self.functions.insert(name, function);
@@ -62,13 +110,21 @@ impl UserFunctions {
         Ok(())
     }
 
-    pub(crate) async fn call(&self, name: &str, param: Value) -> Result<Value> {
+    pub(crate) async fn call(&self, name: &str, args: &[Value]) -> Result<Value> {
         self.get(name)?
-            .call(param)
+            .call_args(args)
             .await
-            .map_err(|err| Error::UserFunctionError {
-                function: name.to_owned(),
-                error: err,
+            .map_err(|err| match err.downcast::<Error>() {
+                // Argument errors from typed functions already name the function
+                Ok(error @ Error::InvalidFunctionArgs { .. }) => error,
+                Ok(error) => Error::UserFunctionError {
+                    function: name.to_owned(),
+                    error: error.into(),
+                },
+                Err(err) => Error::UserFunctionError {
+                    function: name.to_owned(),
+                    error: err,
+                },
             })
     }
 }
@@ -124,7 +180,7 @@ mod when_managing_user_functions {
 
     #[async_trait]
     impl UserFunction for TestFunc {
-        async fn call(&self, _params: Value) -> FunctionResult {
+        async fn call_args(&self, _args: &[Value]) -> FunctionResult {
             Ok(Value::None)
         }
 
@@ -0,0 +1,155 @@
+//! Use plain Rust functions and closures as user-functions
+//!
+//! Arguments are converted from `Value`s using `TryFrom<Value>` and results are
+//! converted back using `Into<Value>`, so any type with those conversions can
+//! be used in the function signature.
+
+use crate::{
+    function::{FunctionResult, UserFunction},
+    value::Value,
+    Error,
+};
+use async_trait::async_trait;
+use std::{fmt::Display, future::Future, marker::PhantomData, pin::Pin};
+
+/// Functions and closures taking up to eight arguments that can be called as
+/// a user-function, see `Builder::with_fn`
+pub trait TypedFunction<Args>: Send + Sync + 'static {
+    /// Convert the arguments and call the function
+    fn call_typed(&self, name: &str, args: &[Value]) -> FunctionResult;
+}
+
+/// Future returned from calling an async typed function
+pub type TypedFuture = Pin<Box<dyn Future<Output = FunctionResult> + Send>>;
+
+/// Functions and closures taking up to eight arguments that return a future
+/// that can be called as a user-function, see `Builder::with_async_fn`
+pub trait AsyncTypedFunction<Args>: Send + Sync + 'static {
+    /// Convert the arguments and call the function, the arguments are
+    /// converted before the returned future is polled
+    fn call_typed(&self, name: &str, args: &[Value]) -> Result<TypedFuture, Error>;
+}
+
+/// Check that a function was called with the expected number of arguments
+fn expect_args<'a, const N: usize>(name: &str, args: &'a [Value]) -> Result<&'a [Value; N], Error> {
+    args.try_into().map_err(|_| Error::InvalidFunctionArgs {
+        function: name.to_string(),
+        reason: format!("expected {N} arguments, found {}", args.len()),
+    })
+}
+
+/// Convert an argument to the type the function expects
+fn convert_arg<T>(name: &str, position: usize, arg: &Value) -> Result<T, Error>
+where
+    T: TryFrom<Value>,
+    T::Error: Display,
+{
+    T::try_from(arg.clone()).map_err(|error| Error::InvalidFunctionArgs {
+        function: name.to_string(),
+        reason: format!("argument {position} is not valid; {error}"),
+    })
+}
+
+macro_rules! impl_typed_function {
+    ($count:literal $(, $position:literal $arg:ident $value:ident)*) => {
+        impl<Func, Ret, Err, $($arg,)*> TypedFunction<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Result<Ret, Err> + Send + Sync + 'static,
+            Ret: Into<Value>,
+            Err: Into<anyhow::Error>,
+            $($arg: TryFrom<Value>, <$arg as TryFrom<Value>>::Error: Display,)*
+        {
+            fn call_typed(&self, name: &str, args: &[Value]) -> FunctionResult {
+                let [$($value),*] = expect_args::<$count>(name, args)?;
+                $(let $value = convert_arg::<$arg>(name, $position, $value)?;)*
+
+                (self)($($value),*).map(Into::into).map_err(Into::into)
+            }
+        }
+
+        impl<Func, Fut, Ret, Err, $($arg,)*> AsyncTypedFunction<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = Result<Ret, Err>> + Send + 'static,
+            Ret: Into<Value>,
+            Err: Into<anyhow::Error>,
+            $($arg: TryFrom<Value>, <$arg as TryFrom<Value>>::Error: Display,)*
+        {
+            fn call_typed(&self, name: &str, args: &[Value]) -> Result<TypedFuture, Error> {
+                let [$($value),*] = expect_args::<$count>(name, args)?;
+                $(let $value = convert_arg::<$arg>(name, $position, $value)?;)*
+
+                let future = (self)($($value),*);
+                Ok(Box::pin(async move {
+                    future.await.map(Into::into).map_err(Into::into)
+                }))
+            }
+        }
+    };
+}
+
+impl_typed_function!(0);
+impl_typed_function!(1, 1 A a);
+impl_typed_function!(2, 1 A a, 2 B b);
+impl_typed_function!(3, 1 A a, 2 B b, 3 C c);
+impl_typed_function!(4, 1 A a, 2 B b, 3 C c, 4 D d);
+impl_typed_function!(5, 1 A a, 2 B b, 3 C c, 4 D d, 5 E e);
+impl_typed_function!(6, 1 A a, 2 B b, 3 C c, 4 D d, 5 E e, 6 F f);
+impl_typed_function!(7, 1 A a, 2 B b, 3 C c, 4 D d, 5 E e, 6 F f, 7 G g);
+impl_typed_function!(8, 1 A a, 2 B b, 3 C c, 4 D d, 5 E e, 6 F f, 7 G g, 8 H h);
+
+/// User-function that calls a typed function
+pub(crate) struct FnFunction<Func, Args> {
+    name: String,
+    function: Func,
+    args: PhantomData<fn(Args)>,
+}
+
+impl<Func, Args> FnFunction<Func, Args> {
+    pub(crate) fn new(name: impl Into<String>, function: Func) -> Self {
+        Self {
+            name: name.into(),
+            function,
+            args: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<Func: TypedFunction<Args>, Args> UserFunction for FnFunction<Func, Args> {
+    async fn call_args(&self, args: &[Value]) -> FunctionResult {
+        self.function.call_typed(&self.name, args)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// User-function that calls an async typed function
+pub(crate) struct AsyncFnFunction<Func, Args> {
+    name: String,
+    function: Func,
+    args: PhantomData<fn(Args)>,
+}
+
+impl<Func, Args> AsyncFnFunction<Func, Args> {
+    pub(crate) fn new(name: impl Into<String>, function: Func) -> Self {
+        Self {
+            name: name.into(),
+            function,
+            args: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<Func: AsyncTypedFunction<Args>, Args> UserFunction for AsyncFnFunction<Func, Args> {
+    async fn call_args(&self, args: &[Value]) -> FunctionResult {
+        Ok(self.function.call_typed(&self.name, args)?.await?)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
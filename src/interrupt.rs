@@ -0,0 +1,255 @@
+//! Stop rule evaluation when it takes too long or is cancelled
+
+use crate::{
+    error::{Error, Result},
+    timer::Alarm,
+};
+use std::{
+    collections::BTreeMap,
+    future::{poll_fn, Future},
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    task::{Poll, Waker},
+    time::{Duration, Instant},
+};
+
+/// Cancels evaluations that were started with it, see
+/// `RuleSet::evaluate_cancellable`
+///
+/// Tokens can be cloned and cancelled from any thread. Rules that are still
+/// being evaluated when the token is cancelled fail with `Error::Cancelled`,
+/// including rules that are waiting for a user-function.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<TokenState>);
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    wakers: Mutex<BTreeMap<u64, Waker>>,
+    next_id: AtomicU64,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the evaluations using this token
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+
+        let wakers = std::mem::take(&mut *self.wakers());
+        wakers.into_values().for_each(Waker::wake);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wake a future when the token is cancelled, the waker is removed from
+    /// the token when the returned guard is dropped
+    fn wake_on_cancel(&self) -> CancelGuard<'_> {
+        CancelGuard {
+            token: self,
+            id: self.0.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    fn wakers(&self) -> MutexGuard<'_, BTreeMap<u64, Waker>> {
+        self.0
+            .wakers
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Keeps the waker of a future that waits for a token to be cancelled
+struct CancelGuard<'a> {
+    token: &'a CancellationToken,
+    id: u64,
+}
+
+impl CancelGuard<'_> {
+    /// Wake the waker when the token is cancelled, replaces the waker that was
+    /// registered before
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.token.wakers();
+        match wakers.get_mut(&self.id) {
+            Some(registered) if registered.will_wake(waker) => {}
+            Some(registered) => *registered = waker.clone(),
+            None => {
+                wakers.insert(self.id, waker.clone());
+            }
+        }
+    }
+}
+
+impl Drop for CancelGuard<'_> {
+    fn drop(&mut self) {
+        self.token.wakers().remove(&self.id);
+    }
+}
+
+/// The deadline and cancellation token an evaluation is checked against
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Interrupt<'a> {
+    deadline: Option<Instant>,
+    rule_timeout: Option<Duration>,
+    token: Option<&'a CancellationToken>,
+}
+
+impl<'a> Interrupt<'a> {
+    /// Start an evaluation that has to finish within `timeout`, every rule has
+    /// to finish within `rule_timeout`
+    pub(crate) fn new(
+        timeout: Option<Duration>,
+        rule_timeout: Option<Duration>,
+        token: Option<&'a CancellationToken>,
+    ) -> Self {
+        Self {
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            rule_timeout,
+            token,
+        }
+    }
+
+    /// Start evaluating a rule, the rule deadline is never later than the
+    /// deadline of the evaluation
+    pub(crate) fn start_rule(&self) -> Self {
+        let rule_deadline = self.rule_timeout.map(|timeout| Instant::now() + timeout);
+
+        Self {
+            deadline: match (self.deadline, rule_deadline) {
+                (Some(deadline), Some(rule_deadline)) => Some(deadline.min(rule_deadline)),
+                (deadline, rule_deadline) => deadline.or(rule_deadline),
+            },
+            ..*self
+        }
+    }
+
+    /// Can evaluation be interrupted at all
+    pub(crate) fn is_active(&self) -> bool {
+        self.deadline.is_some() || self.token.is_some()
+    }
+
+    /// Fail when the evaluation was cancelled or the deadline has passed
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.token.is_some_and(CancellationToken::is_cancelled) {
+            return Err(Error::Cancelled);
+        }
+
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(Error::Timeout),
+            _ => Ok(()),
+        }
+    }
+
+    /// Run a future until it completes, the evaluation is cancelled or the
+    /// deadline passes
+    pub(crate) async fn run<T>(&self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        if !self.is_active() {
+            return future.await;
+        }
+
+        let mut future = pin!(future);
+        let alarm = self.deadline.map(Alarm::new);
+        let on_cancel = self.token.map(CancellationToken::wake_on_cancel);
+        poll_fn(|context| {
+            if let Poll::Ready(result) = future.as_mut().poll(context) {
+                return Poll::Ready(result);
+            }
+
+            if let Some(on_cancel) = &on_cancel {
+                on_cancel.register(context.waker());
+            }
+            if let Some(alarm) = &alarm {
+                alarm.register(context.waker());
+            }
+
+            match self.check() {
+                Ok(()) => Poll::Pending,
+                Err(error) => Poll::Ready(Err(error)),
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod when_interrupting_evaluation {
+    use super::*;
+    use crate::blocking::block_on;
+    use std::future::pending;
+
+    #[test]
+    fn should_not_interrupt_by_default() {
+        let interrupt = Interrupt::default();
+
+        assert!(!interrupt.is_active());
+        assert_eq!(block_on(interrupt.run(async { Ok(5) })).unwrap(), 5);
+    }
+
+    #[test]
+    fn should_time_out_pending_future() {
+        let interrupt = Interrupt::new(Some(Duration::from_millis(10)), None, None);
+
+        let result = block_on(interrupt.run(pending::<Result<()>>()));
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn should_use_earliest_deadline_for_rules() {
+        let interrupt = Interrupt::new(
+            Some(Duration::from_secs(60)),
+            Some(Duration::from_millis(10)),
+            None,
+        );
+
+        let result = block_on(interrupt.start_rule().run(pending::<Result<()>>()));
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+
+    #[test]
+    fn should_cancel_pending_future_from_other_thread() {
+        let token = CancellationToken::new();
+        let interrupt = Interrupt::new(None, None, Some(&token));
+
+        let cancel = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            cancel.cancel();
+        });
+
+        let result = block_on(interrupt.run(pending::<Result<()>>()));
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn should_remove_token_waker_when_finished() {
+        let token = CancellationToken::new();
+        let interrupt = Interrupt::new(None, None, Some(&token));
+
+        let mut polled = false;
+        let yield_once = poll_fn(|context| match std::mem::replace(&mut polled, true) {
+            true => Poll::Ready(Ok(5)),
+            false => {
+                context.waker().wake_by_ref();
+                Poll::Pending
+            }
+        });
+
+        assert_eq!(block_on(interrupt.run(yield_once)).unwrap(), 5);
+        assert!(token.wakers().is_empty());
+    }
+}
@@ -1,11 +1,12 @@
 //! Reval, short for Rust Evaluator is a light-weight expression evaluator library. It can be used as a rules-engine or in other situations where simple user expressions need to be evaluated.
 //!
-//! Expressions or rules can be written in a simple DSL or in a json format that is directly parsed into Reval expression AST objects. For now the Reval DSL parser is experimental but eventually this should replace json rules.
+//! Expressions or rules are written in a simple DSL that is parsed into Reval expression AST objects. Parsed rules and expressions implement `serde::Serialize` and `serde::Deserialize` so they can be stored, for example as json, and loaded again without parsing them.
 //!
 //! Expressions can be evaluated against input data to produce output data. The input and output data is passed in and out of the rules as a `reval::Value` object which can contain simple data or more complex structures like maps or vectors.
 //! Data can be nested so complex data can be passed into Reval expressions.
 //!
 //! To make it easy to construct input data from your own datatypes the Reval crate implements a serde serializer for Value types. So any type that implements `serde::Serialize` can be serialized into a `reval::Value` without writing any code.
+//! The other way around the results of evaluating rules can be deserialized into any type that implements `serde::Deserialize` using `Outcome::deserialize`.
 //!
 //! More information on writing rules can be found here: [Writing Rules](https://mendelt.github.io/reval/)
 //!
@@ -41,7 +42,7 @@
 //! # })
 //!  ```
 //!
-//! Reval can be extended with user-functions by implementing the `UserFunction` trait on a type and passing an instance of that type to the RuleSet. The following example shows how this would work;
+//! Reval can be extended with user-functions by implementing the `UserFunction` or `SingleArgFunction` trait on a type and passing an instance of that type to the RuleSet. The following example shows how this would work;
 //! The input data-type for the rules, must implement serde::Serialize so it
 //! can be serialized into a `reval::Value`
 //! ```rust
@@ -58,7 +59,7 @@
 //! // bypass the age check
 //! struct FakeId;
 //! #[async_trait::async_trait]
-//! impl UserFunction for FakeId {
+//! impl SingleArgFunction for FakeId {
 //!     async fn call(&self, param: Value) -> FunctionResult {
 //!        let age: i128 = param.try_into()?;
 //!        Ok((age * 2).into())
@@ -92,13 +93,30 @@
 //! }
 //! # })
 //!  ```
+//!
+//! The RuleSet supports more than these examples show:
+//!
+//! - User-functions receive any number of arguments in `UserFunction::call_args`, functions with a single parameter like `FakeId` can implement `SingleArgFunction` instead.
+//! - `Builder::with_fn` and `Builder::with_async_fn` register Rust functions and closures as user-functions, converting their arguments and results automatically.
+//! - `RuleSet::evaluate_blocking` evaluates rules without an async runtime.
+//! - `Rule::compile` compiles a rule that is evaluated often into a `CompiledRule`.
+//! - `Builder::with_concurrency` evaluates rules that call slow async user-functions concurrently.
+//! - `RuleSet::evaluate_batch`, `RuleSet::evaluate_stream` and `RuleSet::evaluate_batch_blocking` evaluate many records against a RuleSet.
+//! - `Builder::with_limits` limits the work a rule written by an untrusted user can do, see `EvalLimits`.
+//! - `Builder::with_rule_timeout`, `Builder::with_timeout` and `RuleSet::evaluate_cancellable` stop evaluations that take too long.
+//! - `Builder::with_numeric_policy` chooses whether operators promote numbers of different types, see `NumericPolicy`.
+//! - `Builder::with_logic_policy` chooses how logic operators treat `none`, see `LogicPolicy`.
 
+mod blocking;
 pub mod error;
 pub mod expr;
 pub mod function;
+pub mod interrupt;
+pub mod limits;
 pub mod parse;
 pub mod ruleset;
 pub mod symbol;
+mod timer;
 pub mod value;
 
 pub use error::{Error, Result};
@@ -106,9 +124,11 @@ pub use error::{Error, Result};
 pub mod prelude {
     pub use crate::{
         expr::Expr,
-        function::{FunctionResult, UserFunction},
-        ruleset::{ruleset, Builder, Rule, RuleSet},
+        function::{FunctionResult, SingleArgFunction, UserFunction},
+        interrupt::CancellationToken,
+        limits::EvalLimits,
+        ruleset::{ruleset, Builder, CompiledRule, Rule, RuleSet},
         symbol::Symbols,
-        value::Value,
+        value::{logic::LogicPolicy, numeric::NumericPolicy, Value},
     };
 }
@@ -0,0 +1,321 @@
+//! Limit the resources a single rule evaluation can use
+
+use crate::{
+    error::{Error, Result},
+    value::Value,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Limits on the work done evaluating a single rule, see `Builder::with_limits`
+///
+/// Every limit is disabled by default. Rules that exceed a limit fail with
+/// `Error::LimitExceeded` instead of running without bound.
+///
+/// ```rust
+/// use reval::prelude::*;
+///
+/// let ruleset = ruleset()
+///     .with_rule(Rule::parse("// squares\nfor x in xs map x * x").unwrap())
+///     .unwrap()
+///     .with_limits(EvalLimits::new().with_max_steps(10))
+///     .build();
+///
+/// let facts = serde_json::json!({"xs": [1, 2, 3, 4, 5, 6]});
+/// let outcomes = ruleset.evaluate_blocking(&facts).unwrap();
+/// assert!(outcomes[0].value.is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EvalLimits {
+    max_steps: Option<usize>,
+    max_depth: Option<usize>,
+    max_collection_len: Option<usize>,
+    max_string_len: Option<usize>,
+    max_function_calls: Option<usize>,
+}
+
+impl EvalLimits {
+    /// Limits that do not restrict evaluation
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of expressions evaluated, every iteration of a `for`
+    /// loop evaluates its body again
+    pub fn with_max_steps(mut self, max: usize) -> Self {
+        self.max_steps = Some(max);
+        self
+    }
+
+    /// Limit how deeply expressions are nested
+    pub fn with_max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// Limit the number of items in vecs and maps produced by the rule,
+    /// including the items of nested vecs and maps
+    pub fn with_max_collection_len(mut self, max: usize) -> Self {
+        self.max_collection_len = Some(max);
+        self
+    }
+
+    /// Limit the length in bytes of strings produced by the rule, including
+    /// strings nested in vecs and maps
+    pub fn with_max_string_len(mut self, max: usize) -> Self {
+        self.max_string_len = Some(max);
+        self
+    }
+
+    /// Limit the number of user-function calls
+    pub fn with_max_function_calls(mut self, max: usize) -> Self {
+        self.max_function_calls = Some(max);
+        self
+    }
+
+    /// Are steps or depth limited, these have to be tracked for every
+    /// expression
+    pub(crate) fn limits_steps(&self) -> bool {
+        self.max_steps.is_some() || self.max_depth.is_some()
+    }
+
+    /// Are the sizes of produced values limited
+    fn limits_size(&self) -> bool {
+        self.max_collection_len.is_some() || self.max_string_len.is_some()
+    }
+}
+
+/// The kind of limit that was exceeded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Steps,
+    Depth,
+    CollectionLen,
+    StringLen,
+    FunctionCalls,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Steps => write!(f, "evaluation steps"),
+            Limit::Depth => write!(f, "nested expressions"),
+            Limit::CollectionLen => write!(f, "collection items"),
+            Limit::StringLen => write!(f, "string bytes"),
+            Limit::FunctionCalls => write!(f, "user-function calls"),
+        }
+    }
+}
+
+/// Tracks the resources used by one rule evaluation against its limits
+pub(crate) struct Budget {
+    limits: EvalLimits,
+    steps: AtomicUsize,
+    depth: AtomicUsize,
+    calls: AtomicUsize,
+}
+
+impl Budget {
+    pub(crate) fn new(limits: EvalLimits) -> Self {
+        Self {
+            limits,
+            steps: AtomicUsize::new(0),
+            depth: AtomicUsize::new(0),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count a step evaluating an expression at a nesting depth
+    pub(crate) fn step(&self, depth: usize) -> Result<()> {
+        if let Some(max) = self.limits.max_steps {
+            check(
+                Limit::Steps,
+                self.steps.fetch_add(1, Ordering::Relaxed) + 1,
+                max,
+            )?;
+        }
+        if let Some(max) = self.limits.max_depth {
+            check(Limit::Depth, depth, max)?;
+        }
+        Ok(())
+    }
+
+    /// Enter a nested expression and count a step, every call has to be
+    /// matched by a call to `exit`
+    pub(crate) fn enter(&self) -> Result<()> {
+        match self.limits.limits_steps() {
+            true => self.step(self.depth.fetch_add(1, Ordering::Relaxed) + 1),
+            false => Ok(()),
+        }
+    }
+
+    pub(crate) fn exit(&self) {
+        if self.limits.limits_steps() {
+            self.depth.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count a user-function call
+    pub(crate) fn call(&self) -> Result<()> {
+        match self.limits.max_function_calls {
+            Some(max) => check(
+                Limit::FunctionCalls,
+                self.calls.fetch_add(1, Ordering::Relaxed) + 1,
+                max,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Count an item added to a collection that is being built, together with
+    /// the items nested in it. `len` is the number of items counted so far
+    pub(crate) fn collect(&self, len: &mut usize, item: &Value) -> Result<()> {
+        if !self.limits.limits_size() {
+            return Ok(());
+        }
+
+        *len += 1;
+        self.check_len(*len)?;
+        self.measure(item, len)
+    }
+
+    /// Check the size of a value that was produced. Items of nested vecs and
+    /// maps count towards the collection limit of the value and nested
+    /// strings are checked against the string limit
+    pub(crate) fn check_size(&self, value: &Value) -> Result<()> {
+        match self.limits.limits_size() {
+            true => self.measure(value, &mut 0),
+            false => Ok(()),
+        }
+    }
+
+    /// Add the items in a value and the items nested in them to `len`, stops
+    /// as soon as a limit is exceeded
+    fn measure(&self, value: &Value, len: &mut usize) -> Result<()> {
+        match value {
+            Value::Vec(vec) => {
+                *len += vec.len();
+                self.check_len(*len)?;
+                vec.iter().try_for_each(|item| self.measure(item, len))
+            }
+            Value::Map(map) => {
+                *len += map.len();
+                self.check_len(*len)?;
+                map.values().try_for_each(|item| self.measure(item, len))
+            }
+            Value::String(string) => match self.limits.max_string_len {
+                Some(max) => check(Limit::StringLen, string.len(), max),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn check_len(&self, len: usize) -> Result<()> {
+        match self.limits.max_collection_len {
+            Some(max) => check(Limit::CollectionLen, len, max),
+            None => Ok(()),
+        }
+    }
+}
+
+fn check(limit: Limit, value: usize, max: usize) -> Result<()> {
+    match value > max {
+        true => Err(Error::LimitExceeded { limit, max }),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod when_tracking_limits {
+    use super::*;
+
+    #[test]
+    fn should_not_limit_by_default() {
+        let budget = Budget::new(EvalLimits::new());
+
+        for _ in 0..1000 {
+            budget.enter().unwrap();
+            budget.call().unwrap();
+        }
+        budget.check_size(&"x".repeat(1000).into()).unwrap();
+    }
+
+    #[test]
+    fn should_fail_after_max_steps() {
+        let budget = Budget::new(EvalLimits::new().with_max_steps(2));
+
+        budget.step(1).unwrap();
+        budget.step(1).unwrap();
+        assert!(matches!(
+            budget.step(1),
+            Err(Error::LimitExceeded {
+                limit: Limit::Steps,
+                max: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn should_track_depth_between_enter_and_exit() {
+        let budget = Budget::new(EvalLimits::new().with_max_depth(2));
+
+        budget.enter().unwrap();
+        budget.enter().unwrap();
+        budget.exit();
+        budget.enter().unwrap();
+        assert!(matches!(
+            budget.enter(),
+            Err(Error::LimitExceeded {
+                limit: Limit::Depth,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn should_check_value_sizes() {
+        let budget = Budget::new(
+            EvalLimits::new()
+                .with_max_collection_len(2)
+                .with_max_string_len(3),
+        );
+
+        budget
+            .check_size(&Value::Vec(vec![1.into(), 2.into()]))
+            .unwrap();
+        budget.check_size(&"abc".into()).unwrap();
+        assert!(budget
+            .check_size(&Value::Vec(vec![1.into(), 2.into(), 3.into()]))
+            .is_err());
+        assert!(budget.check_size(&"abcd".into()).is_err());
+    }
+
+    #[test]
+    fn should_count_nested_items() {
+        let budget = Budget::new(
+            EvalLimits::new()
+                .with_max_collection_len(6)
+                .with_max_string_len(3),
+        );
+        let pair = Value::Vec(vec![1.into(), 2.into()]);
+
+        budget
+            .check_size(&Value::Vec(vec![pair.clone(), pair.clone()]))
+            .unwrap();
+        assert!(budget
+            .check_size(&Value::Vec(vec![pair.clone(), pair.clone(), pair.clone()]))
+            .is_err());
+        assert!(budget
+            .check_size(&Value::Vec(vec![Value::Vec(vec!["abcd".into()])]))
+            .is_err());
+
+        let mut len = 0;
+        budget.collect(&mut len, &pair).unwrap();
+        budget.collect(&mut len, &pair).unwrap();
+        assert_eq!(len, 6);
+        assert!(budget.collect(&mut len, &1.into()).is_err());
+    }
+}
@@ -1,11 +1,12 @@
-use super::Error;
+use super::{Error, SyntaxError};
 use crate::{expr::Expr, parse::reval};
 
 impl Expr {
     pub fn parse(input: &str) -> Result<Self, Error> {
         reval::ExprParser::new()
             .parse(input)
-            .map_err(|error| Error::ExprParseError(error.to_string()))
+            .map(|spanned| spanned.expr)
+            .map_err(|error| Error::ExprParseError(SyntaxError::from_parse_error(input, error)))
     }
 }
 
@@ -717,6 +718,50 @@ mod when_parsing_index_expression {
             Expr::index(Expr::reff("ref"), "index".into())
         );
     }
+
+    #[test]
+    fn should_parse_strict_index() {
+        assert_eq!(
+            Expr::parse("ref!.index.3").unwrap(),
+            Expr::index(
+                Expr::strict_index(Expr::reff("ref"), "index".into()),
+                3.into()
+            )
+        );
+        assert_eq!(
+            Expr::parse("ref.index!.3").unwrap().to_string(),
+            "((ref.index)!.3)"
+        );
+    }
+}
+
+#[cfg(test)]
+mod when_parsing_coalesce_expression {
+    use super::*;
+
+    #[test]
+    fn should_parse_coalesce_right_associatively() {
+        assert_eq!(
+            Expr::parse("a ?? b ?? c").unwrap().to_string(),
+            "(a ?? (b ?? c))"
+        );
+    }
+
+    #[test]
+    fn should_bind_coalesce_tighter_than_comparisons() {
+        assert_eq!(
+            Expr::parse("a.b ?? d0 > d5 and c").unwrap().to_string(),
+            "((((a.b) ?? d0) > d5) and c)"
+        );
+    }
+
+    #[test]
+    fn should_bind_coalesce_looser_than_arithmetic() {
+        assert_eq!(
+            Expr::parse("a ?? i1 + i2").unwrap().to_string(),
+            "(a ?? (i1 + i2))"
+        );
+    }
 }
 
 #[cfg(test)]
@@ -788,6 +833,33 @@ mod when_parsing_function_call {
             "function((i1 + i2))"
         );
     }
+
+    #[test]
+    fn should_parse_function_without_params() {
+        assert_eq!(
+            Expr::parse("function()").unwrap(),
+            Expr::func_args("function", [])
+        );
+    }
+
+    #[test]
+    fn should_parse_function_with_multiple_params() {
+        assert_eq!(
+            Expr::parse("function(i1, a, \"b\")").unwrap(),
+            Expr::func_args(
+                "function",
+                [Expr::value(1), Expr::reff("a"), Expr::value("b")]
+            )
+        );
+    }
+
+    #[test]
+    fn should_parse_function_with_trailing_comma() {
+        assert_eq!(
+            Expr::parse("function(i1, i2,)").unwrap(),
+            Expr::func_args("function", [Expr::value(1), Expr::value(2)])
+        );
+    }
 }
 
 #[cfg(test)]
@@ -893,4 +965,146 @@ mod when_parsing_for_map_filter {
             )
         );
     }
+
+    #[test]
+    fn should_parse_for_fold_expression() {
+        assert_eq!(
+            Expr::parse("for item in list fold total = i0 with total + item").unwrap(),
+            Expr::for_fold(
+                "item",
+                Expr::reff("list"),
+                "total",
+                Expr::value(0),
+                Expr::add(Expr::reff("total"), Expr::reff("item"))
+            )
+        );
+    }
+
+    #[test]
+    fn should_parse_for_sort_by_expression() {
+        assert_eq!(
+            Expr::parse("for item in list sort_by item.price").unwrap(),
+            Expr::for_sort_by(
+                "item",
+                Expr::reff("list"),
+                Expr::index(Expr::reff("item"), "price".into())
+            )
+        );
+    }
+
+    #[test]
+    fn should_parse_for_map_entries_expression() {
+        assert_eq!(
+            Expr::parse("for key, item in prices map key + item").unwrap(),
+            Expr::for_map_entries(
+                "key",
+                "item",
+                Expr::reff("prices"),
+                Expr::add(Expr::reff("key"), Expr::reff("item"))
+            )
+        );
+    }
+
+    #[test]
+    fn should_parse_for_filter_entries_expression() {
+        assert_eq!(
+            Expr::parse("for key, item in prices filter item > i3").unwrap(),
+            Expr::for_filter_entries(
+                "key",
+                "item",
+                Expr::reff("prices"),
+                Expr::gt(Expr::reff("item"), Expr::value(3))
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod when_parsing_let_expression {
+    use super::*;
+
+    #[test]
+    fn should_parse_let_expression() {
+        assert_eq!(
+            Expr::parse("let total = dec(order.total) in total * :vat").unwrap(),
+            Expr::let_in(
+                "total",
+                Expr::dec(Expr::index(Expr::reff("order"), "total".into())),
+                Expr::mult(Expr::reff("total"), Expr::symbol("vat"))
+            )
+        );
+    }
+
+    #[test]
+    fn should_parse_nested_let_expressions() {
+        assert_eq!(
+            Expr::parse("let a = i1 in let b = a + i1 in a * b")
+                .unwrap()
+                .to_string(),
+            "(let a = i1 in (let b = (a + i1) in (a * b)))"
+        );
+    }
+
+    #[test]
+    fn should_end_let_value_at_first_in() {
+        assert_eq!(
+            Expr::parse("let x = a in x in list").unwrap().to_string(),
+            "(let x = a in (list contains x))"
+        );
+        assert_eq!(
+            Expr::parse("let x = (a in list) in x").unwrap().to_string(),
+            "(let x = (list contains a) in x)"
+        );
+    }
+
+    #[test]
+    fn should_parse_if_as_let_value() {
+        assert_eq!(
+            Expr::parse("let x = if c then a else b in x")
+                .unwrap()
+                .to_string(),
+            "(let x = (if c then a else b) in x)"
+        );
+    }
+}
+
+#[cfg(test)]
+mod when_parsing_slice_expression {
+    use super::*;
+
+    #[test]
+    fn should_parse_slice_with_positions() {
+        assert_eq!(
+            Expr::parse("list[1..3]").unwrap(),
+            Expr::slice(
+                Expr::reff("list"),
+                Some(Expr::value(1)),
+                Some(Expr::value(3))
+            )
+        );
+    }
+
+    #[test]
+    fn should_parse_slice_with_open_positions() {
+        assert_eq!(
+            Expr::parse("list[..i-1]").unwrap(),
+            Expr::slice(Expr::reff("list"), None, Some(Expr::value(-1)))
+        );
+        assert_eq!(
+            Expr::parse("list.items[start..]").unwrap(),
+            Expr::slice(
+                Expr::index(Expr::reff("list"), "items".into()),
+                Some(Expr::reff("start")),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn should_display_slice_expression() {
+        assert_eq!(
+            Expr::parse("list[1..]").unwrap().to_string(),
+            "(list[i1..])"
+        );
+    }
 }
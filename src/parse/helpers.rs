@@ -1,10 +1,15 @@
 //! Helper methods for the lalrpop parser
 
 use crate::{
-    parse::unescape::{unescape, UnescapeError},
+    expr::Expr,
+    parse::{
+        span::Span,
+        unescape::{unescape, UnescapeError},
+    },
     value::Value,
 };
 use rust_decimal::{self, Decimal};
+use std::collections::BTreeMap;
 use std::num::{ParseFloatError, ParseIntError};
 use std::str::FromStr;
 use thiserror;
@@ -40,6 +45,10 @@ pub(crate) fn parse_string_literal(value: &str) -> Result<Value, RevalParseError
     Ok(Value::String(unescaped))
 }
 
+pub(crate) fn parse_index(value: &str) -> Result<usize, RevalParseError> {
+    Ok(usize::from_str(value)?)
+}
+
 #[derive(Clone, Debug, thiserror::Error, PartialEq)]
 pub(crate) enum RevalParseError {
     #[error("Error parsing integer")]
@@ -60,3 +69,116 @@ pub(crate) enum RevalParseError {
     #[error("Invalid value for rule name, must be a String value")]
     InvalidNameValue,
 }
+
+impl RevalParseError {
+    /// Attach the location of the offending source text to the error
+    pub(crate) fn at(self, start: usize, end: usize) -> SpannedParseError {
+        SpannedParseError {
+            span: Span::new(start, end),
+            error: self,
+        }
+    }
+}
+
+/// Parse error with the location of the source text that caused it
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SpannedParseError {
+    pub(crate) span: Span,
+    pub(crate) error: RevalParseError,
+}
+
+/// An expression together with the spans of all its nodes
+///
+/// The spans are stored in the same order `Expr::preorder` returns the nodes,
+/// so the first span covers the whole expression.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SpannedExpr {
+    pub(crate) expr: Expr,
+    pub(crate) spans: Vec<Span>,
+}
+
+impl SpannedExpr {
+    fn build(start: usize, end: usize, expr: Expr, children: Vec<Vec<Span>>) -> Self {
+        let spans = std::iter::once(Span::new(start, end))
+            .chain(children.into_iter().flatten())
+            .collect();
+
+        Self { expr, spans }
+    }
+}
+
+pub(crate) fn leaf(start: usize, expr: Expr, end: usize) -> SpannedExpr {
+    SpannedExpr::build(start, end, expr, Vec::new())
+}
+
+pub(crate) fn unary(
+    start: usize,
+    inner: SpannedExpr,
+    end: usize,
+    build: impl FnOnce(Expr) -> Expr,
+) -> SpannedExpr {
+    SpannedExpr::build(start, end, build(inner.expr), vec![inner.spans])
+}
+
+pub(crate) fn binary(
+    start: usize,
+    left: SpannedExpr,
+    right: SpannedExpr,
+    end: usize,
+    build: impl FnOnce(Expr, Expr) -> Expr,
+) -> SpannedExpr {
+    let expr = build(left.expr, right.expr);
+    SpannedExpr::build(start, end, expr, vec![left.spans, right.spans])
+}
+
+pub(crate) fn ternary(
+    start: usize,
+    first: SpannedExpr,
+    second: SpannedExpr,
+    third: SpannedExpr,
+    end: usize,
+    build: impl FnOnce(Expr, Expr, Expr) -> Expr,
+) -> SpannedExpr {
+    let expr = build(first.expr, second.expr, third.expr);
+    let spans = vec![first.spans, second.spans, third.spans];
+    SpannedExpr::build(start, end, expr, spans)
+}
+
+pub(crate) fn nary(
+    start: usize,
+    items: Vec<SpannedExpr>,
+    end: usize,
+    build: impl FnOnce(Vec<Expr>) -> Expr,
+) -> SpannedExpr {
+    let (exprs, spans) = items
+        .into_iter()
+        .map(|item| (item.expr, item.spans))
+        .unzip();
+    SpannedExpr::build(start, end, build(exprs), spans)
+}
+
+pub(crate) fn slice(
+    start: usize,
+    value: SpannedExpr,
+    from: Option<SpannedExpr>,
+    to: Option<SpannedExpr>,
+    end: usize,
+) -> SpannedExpr {
+    let spans = std::iter::once(value.spans)
+        .chain(from.as_ref().map(|from| from.spans.clone()))
+        .chain(to.as_ref().map(|to| to.spans.clone()))
+        .collect();
+    let expr = Expr::slice(value.expr, from.map(|from| from.expr), to.map(|to| to.expr));
+    SpannedExpr::build(start, end, expr, spans)
+}
+
+pub(crate) fn map(start: usize, items: Vec<(String, SpannedExpr)>, end: usize) -> SpannedExpr {
+    // Collecting into a map orders the items by key and keeps the last item
+    // for duplicate keys, just like `Expr::Map` does
+    let items: BTreeMap<String, SpannedExpr> = items.into_iter().collect();
+    let (exprs, spans) = items
+        .into_iter()
+        .map(|(key, item)| ((key, item.expr), item.spans))
+        .unzip();
+    SpannedExpr::build(start, end, Expr::Map(exprs), spans)
+}
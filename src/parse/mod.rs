@@ -3,8 +3,13 @@
 mod expr;
 mod helpers;
 mod rule;
+mod span;
+mod syntax_error;
 mod unescape;
 
+pub use span::Span;
+pub use syntax_error::SyntaxError;
+
 use lalrpop_util::lalrpop_mod;
 lalrpop_mod!(reval);
 
@@ -14,10 +19,10 @@ pub enum Error {
     MissingRuleName,
 
     #[error("Error parsing expression: {0}")]
-    ExprParseError(String),
+    ExprParseError(SyntaxError),
 
     #[error("Error parsing rule: {0}")]
-    RuleParseError(String),
+    RuleParseError(SyntaxError),
 
     #[error("Not a valid symbols file, expected a map definition")]
     SymbolsFileNotValid,
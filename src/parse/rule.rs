@@ -1,6 +1,11 @@
 use crate::{
     expr::Expr,
-    parse::{helpers::RevalParseError, reval, Error},
+    parse::{
+        helpers::{RevalParseError, SpannedExpr, SpannedParseError},
+        reval,
+        span::Span,
+        Error, SyntaxError,
+    },
     ruleset::Rule,
     value::Value,
 };
@@ -14,7 +19,7 @@ impl Rule {
 
         let mut rule_builder = reval::RuleParser::new()
             .parse(input)
-            .map_err(|error| Error::RuleParseError(error.to_string()))?;
+            .map_err(|error| Error::RuleParseError(SyntaxError::from_parse_error(input, error)))?;
 
         let (name, description) = match comment_lines.next() {
             Some(name_line) => {
@@ -39,7 +44,7 @@ impl Rule {
             rule_builder = rule_builder.set_description(&description);
         }
 
-        rule_builder.build()
+        rule_builder.build(input)
     }
 }
 
@@ -49,28 +54,31 @@ const NAME_META: &str = "name";
 /// Build rules from parsed components
 pub(crate) struct RuleBuilder {
     name: Option<String>,
-    expr: Expr,
+    expr: SpannedExpr,
     metadata: BTreeMap<String, Value>,
 }
 
 impl RuleBuilder {
-    pub(crate) fn parse(meta: Vec<(String, Expr)>, expr: Expr) -> Result<Self, RevalParseError> {
+    pub(crate) fn parse(
+        meta: Vec<(Span, String, Expr)>,
+        expr: SpannedExpr,
+    ) -> Result<Self, SpannedParseError> {
         let mut metadata: BTreeMap<String, Value> = BTreeMap::new();
         let mut name = None;
 
-        for (key, expr) in meta {
+        for (span, key, expr) in meta {
             match (&key[..], flatten(expr)) {
                 (NAME_META, Ok(Value::String(name_value))) => {
                     name = Some(name_value);
                 }
                 (NAME_META, Ok(_)) => {
-                    return Err(RevalParseError::InvalidNameValue);
+                    return Err(RevalParseError::InvalidNameValue.at(span.start, span.end));
                 }
                 (_, Ok(value)) => {
                     metadata.insert(key, value);
                 }
                 (_, Err(_)) => {
-                    return Err(RevalParseError::InvalidMetadata(key));
+                    return Err(RevalParseError::InvalidMetadata(key).at(span.start, span.end));
                 }
             }
         }
@@ -100,12 +108,13 @@ impl RuleBuilder {
         self
     }
 
-    pub(crate) fn build(self) -> Result<Rule, Error> {
+    pub(crate) fn build(self, source: &str) -> Result<Rule, Error> {
         Ok(Rule::new(
             self.name.ok_or(Error::MissingRuleName)?,
             self.metadata,
-            self.expr,
-        ))
+            self.expr.expr,
+        )
+        .with_source(source, self.expr.spans))
     }
 }
 
@@ -391,3 +400,49 @@ i3"#,
         assert_eq!(rule.description(), Some("new description"));
     }
 }
+
+#[cfg(test)]
+mod when_recording_spans {
+    use super::*;
+
+    /// Every expression in the rule should have a span that parses back to
+    /// the same expression
+    fn assert_spans_reparse(input: &str) {
+        let rule = Rule::parse(input).unwrap();
+
+        for node in rule.expr().preorder() {
+            let span = rule.span_of(node).unwrap();
+            assert_eq!(
+                &Expr::parse(&input[span.start..span.end]).unwrap(),
+                node,
+                "span {span} of {node}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_record_span_for_every_expression() {
+        assert_spans_reparse(
+            "// rule\nif a.b.0 > i5 and !c then [-d, f(e, i2 * x), g(), :sym] else {z: i1 + i2, y: dec(g) * h}",
+        );
+    }
+
+    #[test]
+    fn should_record_spans_for_swapped_in_operands() {
+        assert_spans_reparse("// rule\n(a + i1) in list and \"x\" starts s");
+    }
+
+    #[test]
+    fn should_record_spans_for_loops() {
+        assert_spans_reparse("// rule\nfor x in list filter x.a == ( i1 ) or is_some(x)");
+    }
+
+    #[test]
+    fn should_record_span_of_last_duplicate_map_key() {
+        let input = "// rule\n{a: i1, a: i2}";
+        let rule = Rule::parse(input).unwrap();
+
+        let item = rule.expr().children()[0];
+        assert_eq!(rule.span_of(item), Some(Span::new(19, 21)));
+    }
+}
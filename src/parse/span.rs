@@ -0,0 +1,119 @@
+//! Locations in the source text of parsed rules and expressions
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// A byte range in the source text that was parsed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Line and column of the start of the span, both starting at 1. The column
+    /// is counted in characters, not bytes
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        let start = clamp_to_char_boundary(source, self.start);
+        let before = &source[..start];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+        let column = source[line_start..start].chars().count() + 1;
+
+        (line, column)
+    }
+
+    /// Render the line that contains the start of the span with a caret
+    /// underneath the spanned text, like:
+    ///
+    /// ```text
+    ///   |
+    /// 1 | i5 + + i6
+    ///   |      ^
+    /// ```
+    pub fn snippet(&self, source: &str) -> String {
+        let start = clamp_to_char_boundary(source, self.start);
+        let (line, column) = self.location(source);
+
+        let line_start = source[..start]
+            .rfind('\n')
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let line_end = source[start..]
+            .find('\n')
+            .map(|index| start + index)
+            .unwrap_or(source.len());
+        let text = source[line_start..line_end].trim_end_matches('\r');
+
+        let end = clamp_to_char_boundary(source, self.end.clamp(start, line_end));
+        let width = source[start..end].chars().count().max(1);
+
+        let number = line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        format!(
+            "{gutter} |\n{number} | {text}\n{gutter} | {}{}",
+            " ".repeat(column - 1),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}..{}", self.start, self.end)
+    }
+}
+
+fn clamp_to_char_boundary(source: &str, index: usize) -> usize {
+    let mut index = index.min(source.len());
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod when_locating_spans {
+    use super::*;
+
+    #[test]
+    fn should_locate_span_on_first_line() {
+        assert_eq!(Span::new(3, 4).location("i5 + i6"), (1, 4));
+    }
+
+    #[test]
+    fn should_locate_span_on_later_line() {
+        assert_eq!(Span::new(9, 10).location("i5\n+ i6\n+ x"), (3, 2));
+    }
+
+    #[test]
+    fn should_count_columns_in_characters() {
+        assert_eq!(Span::new(7, 8).location("\"ü\" + x"), (1, 7));
+    }
+
+    #[test]
+    fn should_render_snippet_with_caret() {
+        assert_eq!(
+            Span::new(5, 6).snippet("i5 + + i6"),
+            "  |\n1 | i5 + + i6\n  |      ^"
+        );
+    }
+
+    #[test]
+    fn should_underline_whole_span_on_later_line() {
+        assert_eq!(
+            Span::new(8, 12).snippet("i5 +\n   true"),
+            "  |\n2 |    true\n  |    ^^^^"
+        );
+    }
+
+    #[test]
+    fn should_render_caret_after_end_of_input() {
+        assert_eq!(Span::new(4, 4).snippet("i5 +"), "  |\n1 | i5 +\n  |     ^");
+    }
+}
@@ -0,0 +1,377 @@
+//! Structured errors for rules and expressions that could not be parsed
+
+use crate::parse::{helpers::SpannedParseError, span::Span};
+use itertools::Itertools;
+use lalrpop_util::{lexer::Token, ParseError};
+use std::fmt::Display;
+
+/// Describes where and why parsing failed
+///
+/// Contains the byte span of the offending text, its line and column, the
+/// tokens the parser expected instead and a rendered snippet of the source
+/// with the offending text underlined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    message: String,
+    span: Span,
+    line: usize,
+    column: usize,
+    expected: Vec<String>,
+    snippet: String,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(
+        source: &str,
+        message: impl Into<String>,
+        span: Span,
+        expected: Vec<String>,
+    ) -> Self {
+        let (line, column) = span.location(source);
+
+        Self {
+            message: message.into(),
+            span,
+            line,
+            column,
+            expected,
+            snippet: span.snippet(source),
+        }
+    }
+
+    pub(crate) fn from_parse_error(
+        source: &str,
+        error: ParseError<usize, Token<'_>, SpannedParseError>,
+    ) -> Self {
+        match error {
+            ParseError::InvalidToken { location } => {
+                let width = source[location..]
+                    .chars()
+                    .next()
+                    .map(char::len_utf8)
+                    .unwrap_or(0);
+                Self::new(
+                    source,
+                    "Invalid token",
+                    Span::new(location, location + width),
+                    Vec::new(),
+                )
+            }
+            ParseError::UnrecognizedEof { location, expected } => Self::new(
+                source,
+                "Unexpected end of input",
+                Span::new(location, location),
+                readable_tokens(&expected),
+            ),
+            ParseError::UnrecognizedToken {
+                token: (start, token, end),
+                expected,
+            } => Self::new(
+                source,
+                format!("Unexpected token `{token}`"),
+                Span::new(start, end),
+                readable_tokens(&expected),
+            ),
+            ParseError::ExtraToken {
+                token: (start, token, end),
+            } => Self::new(
+                source,
+                format!("Unexpected token `{token}` after end of expression"),
+                Span::new(start, end),
+                Vec::new(),
+            ),
+            ParseError::User { error } => {
+                Self::new(source, error.error.to_string(), error.span, Vec::new())
+            }
+        }
+    }
+
+    /// Short description of what went wrong
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Byte span of the offending text in the source
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Line of the offending text, starting at 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Column of the offending text in characters, starting at 1
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Readable descriptions of the tokens the parser expected instead
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// The offending source line with a caret underneath the offending text
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{} at {}:{}",
+            self.message, self.line, self.column
+        )?;
+
+        match self.expected.as_slice() {
+            [] => Ok(()),
+            [expected] => write!(formatter, ", expected {expected}"),
+            [expected @ .., last] => write!(
+                formatter,
+                ", expected one of {} or {last}",
+                expected.iter().join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// Tokens that continue an expression with an operator
+const OPERATOR_TOKENS: [&str; 25] = [
+    "KWD_AND",
+    "KWD_OR",
+    "KWD_IN",
+    "KWD_CONTAINS",
+    "KWD_STARTS",
+    "KWD_ENDS",
+    "OP_EQ1",
+    "OP_EQ2",
+    "OP_NEQ",
+    "OP_GT",
+    "OP_LT",
+    "OP_GTE",
+    "OP_LTE",
+    "OP_ADD",
+    "OP_SUB",
+    "OP_MULT",
+    "OP_DIV",
+    "OP_REM",
+    "OP_BIT_AND",
+    "OP_BIT_OR",
+    "OP_BIT_XOR",
+    "OP_COALESCE",
+    "DOT",
+    "OP_STRICT_DOT",
+    "LBRACKET",
+];
+
+/// Tokens besides the operators that can not start an expression
+const NON_STARTING_TOKENS: [&str; 12] = [
+    "KWD_THEN",
+    "KWD_ELSE",
+    "KWD_MAP",
+    "KWD_FILTER",
+    "KWD_FOLD",
+    "KWD_WITH",
+    "KWD_SORT_BY",
+    "OP_RANGE",
+    "OP_META",
+    "COMMA",
+    "SEMICOLON",
+    "INDEX",
+];
+
+/// Translate the terminal names reported by the parser to readable
+/// descriptions. When the parser expects the start of an expression, all
+/// tokens that could start one are summarized as "expression". In the same
+/// way all operators that could continue an expression are summarized as
+/// "operator".
+fn readable_tokens(expected: &[String]) -> Vec<String> {
+    let expects = |name: &str| expected.iter().any(|token| token == name);
+    let expects_expression = expects("IDENT") && expects("STRING");
+    let expects_operator = expects("OP_ADD") && expects("KWD_AND");
+
+    let is_summarized = |token: &str| {
+        let is_operator = OPERATOR_TOKENS.contains(&token);
+        // `-` is both a binary operator and the start of a negation, `[` both
+        // starts a slice and a vec
+        let is_starter = (!is_operator || token == "OP_SUB" || token == "LBRACKET")
+            && !NON_STARTING_TOKENS.contains(&token);
+
+        (expects_expression && is_starter) || (expects_operator && is_operator)
+    };
+
+    let summaries = [
+        expects_expression.then_some("expression"),
+        expects_operator.then_some("operator"),
+    ];
+
+    summaries
+        .into_iter()
+        .flatten()
+        .map(str::to_string)
+        .chain(
+            expected
+                .iter()
+                .filter(|token| !is_summarized(token))
+                .map(|token| readable_token(token)),
+        )
+        .unique()
+        .collect()
+}
+
+fn readable_token(token: &str) -> String {
+    let description = match token {
+        "IDENT" => "identifier",
+        "INDEX" => "index",
+        "STRING" => "string",
+        "INT" | "HEX_INT" | "OCT_INT" | "BIN_INT" => "integer",
+        "FLOAT" => "float",
+        "DECIMAL" => "decimal",
+        "TRUE" => "`true`",
+        "FALSE" => "`false`",
+        "OP_EQ1" => "`=`",
+        "OP_EQ2" => "`==`",
+        "OP_NEQ" => "`!=`",
+        "OP_GT" => "`>`",
+        "OP_LT" => "`<`",
+        "OP_GTE" => "`>=`",
+        "OP_LTE" => "`<=`",
+        "OP_ADD" => "`+`",
+        "OP_SUB" => "`-`",
+        "OP_MULT" => "`*`",
+        "OP_DIV" => "`/`",
+        "OP_REM" => "`%`",
+        "OP_NOT" => "`!`",
+        "OP_BIT_AND" => "`&`",
+        "OP_BIT_OR" => "`|`",
+        "OP_BIT_XOR" => "`^`",
+        "OP_META" => "`@`",
+        "OP_COALESCE" => "`??`",
+        "OP_STRICT_DOT" => "`!.`",
+        "OP_RANGE" => "`..`",
+        "COMMA" => "`,`",
+        "COLON" => "`:`",
+        "SEMICOLON" => "`;`",
+        "DOT" => "`.`",
+        "LPAREN" => "`(`",
+        "RPAREN" => "`)`",
+        "LBRACKET" => "`[`",
+        "RBRACKET" => "`]`",
+        "LBRACE" => "`{`",
+        "RBRACE" => "`}`",
+        _ => {
+            return match token.strip_prefix("KWD_") {
+                Some(keyword) => format!("`{}`", keyword.to_lowercase()),
+                None => token.to_string(),
+            }
+        }
+    };
+
+    description.to_string()
+}
+
+#[cfg(test)]
+mod when_reporting_parse_errors {
+    use crate::{
+        expr::Expr,
+        parse::{Error, Span, SyntaxError},
+        ruleset::Rule,
+    };
+
+    fn expr_error(input: &str) -> SyntaxError {
+        match Expr::parse(input) {
+            Err(Error::ExprParseError(error)) => error,
+            other => panic!("Expected a syntax error, got {other:?}"),
+        }
+    }
+
+    fn rule_error(input: &str) -> SyntaxError {
+        match Rule::parse(input) {
+            Err(Error::RuleParseError(error)) => error,
+            other => panic!("Expected a syntax error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn should_locate_unexpected_token() {
+        let error = expr_error("i5 + * i6");
+
+        assert_eq!(error.message(), "Unexpected token `*`");
+        assert_eq!(error.span(), Span::new(5, 6));
+        assert_eq!((error.line(), error.column()), (1, 6));
+    }
+
+    #[test]
+    fn should_summarize_expected_expression() {
+        assert_eq!(expr_error("i5 + * i6").expected(), ["expression"]);
+    }
+
+    #[test]
+    fn should_list_readable_expected_tokens() {
+        assert_eq!(
+            expr_error("{one: i1 two: i2}").expected(),
+            ["operator", "`,`", "`}`"]
+        );
+    }
+
+    #[test]
+    fn should_describe_keywords_as_source_text() {
+        assert_eq!(
+            expr_error("if true i1 else i2").expected(),
+            ["operator", "`then`"]
+        );
+    }
+
+    #[test]
+    fn should_report_unexpected_end_of_input() {
+        let error = expr_error("i5 +\n");
+
+        assert_eq!(error.message(), "Unexpected end of input");
+        assert_eq!(error.span(), Span::new(4, 4));
+        assert_eq!(error.expected(), ["expression"]);
+    }
+
+    #[test]
+    fn should_report_invalid_token() {
+        let error = expr_error("i5 + $");
+
+        assert_eq!(error.message(), "Invalid token");
+        assert_eq!(error.span(), Span::new(5, 6));
+    }
+
+    #[test]
+    fn should_locate_invalid_literal() {
+        let error = expr_error("i1 + i99999999999999999999999999999999999999999");
+
+        assert_eq!(error.message(), "Error parsing integer");
+        assert_eq!(error.span(), Span::new(5, 47));
+    }
+
+    #[test]
+    fn should_locate_errors_on_later_lines() {
+        let error = rule_error("// rule name\ni5 +\n  i6 i7");
+
+        assert_eq!((error.line(), error.column()), (3, 6));
+        assert_eq!(error.snippet(), "  |\n3 |   i6 i7\n  |      ^^");
+    }
+
+    #[test]
+    fn should_locate_invalid_metadata() {
+        let error = rule_error("@name: \"rule\";\n@score: a + i1;\ni5");
+
+        assert_eq!(error.message(), "Invalid metadata expression: score");
+        assert_eq!((error.line(), error.column()), (2, 1));
+    }
+
+    #[test]
+    fn should_display_message_location_and_expected_tokens() {
+        assert_eq!(
+            expr_error("{one: i1 two: i2}").to_string(),
+            "Unexpected token `two` at 1:10, expected one of operator, `,` or `}`"
+        );
+    }
+}
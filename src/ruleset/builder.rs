@@ -1,10 +1,15 @@
 use crate::{
     error::{Error, Result},
-    function::{UserFunction, UserFunctions},
+    expr::Plan,
+    function::{
+        AsyncFnFunction, AsyncTypedFunction, FnFunction, TypedFunction, UserFunction, UserFunctions,
+    },
+    limits::EvalLimits,
     ruleset::{rule::Rule, RuleSet},
     symbol::Symbols,
-    value::Value,
+    value::{logic::LogicPolicy, numeric::NumericPolicy, Value},
 };
+use std::time::Duration;
 
 /// Start building a ruleset
 pub fn ruleset() -> Builder {
@@ -12,6 +17,13 @@ pub fn ruleset() -> Builder {
         rules: Vec::new(),
         functions: Default::default(),
         symbols: Default::default(),
+        concurrency: 1,
+        batch_concurrency: 1,
+        limits: EvalLimits::default(),
+        timeout: None,
+        rule_timeout: None,
+        numeric_policy: NumericPolicy::default(),
+        logic_policy: LogicPolicy::default(),
     }
 }
 
@@ -20,6 +32,13 @@ pub struct Builder {
     rules: Vec<Rule>,
     functions: UserFunctions,
     symbols: Symbols,
+    concurrency: usize,
+    batch_concurrency: usize,
+    limits: EvalLimits,
+    timeout: Option<Duration>,
+    rule_timeout: Option<Duration>,
+    numeric_policy: NumericPolicy,
+    logic_policy: LogicPolicy,
 }
 
 impl Builder {
@@ -52,6 +71,70 @@ impl Builder {
         Ok(self)
     }
 
+    /// Add a Rust function or closure as a user-function to the ruleset.
+    /// Arguments are converted from `Value`s using `TryFrom<Value>` and the
+    /// result is converted back using `Into<Value>`. Calling the function with
+    /// the wrong number or type of arguments returns an
+    /// `Error::InvalidFunctionArgs` that names the function.
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    ///
+    /// let ruleset = ruleset()
+    ///     .with_fn("allowed", |age: i64, country: String| -> anyhow::Result<bool> {
+    ///         Ok(age >= if country == "US" { 21 } else { 18 })
+    ///     })
+    ///     .unwrap()
+    ///     .with_rule(Rule::parse(r#"// allowed
+    ///         allowed(i19, "NL")"#).unwrap())
+    ///     .unwrap()
+    ///     .build();
+    ///
+    /// let outcomes = ruleset.evaluate(&()).await.unwrap();
+    /// assert_eq!(outcomes[0].value.as_ref().unwrap(), &true.into());
+    /// # })
+    /// ```
+    pub fn with_fn<Args: 'static>(
+        mut self,
+        name: impl Into<String>,
+        function: impl TypedFunction<Args>,
+    ) -> Result<Self> {
+        self.functions
+            .add_function(FnFunction::new(name, function))?;
+        Ok(self)
+    }
+
+    /// Add an async Rust function or closure as a user-function to the
+    /// ruleset, arguments and results are converted like in `with_fn`
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    ///
+    /// let ruleset = ruleset()
+    ///     .with_async_fn("double", |value: i64| async move {
+    ///         Ok::<_, anyhow::Error>(value * 2)
+    ///     })
+    ///     .unwrap()
+    ///     .with_rule(Rule::parse("// double\ndouble(i21)").unwrap())
+    ///     .unwrap()
+    ///     .build();
+    ///
+    /// let outcomes = ruleset.evaluate(&()).await.unwrap();
+    /// assert_eq!(outcomes[0].value.as_ref().unwrap(), &42.into());
+    /// # })
+    /// ```
+    pub fn with_async_fn<Args: 'static>(
+        mut self,
+        name: impl Into<String>,
+        function: impl AsyncTypedFunction<Args>,
+    ) -> Result<Self> {
+        self.functions
+            .add_function(AsyncFnFunction::new(name, function))?;
+        Ok(self)
+    }
+
     /// Add multiple boxed user-functions to the ruleset
     pub fn with_functions(
         mut self,
@@ -73,12 +156,162 @@ impl Builder {
         Ok(self)
     }
 
+    /// Evaluate up to `limit` rules concurrently. Rules still run on the task
+    /// that evaluates the RuleSet, this lets rules that wait for async
+    /// user-functions overlap. Outcomes are returned in rule order. The
+    /// default limit of 1 evaluates rules one after another
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    ///
+    /// let ruleset = ruleset()
+    ///     .with_rule(Rule::parse("// one\ni1").unwrap())
+    ///     .unwrap()
+    ///     .with_rule(Rule::parse("// two\ni2").unwrap())
+    ///     .unwrap()
+    ///     .with_concurrency(8)
+    ///     .build();
+    ///
+    /// let outcomes = ruleset.evaluate(&()).await.unwrap();
+    /// assert_eq!(outcomes[0].rule.name(), "one");
+    /// assert_eq!(outcomes[1].rule.name(), "two");
+    /// # })
+    /// ```
+    pub fn with_concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+
+    /// Evaluate up to `limit` records concurrently in `RuleSet::evaluate_batch`
+    /// and `RuleSet::evaluate_stream`. Outcomes are returned in record order.
+    /// The default limit of 1 evaluates records one after another
+    pub fn with_batch_concurrency(mut self, limit: usize) -> Self {
+        self.batch_concurrency = limit.max(1);
+        self
+    }
+
+    /// Limit the work each rule evaluation can do, rules that exceed a limit
+    /// fail with `Error::LimitExceeded`. Evaluation is not limited by default
+    pub fn with_limits(mut self, limits: EvalLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Stop evaluating the RuleSet after `timeout`, rules that have not
+    /// finished by then return `Error::Timeout` as their outcome
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Stop evaluating a rule after `timeout`, including rules that are
+    /// waiting for a user-function that does not return. A rule that times
+    /// out returns `Error::Timeout` as its outcome, other rules are still
+    /// evaluated
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    /// use std::{future::pending, time::Duration};
+    ///
+    /// let ruleset = ruleset()
+    ///     .with_rule(Rule::parse("// hangs\nhang(i1)").unwrap())
+    ///     .unwrap()
+    ///     .with_rule(Rule::parse("// answers\ni42").unwrap())
+    ///     .unwrap()
+    ///     .with_async_fn("hang", |_: Value| pending::<anyhow::Result<Value>>())
+    ///     .unwrap()
+    ///     .with_rule_timeout(Duration::from_millis(10))
+    ///     .build();
+    ///
+    /// let outcomes = ruleset.evaluate(&()).await.unwrap();
+    /// assert!(matches!(
+    ///     outcomes[0].value.as_ref().unwrap_err().without_span(),
+    ///     reval::Error::Timeout
+    /// ));
+    /// assert_eq!(outcomes[1].value.as_ref().unwrap(), &42.into());
+    /// # })
+    /// ```
+    pub fn with_rule_timeout(mut self, timeout: Duration) -> Self {
+        self.rule_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how operators treat numbers of different types. By default numbers
+    /// are promoted to a common type, `NumericPolicy::Strict` only applies
+    /// operators to numbers of the same type
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    ///
+    /// let rule = Rule::parse("// total\ni5 + d1.5").unwrap();
+    ///
+    /// let promoting = ruleset().with_rule(rule.clone()).unwrap().build();
+    /// let outcomes = promoting.evaluate(&()).await.unwrap();
+    /// assert_eq!(outcomes[0].value.as_ref().unwrap().to_string(), "d6.5");
+    ///
+    /// let strict = ruleset()
+    ///     .with_rule(rule)
+    ///     .unwrap()
+    ///     .with_numeric_policy(NumericPolicy::Strict)
+    ///     .build();
+    /// let outcomes = strict.evaluate(&()).await.unwrap();
+    /// assert!(outcomes[0].value.is_err());
+    /// # })
+    /// ```
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Self {
+        self.numeric_policy = policy;
+        self
+    }
+
+    /// Set how `and`, `or`, `!` and `^` treat `none`. By default `none` is an
+    /// unknown truth value that propagates unless the other side decides the
+    /// result, `LogicPolicy::NoneIsFalse` treats `none` as `false`
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    ///
+    /// let rule = Rule::parse("// check\nnone or false").unwrap();
+    ///
+    /// let kleene = ruleset().with_rule(rule.clone()).unwrap().build();
+    /// let outcomes = kleene.evaluate(&()).await.unwrap();
+    /// assert_eq!(outcomes[0].value.as_ref().unwrap(), &Value::None);
+    ///
+    /// let none_is_false = ruleset()
+    ///     .with_rule(rule)
+    ///     .unwrap()
+    ///     .with_logic_policy(LogicPolicy::NoneIsFalse)
+    ///     .build();
+    /// let outcomes = none_is_false.evaluate(&()).await.unwrap();
+    /// assert_eq!(outcomes[0].value.as_ref().unwrap(), &false.into());
+    /// # })
+    /// ```
+    pub fn with_logic_policy(mut self, policy: LogicPolicy) -> Self {
+        self.logic_policy = policy;
+        self
+    }
+
     /// Finalize the builder and create the RuleSet
     pub fn build(self) -> RuleSet {
         RuleSet {
+            plans: self
+                .rules
+                .iter()
+                .map(|rule| Plan::compile(rule.expr()))
+                .collect(),
             rules: self.rules,
             functions: self.functions,
             symbols: self.symbols,
+            concurrency: self.concurrency,
+            batch_concurrency: self.batch_concurrency,
+            limits: self.limits,
+            timeout: self.timeout,
+            rule_timeout: self.rule_timeout,
+            numeric_policy: self.numeric_policy,
+            logic_policy: self.logic_policy,
         }
     }
 }
@@ -0,0 +1,98 @@
+use crate::{
+    blocking::block_on,
+    error::{Error, Result},
+    expr::Plan,
+    interrupt::{CancellationToken, Interrupt},
+    ruleset::{Rule, RuleSet},
+    value::Value,
+};
+
+/// A rule compiled into a flat evaluation plan, see `Rule::compile`
+///
+/// Compiled rules give the same results as evaluating the rule expression
+/// directly but avoid allocating a future for every expression. Fact
+/// references are resolved once when compiling and only the values that are
+/// used get cloned. Evaluation only suspends when a user-function is called.
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    rule: Rule,
+    plan: Plan,
+}
+
+impl CompiledRule {
+    pub(super) fn new(rule: Rule) -> Self {
+        let plan = Plan::compile(rule.expr());
+        Self { rule, plan }
+    }
+
+    /// The rule this was compiled from
+    pub fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    /// Return the name of the rule
+    pub fn name(&self) -> &str {
+        self.rule.name()
+    }
+
+    /// Evaluate the compiled rule against a value, using the user-functions
+    /// and symbols from a ruleset. Errors are located in the rule source like
+    /// they are for `RuleSet::evaluate`. The limits and timeouts of the
+    /// ruleset apply to the compiled rule
+    pub async fn evaluate(&self, ruleset: &RuleSet, facts: &Value) -> Result<Value> {
+        run_plan(
+            &self.rule,
+            &self.plan,
+            ruleset,
+            facts,
+            ruleset.interrupt(None).start_rule(),
+        )
+        .await
+    }
+
+    /// Evaluate the compiled rule against a value, stops when the token is
+    /// cancelled and returns `Error::Cancelled`, see
+    /// `RuleSet::evaluate_cancellable`
+    pub async fn evaluate_cancellable(
+        &self,
+        ruleset: &RuleSet,
+        facts: &Value,
+        token: &CancellationToken,
+    ) -> Result<Value> {
+        run_plan(
+            &self.rule,
+            &self.plan,
+            ruleset,
+            facts,
+            ruleset.interrupt(Some(token)).start_rule(),
+        )
+        .await
+    }
+
+    /// Evaluate the compiled rule without an async runtime by blocking on
+    /// `evaluate`. Must not be called from inside an async runtime, see
+    /// `RuleSet::evaluate_blocking`
+    pub fn evaluate_blocking(&self, ruleset: &RuleSet, facts: &Value) -> Result<Value> {
+        block_on(self.evaluate(ruleset, facts))
+    }
+}
+
+/// Run the plan of a rule, errors are located in the rule source like they are
+/// for the tree walking evaluator
+pub(super) async fn run_plan(
+    rule: &Rule,
+    plan: &Plan,
+    ruleset: &RuleSet,
+    facts: &Value,
+    interrupt: Interrupt<'_>,
+) -> Result<Value> {
+    plan.run(ruleset, facts, interrupt)
+        .await
+        .map_err(|(error, node)| match rule.span_at_position(node) {
+            Some(span) => Error::Located {
+                span,
+                error: Box::new(error),
+            },
+            None => error,
+        })
+}
@@ -1,25 +1,53 @@
 //! Manage and evaluate a set of rules or expressions using RuleSets
 
 mod builder;
+mod compiled;
 mod rule;
 
 pub use self::{
     builder::{ruleset, Builder},
+    compiled::CompiledRule,
     rule::Rule,
 };
 use crate::{
+    blocking::block_on,
     error::Result,
+    expr::{Expr, Plan},
     function::UserFunctions,
+    interrupt::{CancellationToken, Interrupt},
+    limits::EvalLimits,
     symbol::Symbols,
-    value::{ser::ValueSerializer, Value},
+    value::{
+        de::from_value, logic::LogicPolicy, numeric::NumericPolicy, ser::ValueSerializer, Value,
+    },
 };
-use serde::Serialize;
+use futures_util::{stream, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{future::Future, time::Duration};
 
 #[derive(Default)]
 pub struct RuleSet {
     rules: Vec<Rule>,
+    /// The rules compiled for evaluation without an async runtime, in the
+    /// same order as the rules
+    plans: Vec<Plan>,
     functions: UserFunctions,
     symbols: Symbols,
+    /// The maximum number of rules to evaluate concurrently, rules are
+    /// evaluated one after another for 0 or 1
+    concurrency: usize,
+    /// The maximum number of records to evaluate concurrently in a batch
+    batch_concurrency: usize,
+    /// Limits on the work done evaluating each rule
+    limits: EvalLimits,
+    /// Deadline for evaluating all rules
+    timeout: Option<Duration>,
+    /// Deadline for evaluating a single rule
+    rule_timeout: Option<Duration>,
+    /// How operators treat numbers of different types
+    numeric_policy: NumericPolicy,
+    /// How logic operators treat `none`
+    logic_policy: LogicPolicy,
 }
 
 impl RuleSet {
@@ -29,23 +57,245 @@ impl RuleSet {
             .await
     }
 
+    /// Evaluate the rules in the RuleSet against a piece of data, stops when
+    /// the token is cancelled. Rules that were not finished return
+    /// `Error::Cancelled` as their outcome
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    ///
+    /// let ruleset = ruleset()
+    ///     .with_rule(Rule::parse("// answer\ni42").unwrap())
+    ///     .unwrap()
+    ///     .build();
+    ///
+    /// let token = CancellationToken::new();
+    /// token.cancel();
+    ///
+    /// let outcomes = ruleset.evaluate_cancellable(&(), &token).await.unwrap();
+    /// assert!(matches!(
+    ///     outcomes[0].value.as_ref().unwrap_err().without_span(),
+    ///     reval::Error::Cancelled
+    /// ));
+    /// # })
+    /// ```
+    pub async fn evaluate_cancellable(
+        &self,
+        facts: &impl Serialize,
+        token: &CancellationToken,
+    ) -> Result<Vec<Outcome<'_>>> {
+        self.evaluate_value_cancellable(&facts.serialize(ValueSerializer)?, token)
+            .await
+    }
+
+    /// Evaluate the rules in the RuleSet against a piece of data without an
+    /// async runtime. The rules are evaluated on the current thread using
+    /// their compiled plans, see `Rule::compile`, so evaluating an expression
+    /// does not allocate a future. The thread is only parked while async
+    /// user-functions wait. User-functions that depend on a specific async
+    /// runtime, like tokio timers or io, still need that runtime to be running
+    /// on another thread.
+    ///
+    /// Do not call this from inside an async runtime, parking a runtime thread
+    /// blocks the other tasks on it and deadlocks when a user-function waits
+    /// for one of them. Use `evaluate` there instead.
+    ///
+    /// ```rust
+    /// use reval::prelude::*;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let ruleset = ruleset()
+    ///     .with_rule(Rule::parse("// adult\nage >= i18").unwrap())
+    ///     .unwrap()
+    ///     .build();
+    ///
+    /// let outcomes = ruleset
+    ///     .evaluate_blocking(&BTreeMap::from([("age", 21)]))
+    ///     .unwrap();
+    /// assert_eq!(outcomes[0].value.as_ref().unwrap(), &true.into());
+    /// ```
+    pub fn evaluate_blocking(&self, facts: &impl Serialize) -> Result<Vec<Outcome<'_>>> {
+        self.evaluate_value_blocking(&facts.serialize(ValueSerializer)?)
+    }
+
+    /// Evaluate the rules in the RuleSet against a value without an async
+    /// runtime. Must not be called from inside an async runtime, see
+    /// `evaluate_blocking`
+    pub fn evaluate_value_blocking(&self, facts: &Value) -> Result<Vec<Outcome<'_>>> {
+        block_on(self.evaluate_compiled(facts, self.interrupt(None)))
+    }
+
+    /// Evaluate the rules in the RuleSet against many records, yields the
+    /// outcomes for every record in the order of the records. Records are
+    /// evaluated concurrently up to the limit set with
+    /// `Builder::with_batch_concurrency`
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use futures_util::StreamExt;
+    /// use reval::prelude::*;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let ruleset = ruleset()
+    ///     .with_rule(Rule::parse("// adult\nage >= i18").unwrap())
+    ///     .unwrap()
+    ///     .with_batch_concurrency(4)
+    ///     .build();
+    ///
+    /// let records = [12, 18, 40].map(|age| BTreeMap::from([("age", age)]));
+    /// let outcomes: Vec<_> = ruleset.evaluate_batch(records).collect().await;
+    ///
+    /// assert_eq!(outcomes[0].as_ref().unwrap()[0].value.as_ref().unwrap(), &false.into());
+    /// assert_eq!(outcomes[2].as_ref().unwrap()[0].value.as_ref().unwrap(), &true.into());
+    /// # })
+    /// ```
+    pub fn evaluate_batch<'a, T: Serialize + 'a>(
+        &'a self,
+        facts: impl IntoIterator<Item = T, IntoIter: 'a>,
+    ) -> impl Stream<Item = Result<Vec<Outcome<'a>>>> + 'a {
+        self.evaluate_stream(stream::iter(facts))
+    }
+
+    /// Evaluate the rules in the RuleSet against a stream of records, see
+    /// `evaluate_batch`
+    pub fn evaluate_stream<'a, T: Serialize + 'a>(
+        &'a self,
+        facts: impl Stream<Item = T> + 'a,
+    ) -> impl Stream<Item = Result<Vec<Outcome<'a>>>> + 'a {
+        facts
+            .map(move |facts| async move { self.evaluate(&facts).await })
+            .buffered(self.batch_concurrency.max(1))
+    }
+
+    /// Evaluate the rules in the RuleSet against many records without an
+    /// async runtime, returns the outcomes for every record in the order of
+    /// the records. With the `parallel` feature records are evaluated in
+    /// parallel on the rayon thread pool. Must not be called from inside an
+    /// async runtime, see `evaluate_blocking`
+    pub fn evaluate_batch_blocking<T: Serialize + Send>(
+        &self,
+        facts: impl IntoIterator<Item = T>,
+    ) -> Vec<Result<Vec<Outcome<'_>>>> {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+
+            facts
+                .into_iter()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|facts| self.evaluate_blocking(&facts))
+                .collect()
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            facts
+                .into_iter()
+                .map(|facts| self.evaluate_blocking(&facts))
+                .collect()
+        }
+    }
+
     pub async fn evaluate_value(&self, facts: &Value) -> Result<Vec<Outcome<'_>>> {
-        let mut results = Vec::new();
+        self.evaluate_interruptible(facts, self.interrupt(None))
+            .await
+    }
+
+    /// Evaluate the rules in the RuleSet against a value, stops when the token
+    /// is cancelled, see `evaluate_cancellable`
+    pub async fn evaluate_value_cancellable(
+        &self,
+        facts: &Value,
+        token: &CancellationToken,
+    ) -> Result<Vec<Outcome<'_>>> {
+        self.evaluate_interruptible(facts, self.interrupt(Some(token)))
+            .await
+    }
 
-        for rule in self.rules.iter() {
-            results.push(Outcome {
-                value: rule.expr().eval_rule(self, facts).await,
+    async fn evaluate_interruptible(
+        &self,
+        facts: &Value,
+        interrupt: Interrupt<'_>,
+    ) -> Result<Vec<Outcome<'_>>> {
+        let outcomes = self.rules.iter().map(|rule| async move {
+            Outcome {
+                value: Expr::eval_rule(rule, self, facts, interrupt.start_rule()).await,
                 rule,
+            }
+        });
+
+        self.collect_outcomes(outcomes).await
+    }
+
+    /// Evaluate the compiled plans of the rules, gives the same outcomes as
+    /// `evaluate_interruptible`
+    async fn evaluate_compiled(
+        &self,
+        facts: &Value,
+        interrupt: Interrupt<'_>,
+    ) -> Result<Vec<Outcome<'_>>> {
+        let outcomes = self
+            .rules
+            .iter()
+            .zip(&self.plans)
+            .map(|(rule, plan)| async move {
+                Outcome {
+                    value: compiled::run_plan(rule, plan, self, facts, interrupt.start_rule())
+                        .await,
+                    rule,
+                }
             });
+
+        self.collect_outcomes(outcomes).await
+    }
+
+    /// Await the outcomes of the rules, concurrently up to the concurrency
+    /// of the RuleSet
+    async fn collect_outcomes<'a>(
+        &self,
+        outcomes: impl Iterator<Item = impl Future<Output = Outcome<'a>>>,
+    ) -> Result<Vec<Outcome<'a>>> {
+        if self.concurrency <= 1 {
+            let mut results = Vec::with_capacity(self.rules.len());
+            for outcome in outcomes {
+                results.push(outcome.await);
+            }
+            return Ok(results);
         }
 
-        Ok(results)
+        Ok(stream::iter(outcomes)
+            .buffered(self.concurrency)
+            .collect()
+            .await)
     }
 
-    pub(crate) async fn call_function(&self, name: &str, params: Value) -> Result<Value> {
+    pub(crate) async fn call_function(&self, name: &str, params: &[Value]) -> Result<Value> {
         self.functions.call(name, params).await
     }
 
+    pub(crate) fn has_function(&self, name: &str) -> bool {
+        self.functions.contains(name)
+    }
+
+    /// Start an evaluation with the timeouts of the RuleSet
+    pub(crate) fn interrupt<'a>(&self, token: Option<&'a CancellationToken>) -> Interrupt<'a> {
+        Interrupt::new(self.timeout, self.rule_timeout, token)
+    }
+
+    pub(crate) fn numeric_policy(&self) -> NumericPolicy {
+        self.numeric_policy
+    }
+
+    pub(crate) fn logic_policy(&self) -> LogicPolicy {
+        self.logic_policy
+    }
+
+    pub(crate) fn limits(&self) -> EvalLimits {
+        self.limits
+    }
+
     pub(crate) fn get_symbol(&self, symbol: &str) -> Option<&Value> {
         self.symbols.get(symbol)
     }
@@ -58,3 +308,58 @@ pub struct Outcome<'a> {
     pub value: Result<Value>,
     pub rule: &'a Rule,
 }
+
+impl Outcome<'_> {
+    /// Deserialize the resulting value into a Rust type, returns the
+    /// evaluation error if the rule did not evaluate successfully
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    /// use rust_decimal::Decimal;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Approval {
+    ///     approved: bool,
+    ///     limit: Decimal,
+    /// }
+    ///
+    /// let rule = Rule::parse("// approval\n{approved: true, limit: d500}").unwrap();
+    /// let ruleset = ruleset().with_rule(rule).unwrap().build();
+    ///
+    /// for outcome in ruleset.evaluate(&()).await.unwrap() {
+    ///     let approval: Approval = outcome.deserialize().unwrap();
+    ///     assert!(approval.approved);
+    ///     assert_eq!(approval.limit, Decimal::new(500, 0));
+    /// }
+    /// # })
+    /// ```
+    pub fn deserialize<T: DeserializeOwned>(self) -> Result<T> {
+        from_value(self.value?)
+    }
+
+    /// Render the rule source line that caused the evaluation error with the
+    /// failing expression underlined. Returns `None` when the rule evaluated
+    /// successfully or was not parsed from source
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let rule = Rule::parse("// discount\nif vip then i10 else d5 + \"2\"").unwrap();
+    /// let ruleset = ruleset().with_rule(rule).unwrap().build();
+    ///
+    /// let outcomes = ruleset.evaluate(&BTreeMap::from([("vip", false)])).await.unwrap();
+    /// assert_eq!(
+    ///     outcomes[0].error_snippet().unwrap(),
+    ///     "  |\n2 | if vip then i10 else d5 + \"2\"\n  |                      ^^^^^^^^"
+    /// );
+    /// # })
+    /// ```
+    pub fn error_snippet(&self) -> Option<String> {
+        let span = self.value.as_ref().err()?.span()?;
+        Some(span.snippet(self.rule.source()?))
+    }
+}
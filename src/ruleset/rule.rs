@@ -1,12 +1,22 @@
-use crate::{expr::Expr, value::Value};
+use crate::{expr::Expr, parse::Span, ruleset::CompiledRule, value::Value};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 /// A rule is an expression with a name
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Rules can be serialized and deserialized using serde so parsed rules can be
+/// stored and loaded again without parsing them. They are represented as a
+/// struct with `name`, `metadata`, `expr`, `source` and `spans` fields. The
+/// last two are only set for parsed rules and may be left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rule {
     pub(super) name: String,
     metadata: BTreeMap<String, Value>,
     expr: Expr,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    spans: Vec<Span>,
 }
 
 impl Rule {
@@ -16,9 +26,19 @@ impl Rule {
             name: name.into(),
             metadata,
             expr,
+            source: None,
+            spans: Vec::new(),
         }
     }
 
+    /// Store the source text the rule was parsed from together with the spans
+    /// of all expressions in `Expr::preorder` order
+    pub(crate) fn with_source(mut self, source: &str, spans: Vec<Span>) -> Self {
+        self.source = Some(source.to_string());
+        self.spans = spans;
+        self
+    }
+
     /// Return the name of the rule
     pub fn name(&self) -> &str {
         &self.name
@@ -46,4 +66,55 @@ impl Rule {
     pub fn expr(&self) -> &Expr {
         &self.expr
     }
+
+    /// The source text this rule was parsed from
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// The location in the source text of an expression in this rule
+    pub fn span_of(&self, expr: &Expr) -> Option<Span> {
+        self.span_at(expr)
+    }
+
+    pub(crate) fn span_at(&self, expr: *const Expr) -> Option<Span> {
+        if self.spans.is_empty() {
+            return None;
+        }
+
+        let position = self
+            .expr
+            .preorder()
+            .into_iter()
+            .position(|node| std::ptr::eq(node, expr))?;
+
+        self.span_at_position(position)
+    }
+
+    /// The span of the expression at a position in `Expr::preorder` order
+    pub(crate) fn span_at_position(&self, position: usize) -> Option<Span> {
+        self.spans.get(position).copied()
+    }
+
+    /// Compile the rule into a flat evaluation plan that can be evaluated
+    /// repeatedly without walking the expression tree
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use reval::prelude::*;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let ruleset = ruleset().build();
+    /// let rule = Rule::parse("// adult\nperson.age >= i18").unwrap().compile();
+    ///
+    /// let facts = Value::Map(BTreeMap::from([(
+    ///     "person".to_string(),
+    ///     Value::Map(BTreeMap::from([("age".to_string(), Value::Int(21))])),
+    /// )]));
+    /// assert_eq!(rule.evaluate(&ruleset, &facts).await.unwrap(), true.into());
+    /// # })
+    /// ```
+    pub fn compile(&self) -> CompiledRule {
+        CompiledRule::new(self.clone())
+    }
 }
@@ -0,0 +1,177 @@
+//! Wake futures at a deadline without an async runtime. A single background
+//! thread sleeps until the earliest registered deadline and wakes the futures
+//! that are waiting for it
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Condvar, Mutex, MutexGuard,
+    },
+    task::Waker,
+    thread,
+    time::Instant,
+};
+
+/// Registered wakers by deadline, the id keeps entries with the same deadline
+/// apart
+type Queue = BTreeMap<(Instant, u64), Waker>;
+
+struct Timer {
+    queue: Mutex<Queue>,
+    changed: Condvar,
+    next_id: AtomicU64,
+}
+
+lazy_static::lazy_static!(
+    static ref TIMER: &'static Timer = {
+        let timer: &'static Timer = Box::leak(Box::new(Timer {
+            queue: Mutex::new(BTreeMap::new()),
+            changed: Condvar::new(),
+            next_id: AtomicU64::new(0),
+        }));
+
+        thread::Builder::new()
+            .name("reval-timer".to_string())
+            .spawn(|| timer.run())
+            .expect("start the timer thread");
+
+        timer
+    };
+);
+
+impl Timer {
+    fn run(&self) {
+        let mut queue = self.queue();
+
+        loop {
+            let now = Instant::now();
+            queue = match queue.first_key_value() {
+                None => self
+                    .changed
+                    .wait(queue)
+                    .unwrap_or_else(|error| error.into_inner()),
+                Some(((deadline, _), _)) if *deadline <= now => {
+                    if let Some((_, waker)) = queue.pop_first() {
+                        waker.wake();
+                    }
+                    queue
+                }
+                Some(((deadline, _), _)) => {
+                    let timeout = *deadline - now;
+                    self.changed
+                        .wait_timeout(queue, timeout)
+                        .unwrap_or_else(|error| error.into_inner())
+                        .0
+                }
+            };
+        }
+    }
+
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+/// Wakes a future when a deadline has passed. The future registers its waker
+/// every time it is polled, this keeps a single entry in the timer queue that
+/// is removed when the alarm is dropped
+pub(crate) struct Alarm {
+    key: (Instant, u64),
+}
+
+impl Alarm {
+    pub(crate) fn new(deadline: Instant) -> Self {
+        let id = TIMER.next_id.fetch_add(1, Ordering::Relaxed);
+        Self {
+            key: (deadline, id),
+        }
+    }
+
+    /// Wake the waker when the deadline has passed, replaces the waker that
+    /// was registered before. The waker is woken once, a future that is still
+    /// waiting after being polled again registers again
+    pub(crate) fn register(&self, waker: &Waker) {
+        let timer = *TIMER;
+        let mut queue = timer.queue();
+
+        match queue.get_mut(&self.key) {
+            Some(registered) if registered.will_wake(waker) => {}
+            Some(registered) => *registered = waker.clone(),
+            None => {
+                queue.insert(self.key, waker.clone());
+                timer.changed.notify_one();
+            }
+        }
+    }
+}
+
+impl Drop for Alarm {
+    fn drop(&mut self) {
+        TIMER.queue().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod when_waiting_for_deadlines {
+    use super::*;
+    use crate::blocking::block_on;
+    use futures_util::task::noop_waker_ref;
+    use std::{future::poll_fn, task::Poll, time::Duration};
+
+    fn sleep_until(deadline: Instant) -> impl std::future::Future<Output = ()> {
+        let alarm = Alarm::new(deadline);
+        poll_fn(move |context| match Instant::now() >= deadline {
+            true => Poll::Ready(()),
+            false => {
+                alarm.register(context.waker());
+                Poll::Pending
+            }
+        })
+    }
+
+    #[test]
+    fn should_wake_after_deadline() {
+        let deadline = Instant::now() + Duration::from_millis(20);
+        block_on(sleep_until(deadline));
+
+        assert!(Instant::now() >= deadline);
+    }
+
+    #[test]
+    fn should_wake_earlier_deadline_first() {
+        let start = Instant::now();
+        let late = std::thread::spawn(move || {
+            block_on(sleep_until(start + Duration::from_millis(500)));
+        });
+
+        block_on(sleep_until(start + Duration::from_millis(10)));
+        assert!(start.elapsed() < Duration::from_millis(250));
+        late.join().unwrap();
+    }
+
+    #[test]
+    fn should_keep_one_entry_per_alarm() {
+        let alarm = Alarm::new(Instant::now() + Duration::from_secs(60));
+        (0..3).for_each(|_| alarm.register(noop_waker_ref()));
+
+        let entries = TIMER
+            .queue()
+            .keys()
+            .filter(|key| **key == alarm.key)
+            .count();
+        assert_eq!(entries, 1);
+    }
+
+    #[test]
+    fn should_remove_entry_when_alarm_is_dropped() {
+        let alarm = Alarm::new(Instant::now() + Duration::from_secs(60));
+        alarm.register(noop_waker_ref());
+
+        let key = alarm.key;
+        assert!(TIMER.queue().contains_key(&key));
+
+        drop(alarm);
+        assert!(!TIMER.queue().contains_key(&key));
+    }
+}
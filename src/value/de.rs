@@ -0,0 +1,547 @@
+//! Deserialize Rust types from `Value`s
+//!
+//! This is the counterpart of the `ValueSerializer`, it allows the `Value`
+//! returned from a rule to be turned back into any type that implements
+//! `serde::Deserialize`. `DateTime` values are passed to visitors as RFC 3339
+//! strings, `Duration` values as a `(seconds, nanoseconds)` tuple and
+//! `Decimal` values as strings. These are the formats that chrono and
+//! rust_decimal use, so these types can be deserialized directly.
+
+use crate::{
+    error::{Error, Result},
+    value::Value,
+};
+use rust_decimal::prelude::*;
+use serde::{
+    de::{
+        value::StringDeserializer, DeserializeOwned, DeserializeSeed, EnumAccess, Error as DeError,
+        IntoDeserializer, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+use std::{
+    collections::{btree_map, BTreeMap},
+    fmt::Display,
+    vec,
+};
+
+/// Deserialize a type from a `Value`
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    T::deserialize(ValueDeserializer::new(value))
+}
+
+impl DeError for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::de(msg.to_string())
+    }
+}
+
+/// Deserializer that reads a `Value`
+pub struct ValueDeserializer {
+    value: Value,
+}
+
+impl ValueDeserializer {
+    pub fn new(value: Value) -> Self {
+        Self { value }
+    }
+}
+
+impl IntoDeserializer<'_, Error> for Value {
+    type Deserializer = ValueDeserializer;
+
+    fn into_deserializer(self) -> ValueDeserializer {
+        ValueDeserializer::new(self)
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::String(value) => visitor.visit_string(value),
+            Value::Int(value) => visit_int(value, visitor),
+            Value::Float(value) => visitor.visit_f64(value),
+            Value::Decimal(value) => visitor.visit_string(value.to_string()),
+            Value::Bool(value) => visitor.visit_bool(value),
+            Value::DateTime(value) => visitor.visit_string(value.to_rfc3339()),
+            Value::Duration(value) => {
+                // Split into the (seconds, nanoseconds) tuple chrono uses for
+                // TimeDelta, nanoseconds are always positive
+                let (mut secs, mut nanos) = (value.num_seconds(), value.subsec_nanos());
+                if nanos < 0 {
+                    secs -= 1;
+                    nanos += 1_000_000_000;
+                }
+                visit_seq(vec![secs.into(), nanos.into()], visitor)
+            }
+            Value::Vec(values) => visit_seq(values, visitor),
+            Value::Map(map) => visit_map(map, visitor),
+            Value::None => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Decimal(value) => match value.to_f64() {
+                Some(value) => visitor.visit_f64(value),
+                None => Err(Error::invalid_cast(Value::Decimal(value), "f64")),
+            },
+            value => ValueDeserializer::new(value).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::None => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            // Unit variants are serialized as plain strings
+            Value::String(variant) => visitor.visit_enum(EnumDeserializer {
+                variant,
+                value: None,
+            }),
+            // Other variants are serialized as a map with a single entry
+            Value::Map(map) if map.len() == 1 => {
+                let (variant, value) = map.into_iter().next().unwrap();
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            value => Err(Error::unexpected_val_type(value, "enum variant")),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Pass integers to the visitor as the smallest type that fits, most visitors
+/// do not support 128 bit integers
+fn visit_int<'de, V: Visitor<'de>>(value: i128, visitor: V) -> Result<V::Value> {
+    if let Ok(value) = i64::try_from(value) {
+        visitor.visit_i64(value)
+    } else if let Ok(value) = u64::try_from(value) {
+        visitor.visit_u64(value)
+    } else {
+        visitor.visit_i128(value)
+    }
+}
+
+fn visit_seq<'de, V: Visitor<'de>>(values: Vec<Value>, visitor: V) -> Result<V::Value> {
+    let len = values.len();
+    let mut deserializer = SeqDeserializer {
+        iter: values.into_iter(),
+    };
+    let result = visitor.visit_seq(&mut deserializer)?;
+
+    match deserializer.iter.len() {
+        0 => Ok(result),
+        _ => Err(Error::invalid_length(len, &"fewer elements in sequence")),
+    }
+}
+
+fn visit_map<'de, V: Visitor<'de>>(map: BTreeMap<String, Value>, visitor: V) -> Result<V::Value> {
+    let len = map.len();
+    let mut deserializer = MapDeserializer {
+        iter: map.into_iter(),
+        value: None,
+    };
+    let result = visitor.visit_map(&mut deserializer)?;
+
+    match deserializer.iter.len() {
+        0 => Ok(result),
+        _ => Err(Error::invalid_length(len, &"fewer elements in map")),
+    }
+}
+
+struct SeqDeserializer {
+    iter: vec::IntoIter<Value>,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        self.iter
+            .next()
+            .map(|value| seed.deserialize(ValueDeserializer::new(value)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer {
+    iter: btree_map::IntoIter<String, Value>,
+    value: Option<Value>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key_deserializer(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        // Panic because this indicates a bug in the program rather than an
+        // expected failure.
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(ValueDeserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+fn key_deserializer(key: String) -> StringDeserializer<Error> {
+    key.into_deserializer()
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed.deserialize(key_deserializer(self.variant))?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer {
+    value: Option<Value>,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None | Some(Value::None) => Ok(()),
+            Some(value) => Deserialize::deserialize(ValueDeserializer::new(value)),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        match self.value {
+            Some(value) => seed.deserialize(ValueDeserializer::new(value)),
+            None => Err(DeError::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Some(Value::Vec(values)) => visit_seq(values, visitor),
+            Some(value) => Err(Error::unexpected_val_type(value, "tuple variant")),
+            None => Err(DeError::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            Some(Value::Map(map)) => visit_map(map, visitor),
+            Some(value) => Err(Error::unexpected_val_type(value, "struct variant")),
+            None => Err(DeError::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod when_deserializing_value {
+    use super::*;
+    use crate::value::ser::ValueSerializer;
+    use chrono::{prelude::*, TimeDelta};
+    use serde::{Deserialize, Serialize};
+    use std::{collections::BTreeMap, fmt::Debug};
+
+    #[test]
+    fn should_deserialize_bool() {
+        assert_deserialized(Value::Bool(true), true);
+    }
+
+    #[test]
+    fn should_deserialize_ints() {
+        assert_deserialized(Value::Int(8), 8u8);
+        assert_deserialized(Value::Int(-8), -8i64);
+        assert_deserialized(Value::Int(u64::MAX.into()), u64::MAX);
+        assert_deserialized(Value::Int(i128::MIN), i128::MIN);
+    }
+
+    #[test]
+    fn should_not_deserialize_int_out_of_range() {
+        assert!(from_value::<u8>(Value::Int(256)).is_err());
+    }
+
+    #[test]
+    fn should_deserialize_float() {
+        assert_deserialized(Value::Float(1.5), 1.5f64);
+    }
+
+    #[test]
+    fn should_deserialize_string() {
+        assert_deserialized(
+            Value::String("String val".to_owned()),
+            "String val".to_owned(),
+        );
+    }
+
+    #[test]
+    fn should_deserialize_decimal() {
+        assert_deserialized(Value::Decimal(Decimal::new(5025, 2)), Decimal::new(5025, 2));
+    }
+
+    #[test]
+    fn should_deserialize_decimal_to_float() {
+        assert_deserialized(Value::Decimal(Decimal::new(5025, 2)), 50.25f64);
+    }
+
+    #[test]
+    fn should_deserialize_datetime() {
+        let datetime = Utc.with_ymd_and_hms(2015, 7, 30, 3, 26, 13).unwrap();
+        assert_deserialized(Value::DateTime(datetime), datetime);
+    }
+
+    #[test]
+    fn should_deserialize_duration() {
+        assert_deserialized(Value::Duration(TimeDelta::hours(2)), TimeDelta::hours(2));
+    }
+
+    #[test]
+    fn should_deserialize_negative_fractional_duration() {
+        let duration = TimeDelta::milliseconds(-1500);
+        assert_deserialized(Value::Duration(duration), duration);
+    }
+
+    #[test]
+    fn should_deserialize_none_to_option() {
+        assert_deserialized::<Option<String>>(Value::None, None);
+    }
+
+    #[test]
+    fn should_deserialize_some_option_from_inner_type() {
+        assert_deserialized(Value::Int(5), Some(5u32));
+    }
+
+    #[test]
+    fn should_deserialize_vec() {
+        assert_deserialized(
+            Value::Vec(vec![Value::Int(-16), Value::Int(8)]),
+            vec![-16, 8],
+        );
+    }
+
+    #[test]
+    fn should_deserialize_tuple() {
+        assert_deserialized(
+            Value::Vec(vec![Value::Int(-16), Value::String("Test".to_owned())]),
+            (-16, "Test".to_owned()),
+        );
+    }
+
+    #[test]
+    fn should_not_deserialize_tuple_from_longer_vec() {
+        assert!(from_value::<(i32, i32)>(Value::Vec(vec![1.into(), 2.into(), 3.into()])).is_err());
+    }
+
+    #[test]
+    fn should_deserialize_map() {
+        assert_deserialized(
+            Value::Map(BTreeMap::from([("key".to_owned(), Value::Int(5))])),
+            BTreeMap::from([("key".to_owned(), 5)]),
+        );
+    }
+
+    #[test]
+    fn should_deserialize_struct_from_map() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Outcome {
+            approved: bool,
+            limit: Decimal,
+        }
+
+        assert_deserialized(
+            Value::Map(BTreeMap::from([
+                ("approved".to_owned(), Value::Bool(true)),
+                ("limit".to_owned(), Value::Decimal(Decimal::new(500, 0))),
+            ])),
+            Outcome {
+                approved: true,
+                limit: Decimal::new(500, 0),
+            },
+        );
+    }
+
+    #[test]
+    fn should_not_deserialize_struct_with_missing_field() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Data {
+            age: u16,
+        }
+
+        assert!(matches!(
+            from_value::<Data>(Value::Map(BTreeMap::new())),
+            Err(Error::ValueDeserializationError(_))
+        ));
+    }
+
+    #[test]
+    fn should_deserialize_enum_unit_variant_from_str() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum TestEnum {
+            Variant,
+        }
+
+        assert_deserialized(Value::String("Variant".to_owned()), TestEnum::Variant);
+    }
+
+    #[test]
+    fn should_deserialize_enum_val_variant() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum TestEnum {
+            Variant(u64),
+        }
+
+        assert_deserialized(
+            Value::Map(BTreeMap::from([("Variant".to_owned(), Value::Int(14))])),
+            TestEnum::Variant(14),
+        );
+    }
+
+    #[test]
+    fn should_deserialize_enum_tuple_variant() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum TestEnum {
+            Variant(u64, String),
+        }
+
+        assert_deserialized(
+            Value::Map(BTreeMap::from([(
+                "Variant".to_owned(),
+                Value::Vec(vec![Value::Int(14), Value::String("Test".to_owned())]),
+            )])),
+            TestEnum::Variant(14, "Test".to_owned()),
+        );
+    }
+
+    #[test]
+    fn should_deserialize_enum_struct_variant() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum TestEnum {
+            Variant { value: u64 },
+        }
+
+        assert_deserialized(
+            Value::Map(BTreeMap::from([(
+                "Variant".to_owned(),
+                Value::Map(BTreeMap::from([("value".to_owned(), Value::Int(16))])),
+            )])),
+            TestEnum::Variant { value: 16 },
+        );
+    }
+
+    #[test]
+    fn should_deserialize_enum_with_tagged_variants() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(tag = "type")]
+        enum TestEnum {
+            Variant { value: u64 },
+        }
+
+        assert_deserialized(
+            Value::Map(BTreeMap::from([
+                ("type".to_owned(), Value::String("Variant".to_owned())),
+                ("value".to_owned(), Value::Int(16)),
+            ])),
+            TestEnum::Variant { value: 16 },
+        );
+    }
+
+    #[test]
+    fn should_roundtrip_through_value_serializer() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Data {
+            name: String,
+            tags: Vec<String>,
+            nickname: Option<String>,
+            created: DateTime<Utc>,
+            valid_for: TimeDelta,
+        }
+
+        let data = Data {
+            name: "Frank".to_owned(),
+            tags: vec!["one".to_owned(), "two".to_owned()],
+            nickname: None,
+            created: Utc.with_ymd_and_hms(2015, 7, 30, 3, 26, 13).unwrap(),
+            valid_for: TimeDelta::days(3),
+        };
+
+        let value = data.serialize(ValueSerializer).unwrap();
+        assert_eq!(from_value::<Data>(value).unwrap(), data);
+    }
+
+    /// Helper method that tests if deserializing a value yields the expected result
+    fn assert_deserialized<T: DeserializeOwned + PartialEq + Debug>(value: Value, expected: T) {
+        assert_eq!(from_value::<T>(value).unwrap(), expected);
+    }
+}
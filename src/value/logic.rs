@@ -0,0 +1,159 @@
+//! Apply logic operators to booleans and `none`
+
+use crate::{
+    error::{Error, Result},
+    value::Value,
+};
+
+/// How `and`, `or`, `!` and `^` treat `none`, see `Builder::with_logic_policy`
+///
+/// With `Kleene` logic `none` is an unknown truth value. The result is known
+/// when the other side decides it and `none` otherwise:
+///
+/// | `a`     | `b`     | `a and b` | `a or b` | `a ^ b` | `!a`    |
+/// | ------- | ------- | --------- | -------- | ------- | ------- |
+/// | `true`  | `none`  | `none`    | `true`   | `none`  | `false` |
+/// | `false` | `none`  | `false`   | `none`   | `none`  | `true`  |
+/// | `none`  | `true`  | `none`    | `true`   | `none`  | `none`  |
+/// | `none`  | `false` | `false`   | `none`   | `none`  | `none`  |
+/// | `none`  | `none`  | `none`    | `none`   | `none`  | `none`  |
+///
+/// With `NoneIsFalse` every `none` is treated as `false` and the operators
+/// always return a boolean.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogicPolicy {
+    /// Three-valued logic like SQL, `none` propagates unless the other side
+    /// decides the result
+    #[default]
+    Kleene,
+
+    /// Treat `none` as `false`
+    NoneIsFalse,
+}
+
+impl LogicPolicy {
+    /// The truth value of an operand, `None` when it is unknown
+    pub(crate) fn truth(self, operation: &str, value: &Value) -> Result<Option<bool>> {
+        match (self, value) {
+            (_, Value::Bool(value)) => Ok(Some(*value)),
+            (LogicPolicy::Kleene, Value::None) => Ok(None),
+            (LogicPolicy::NoneIsFalse, Value::None) => Ok(Some(false)),
+            (_, value) => Err(Error::invalid_type(operation, [value])),
+        }
+    }
+
+    /// Combine the operands of an `and` or `or`, `decisive` is the value that
+    /// decides the result on its own, `false` for `and` and `true` for `or`
+    pub(crate) fn connect(decisive: bool, left: Option<bool>, right: Option<bool>) -> Value {
+        match (left, right) {
+            (Some(left), _) if left == decisive => Value::Bool(decisive),
+            (_, Some(right)) if right == decisive => Value::Bool(decisive),
+            (Some(_), Some(_)) => Value::Bool(!decisive),
+            _ => Value::None,
+        }
+    }
+
+    pub(crate) fn not(self, value: Value) -> Result<Value> {
+        Ok(truth_value(self.truth("!", &value)?.map(|value| !value)))
+    }
+
+    /// Exclusive or of two booleans or bitwise exclusive or of two integers
+    pub(crate) fn xor(self, left: Value, right: Value) -> Result<Value> {
+        match (&left, &right) {
+            (Value::Int(left), Value::Int(right)) => Ok(Value::Int(left ^ right)),
+            (Value::Int(_), Value::None) | (Value::None, Value::Int(_)) => Ok(Value::None),
+            _ => {
+                let truth = |value| {
+                    self.truth("^", value)
+                        .map_err(|_| Error::invalid_type("^", [&left, &right]))
+                };
+                Ok(truth_value(
+                    truth(&left)?
+                        .zip(truth(&right)?)
+                        .map(|(left, right)| left ^ right),
+                ))
+            }
+        }
+    }
+}
+
+fn truth_value(truth: Option<bool>) -> Value {
+    truth.map(Value::Bool).unwrap_or(Value::None)
+}
+
+#[cfg(test)]
+mod when_applying_logic {
+    use super::*;
+
+    const VALUES: [Value; 3] = [Value::Bool(true), Value::Bool(false), Value::None];
+
+    fn and(policy: LogicPolicy, left: &Value, right: &Value) -> Value {
+        LogicPolicy::connect(
+            false,
+            policy.truth("and", left).unwrap(),
+            policy.truth("and", right).unwrap(),
+        )
+    }
+
+    fn or(policy: LogicPolicy, left: &Value, right: &Value) -> Value {
+        LogicPolicy::connect(
+            true,
+            policy.truth("or", left).unwrap(),
+            policy.truth("or", right).unwrap(),
+        )
+    }
+
+    #[test]
+    fn should_follow_kleene_truth_tables() {
+        let [t, f, n] = &VALUES;
+        let kleene = LogicPolicy::Kleene;
+
+        assert_eq!(and(kleene, t, t), *t);
+        assert_eq!(and(kleene, t, n), *n);
+        assert_eq!(and(kleene, n, f), *f);
+        assert_eq!(and(kleene, n, n), *n);
+
+        assert_eq!(or(kleene, f, f), *f);
+        assert_eq!(or(kleene, f, n), *n);
+        assert_eq!(or(kleene, n, t), *t);
+        assert_eq!(or(kleene, n, n), *n);
+
+        assert_eq!(kleene.not(n.clone()).unwrap(), *n);
+        assert_eq!(kleene.xor(t.clone(), n.clone()).unwrap(), *n);
+        assert_eq!(kleene.xor(t.clone(), f.clone()).unwrap(), *t);
+    }
+
+    #[test]
+    fn should_treat_none_as_false() {
+        let policy = LogicPolicy::NoneIsFalse;
+
+        for left in &VALUES {
+            for right in &VALUES {
+                assert!(matches!(and(policy, left, right), Value::Bool(_)));
+                assert!(matches!(or(policy, left, right), Value::Bool(_)));
+                assert!(matches!(
+                    policy.xor(left.clone(), right.clone()).unwrap(),
+                    Value::Bool(_)
+                ));
+            }
+        }
+
+        assert_eq!(policy.not(Value::None).unwrap(), Value::Bool(true));
+        assert_eq!(
+            and(policy, &Value::Bool(true), &Value::None),
+            Value::Bool(false)
+        );
+    }
+
+    #[test]
+    fn should_keep_bitwise_xor_for_integers() {
+        let policy = LogicPolicy::Kleene;
+
+        assert_eq!(
+            policy.xor(Value::Int(6), Value::Int(3)).unwrap(),
+            Value::Int(5)
+        );
+        assert_eq!(policy.xor(Value::Int(6), Value::None).unwrap(), Value::None);
+        assert!(policy.xor(Value::Int(6), Value::Bool(true)).is_err());
+    }
+}
@@ -1,18 +1,32 @@
 //! The `Value` type encodes data that can be passed in or out from expressions
 pub mod convert;
+pub mod de;
+pub mod logic;
+pub mod numeric;
+mod ordering;
 pub mod ser;
 
 use chrono::{prelude::*, TimeDelta};
 use itertools::Itertools;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, fmt::Display};
 
-#[derive(Clone, Debug, PartialEq)]
+/// A Reval value
+///
+/// Values implement `serde::Serialize` and `serde::Deserialize` so they can be
+/// stored and loaded again without losing type information. Every variant is
+/// represented as an externally tagged enum, in json this looks like
+/// `{"Int": 5}`, `{"String": "text"}` or `"None"`. Decimals are represented as
+/// strings like `{"Decimal": "5.25"}`, datetimes as RFC 3339 strings and
+/// durations as a `[seconds, nanoseconds]` pair. Use the `ser::ValueSerializer`
+/// and `de::ValueDeserializer` instead to convert Rust types to and from values.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     String(String),
     Int(i128),
     Float(f64),
-    Decimal(Decimal),
+    Decimal(#[serde(with = "rust_decimal::serde::str")] Decimal),
     Bool(bool),
     DateTime(DateTime<Utc>),
     Duration(TimeDelta),
@@ -21,6 +35,24 @@ pub enum Value {
     None,
 }
 
+impl Value {
+    /// The name of the variant of this value, like `Int` or `String`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "String",
+            Value::Int(_) => "Int",
+            Value::Float(_) => "Float",
+            Value::Decimal(_) => "Decimal",
+            Value::Bool(_) => "Bool",
+            Value::DateTime(_) => "DateTime",
+            Value::Duration(_) => "Duration",
+            Value::Vec(_) => "Vec",
+            Value::Map(_) => "Map",
+            Value::None => "None",
+        }
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -0,0 +1,181 @@
+//! Promote numbers of different types to a common type before applying
+//! operators to them
+
+use crate::{
+    error::{Error, Result},
+    value::Value,
+};
+use rust_decimal::prelude::*;
+
+/// How operators treat numbers of different types, see
+/// `Builder::with_numeric_policy`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumericPolicy {
+    /// Promote numbers of different types along `Int` → `Decimal` → `Float`
+    /// before applying arithmetic, comparison or equality operators, so
+    /// `i5 + d1.5` gives `d6.5` and `i2 == f2.0` is true
+    #[default]
+    Promote,
+
+    /// Only apply operators to numbers of the same type, `i5 + d1.5` fails
+    /// with `Error::InvalidType`
+    Strict,
+}
+
+impl NumericPolicy {
+    /// Promote two numbers to the type of the widest one, other values are
+    /// returned unchanged. Fails when an `Int` does not fit in a `Decimal`
+    pub(crate) fn promote(
+        self,
+        operation: &str,
+        left: Value,
+        right: Value,
+    ) -> Result<(Value, Value)> {
+        match (self, rank(&left), rank(&right)) {
+            (NumericPolicy::Promote, Some(left_rank), Some(right_rank))
+                if left_rank != right_rank =>
+            {
+                let rank = left_rank.max(right_rank);
+                match (widen(&left, rank), widen(&right, rank)) {
+                    (Some(left), Some(right)) => Ok((left, right)),
+                    _ => Err(Error::overflow(operation, [&left, &right])),
+                }
+            }
+            _ => Ok((left, right)),
+        }
+    }
+
+    /// Compare two values for equality, numbers of different types are equal
+    /// when they are equal after promoting them. Vecs and maps are equal when
+    /// their items are equal this way
+    pub(crate) fn equals(self, left: &Value, right: &Value) -> bool {
+        match (self, left, right) {
+            (NumericPolicy::Promote, Value::Vec(left), Value::Vec(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right)
+                        .all(|(left, right)| self.equals(left, right))
+            }
+            (NumericPolicy::Promote, Value::Map(left), Value::Map(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right)
+                        .all(|((left_key, left), (right_key, right))| {
+                            left_key == right_key && self.equals(left, right)
+                        })
+            }
+            _ => match (self, rank(left), rank(right)) {
+                (NumericPolicy::Promote, Some(left_rank), Some(right_rank))
+                    if left_rank != right_rank =>
+                {
+                    let rank = left_rank.max(right_rank);
+                    widen(left, rank).is_some_and(|left| Some(left) == widen(right, rank))
+                }
+                _ => left == right,
+            },
+        }
+    }
+}
+
+/// Position of a numeric type in the promotion order
+pub(crate) fn rank(value: &Value) -> Option<u8> {
+    match value {
+        Value::Int(_) => Some(0),
+        Value::Decimal(_) => Some(1),
+        Value::Float(_) => Some(2),
+        _ => None,
+    }
+}
+
+/// Convert a number to the type at a position in the promotion order
+pub(super) fn widen(value: &Value, rank: u8) -> Option<Value> {
+    match (value, rank) {
+        (Value::Int(value), 1) => Decimal::from_i128(*value).map(Value::Decimal),
+        (Value::Int(value), 2) => Some(Value::Float(*value as f64)),
+        (Value::Decimal(value), 2) => value.to_f64().map(Value::Float),
+        (value, _) => Some(value.clone()),
+    }
+}
+
+#[cfg(test)]
+mod when_promoting_numbers {
+    use super::*;
+
+    fn promote(left: Value, right: Value) -> (Value, Value) {
+        NumericPolicy::Promote.promote("+", left, right).unwrap()
+    }
+
+    #[test]
+    fn should_promote_int_to_decimal() {
+        assert_eq!(
+            promote(Value::Int(5), Value::Decimal(Decimal::new(15, 1))),
+            (
+                Value::Decimal(Decimal::new(5, 0)),
+                Value::Decimal(Decimal::new(15, 1))
+            )
+        );
+    }
+
+    #[test]
+    fn should_promote_int_and_decimal_to_float() {
+        assert_eq!(
+            promote(Value::Float(1.5), Value::Int(2)),
+            (Value::Float(1.5), Value::Float(2.0))
+        );
+        assert_eq!(
+            promote(Value::Decimal(Decimal::new(25, 1)), Value::Float(1.5)),
+            (Value::Float(2.5), Value::Float(1.5))
+        );
+    }
+
+    #[test]
+    fn should_not_promote_other_values() {
+        assert_eq!(
+            promote(Value::Int(5), Value::String("5".to_string())),
+            (Value::Int(5), Value::String("5".to_string()))
+        );
+        assert_eq!(
+            NumericPolicy::Strict
+                .promote("+", Value::Int(5), Value::Float(1.5))
+                .unwrap(),
+            (Value::Int(5), Value::Float(1.5))
+        );
+    }
+
+    #[test]
+    fn should_fail_for_ints_that_do_not_fit_a_decimal() {
+        assert!(NumericPolicy::Promote
+            .promote("+", Value::Int(i128::MAX), Value::Decimal(Decimal::ONE))
+            .is_err());
+        assert!(
+            !NumericPolicy::Promote.equals(&Value::Int(i128::MAX), &Value::Decimal(Decimal::ONE))
+        );
+    }
+
+    #[test]
+    fn should_compare_promoted_numbers() {
+        assert!(NumericPolicy::Promote.equals(&Value::Int(2), &Value::Float(2.0)));
+        assert!(!NumericPolicy::Strict.equals(&Value::Int(2), &Value::Float(2.0)));
+    }
+
+    #[test]
+    fn should_compare_promoted_numbers_in_collections() {
+        let ints = Value::Vec(vec![Value::Int(1), Value::Float(2.0)]);
+        let floats = Value::Vec(vec![Value::Float(1.0), Value::Int(2)]);
+        assert!(NumericPolicy::Promote.equals(&ints, &floats));
+        assert!(!NumericPolicy::Strict.equals(&ints, &floats));
+        assert!(!NumericPolicy::Promote.equals(&ints, &Value::Vec(vec![Value::Int(1)])));
+
+        let map = |key: &str, value| Value::Map([(key.to_string(), value)].into());
+        assert!(NumericPolicy::Promote.equals(
+            &map("a", ints.clone()),
+            &map(
+                "a",
+                Value::Vec(vec![Value::Decimal(Decimal::ONE), Value::Int(2)])
+            )
+        ));
+        assert!(!NumericPolicy::Promote.equals(&map("a", ints.clone()), &map("b", ints)));
+    }
+}
@@ -0,0 +1,170 @@
+//! A total order over all values
+
+use super::{
+    numeric::{rank, widen},
+    Value,
+};
+use std::cmp::Ordering;
+
+impl Value {
+    /// Order any two values, so values of any type can be sorted consistently
+    ///
+    /// Values of different types are ordered by type first: `none`, `Bool`,
+    /// numbers, `String`, `DateTime`, `Duration`, `Vec` and `Map`. Numbers of
+    /// different types are compared by value and `Int` < `Decimal` < `Float`
+    /// when they are equal, floats are ordered like `f64::total_cmp`. Strings
+    /// are ordered lexicographically, `false` is less than `true` and vecs and
+    /// maps are compared element by element.
+    pub fn total_cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::String(left), Value::String(right)) => left.cmp(right),
+            (Value::Int(left), Value::Int(right)) => left.cmp(right),
+            (Value::Float(left), Value::Float(right)) => left.total_cmp(right),
+            (Value::Decimal(left), Value::Decimal(right)) => left.cmp(right),
+            (Value::Bool(left), Value::Bool(right)) => left.cmp(right),
+            (Value::DateTime(left), Value::DateTime(right)) => left.cmp(right),
+            (Value::Duration(left), Value::Duration(right)) => left.cmp(right),
+            (Value::Vec(left), Value::Vec(right)) => left
+                .iter()
+                .zip(right)
+                .map(|(left, right)| left.total_cmp(right))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| left.len().cmp(&right.len())),
+            (Value::Map(left), Value::Map(right)) => left
+                .iter()
+                .zip(right)
+                .map(|((left_key, left), (right_key, right))| {
+                    left_key.cmp(right_key).then_with(|| left.total_cmp(right))
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| left.len().cmp(&right.len())),
+            (Value::None, Value::None) => Ordering::Equal,
+
+            (left, right) => match (rank(left), rank(right)) {
+                (Some(left_rank), Some(right_rank)) => {
+                    cmp_numbers(left, right, left_rank.max(right_rank))
+                        .then(left_rank.cmp(&right_rank))
+                }
+                _ => type_order(left).cmp(&type_order(right)),
+            },
+        }
+    }
+}
+
+/// Compare numbers of different types after promoting them to the same type
+fn cmp_numbers(left: &Value, right: &Value, rank: u8) -> Ordering {
+    match (widen(left, rank), widen(right, rank)) {
+        (Some(Value::Decimal(left)), Some(Value::Decimal(right))) => left.cmp(&right),
+        (Some(Value::Float(left)), Some(Value::Float(right))) => left.total_cmp(&right),
+        // Only an Int that is too large for a Decimal can fail to widen
+        (None, _) => sign(left),
+        (_, None) => sign(right).reverse(),
+        _ => Ordering::Equal,
+    }
+}
+
+fn sign(value: &Value) -> Ordering {
+    match value {
+        Value::Int(value) => value.cmp(&0),
+        _ => Ordering::Equal,
+    }
+}
+
+/// Position of the type of a value in the total order
+fn type_order(value: &Value) -> u8 {
+    match value {
+        Value::None => 0,
+        Value::Bool(_) => 1,
+        Value::Int(_) | Value::Decimal(_) | Value::Float(_) => 2,
+        Value::String(_) => 3,
+        Value::DateTime(_) => 4,
+        Value::Duration(_) => 5,
+        Value::Vec(_) => 6,
+        Value::Map(_) => 7,
+    }
+}
+
+#[cfg(test)]
+mod when_ordering_values {
+    use super::*;
+    use rust_decimal::Decimal;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn should_order_by_type_first() {
+        let mut values = [
+            Value::Map(BTreeMap::new()),
+            Value::String("a".to_string()),
+            Value::Vec(vec![]),
+            Value::Int(1),
+            Value::Bool(true),
+            Value::None,
+        ];
+        values.sort_by(Value::total_cmp);
+
+        assert_eq!(
+            values.iter().map(Value::type_name).collect::<Vec<_>>(),
+            vec!["None", "Bool", "Int", "String", "Vec", "Map"]
+        );
+    }
+
+    #[test]
+    fn should_order_numbers_by_value() {
+        let mut values = [
+            Value::Float(2.5),
+            Value::Int(2),
+            Value::Decimal(Decimal::new(15, 1)),
+            Value::Float(2.0),
+            Value::Int(i128::MIN),
+            Value::Int(i128::MAX),
+        ];
+        values.sort_by(Value::total_cmp);
+
+        assert_eq!(
+            values,
+            [
+                Value::Int(i128::MIN),
+                Value::Decimal(Decimal::new(15, 1)),
+                Value::Int(2),
+                Value::Float(2.0),
+                Value::Float(2.5),
+                Value::Int(i128::MAX),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_order_strings_and_bools() {
+        assert_eq!(
+            Value::String("Apple".to_string()).total_cmp(&Value::String("Banana".to_string())),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::Bool(false).total_cmp(&Value::Bool(true)),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn should_order_vecs_element_by_element() {
+        let short = Value::Vec(vec![Value::Int(1), Value::Int(2)]);
+        let long = Value::Vec(vec![Value::Int(1), Value::Int(2), Value::Int(0)]);
+        let larger = Value::Vec(vec![Value::Int(1), Value::Int(3)]);
+
+        assert_eq!(short.total_cmp(&long), Ordering::Less);
+        assert_eq!(long.total_cmp(&larger), Ordering::Less);
+        assert_eq!(short.total_cmp(&short.clone()), Ordering::Equal);
+    }
+
+    #[test]
+    fn should_order_nan_after_other_floats() {
+        assert_eq!(
+            Value::Float(f64::NAN).total_cmp(&Value::Float(f64::INFINITY)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::Float(f64::NAN).total_cmp(&Value::Int(i128::MAX)),
+            Ordering::Greater
+        );
+    }
+}
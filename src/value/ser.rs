@@ -14,11 +14,115 @@ use std::{collections::BTreeMap, fmt::Display};
 pub struct ValueSerializer;
 
 impl SerError for Error {
-    fn custom<T: Display>(_msg: T) -> Self {
-        todo!() // TODO implement this
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::ser(msg.to_string())
     }
 }
 
+/// Error serializing a type into a `Value`, contains the path to the field
+/// where serialization failed
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerializationError {
+    path: FieldPath,
+    kind: SerializationErrorKind,
+}
+
+impl SerializationError {
+    pub fn new(kind: SerializationErrorKind) -> Self {
+        Self {
+            path: FieldPath::default(),
+            kind,
+        }
+    }
+
+    /// The path to the field that could not be serialized, like
+    /// `orders[3].customer.id`
+    pub fn path(&self) -> &FieldPath {
+        &self.path
+    }
+
+    pub fn kind(&self) -> &SerializationErrorKind {
+        &self.kind
+    }
+}
+
+impl Display for SerializationError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path.is_empty() {
+            true => write!(formatter, "{}", self.kind),
+            false => write!(formatter, "{} at {}", self.kind, self.path),
+        }
+    }
+}
+
+impl std::error::Error for SerializationError {}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum SerializationErrorKind {
+    #[error("{0}")]
+    Custom(String),
+
+    #[error("map keys must be strings, found {0}")]
+    UnsupportedKey(&'static str),
+
+    #[error("integer {0} does not fit in a Value::Int")]
+    IntegerOverflow(u128),
+}
+
+/// Path from the root of the serialized value to a nested field
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldPath(Vec<PathSegment>);
+
+impl FieldPath {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+}
+
+impl Display for FieldPath {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Field(field) if index == 0 => write!(formatter, "{field}")?,
+                PathSegment::Field(field) => write!(formatter, ".{field}")?,
+                PathSegment::Index(index) => write!(formatter, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+/// Errors are constructed where serialization fails and bubble up through the
+/// serializers for the enclosing structs, maps and sequences. Each of these
+/// prepends its own segment to the path of the error.
+fn in_path(error: Error, segment: PathSegment) -> Error {
+    match error {
+        Error::ValueSerializationError(mut error) => {
+            error.path.0.insert(0, segment);
+            Error::ValueSerializationError(error)
+        }
+        error => error,
+    }
+}
+
+fn in_field(error: Error, field: &str) -> Error {
+    in_path(error, PathSegment::Field(field.to_string()))
+}
+
+fn in_index(error: Error, index: usize) -> Error {
+    in_path(error, PathSegment::Index(index))
+}
+
 impl Serializer for ValueSerializer {
     type Ok = Value;
     type Error = Error;
@@ -72,7 +176,12 @@ impl Serializer for ValueSerializer {
     }
 
     fn serialize_u128(self, value: u128) -> Result<Value> {
-        self.serialize_i128(value as i128)
+        match i128::try_from(value) {
+            Ok(value) => self.serialize_i128(value),
+            Err(_) => Err(Error::ValueSerializationError(SerializationError::new(
+                SerializationErrorKind::IntegerOverflow(value),
+            ))),
+        }
     }
 
     fn serialize_f32(self, value: f32) -> Result<Value> {
@@ -138,7 +247,10 @@ impl Serializer for ValueSerializer {
         value: &T,
     ) -> Result<Value> {
         let mut values: BTreeMap<String, Value> = BTreeMap::new();
-        values.insert(String::from(variant), value.serialize(self)?);
+        let value = value
+            .serialize(self)
+            .map_err(|err| in_field(err, variant))?;
+        values.insert(String::from(variant), value);
         Ok(Value::Map(values))
     }
 
@@ -207,7 +319,10 @@ impl SerializeSeq for SerializeVecValue {
     type Error = Error;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        self.vec.push(value.serialize(ValueSerializer)?);
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|err| in_index(err, self.vec.len()))?;
+        self.vec.push(value);
         Ok(())
     }
 
@@ -252,7 +367,10 @@ impl SerializeTupleVariant for SerializeTupleVariantValue {
     type Error = Error;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        self.vec.push(value.serialize(ValueSerializer)?);
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|err| in_field(in_index(err, self.vec.len()), &self.name))?;
+        self.vec.push(value);
         Ok(())
     }
 
@@ -284,7 +402,10 @@ impl SerializeMap for SerializeMapValue {
         // Panic because this indicates a bug in the program rather than an
         // expected failure.
         let key = key.expect("serialize_value called before serialize_key");
-        self.map.insert(key, value.serialize(ValueSerializer)?);
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|err| in_field(err, &key))?;
+        self.map.insert(key, value);
         Ok(())
     }
 
@@ -324,8 +445,10 @@ impl SerializeStructVariant for SerializeStructVariantValue {
         key: &'static str,
         value: &T,
     ) -> Result<()> {
-        self.map
-            .insert(String::from(key), value.serialize(ValueSerializer)?);
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|err| in_field(in_field(err, key), &self.name))?;
+        self.map.insert(String::from(key), value);
         Ok(())
     }
 
@@ -343,8 +466,10 @@ impl SerializeStructVariant for SerializeStructVariantValue {
 /// than a String
 pub struct StringSerializer;
 
-fn not_a_string<T>() -> Result<T> {
-    Err(Error::ser("String type expected"))
+fn not_a_string<T>(found: &'static str) -> Result<T> {
+    Err(Error::ValueSerializationError(SerializationError::new(
+        SerializationErrorKind::UnsupportedKey(found),
+    )))
 }
 
 impl Serializer for StringSerializer {
@@ -360,51 +485,51 @@ impl Serializer for StringSerializer {
     type SerializeStructVariant = Impossible<String, Error>;
 
     fn serialize_bool(self, _: bool) -> Result<String> {
-        not_a_string()
+        not_a_string("bool")
     }
 
     fn serialize_i8(self, _: i8) -> Result<String> {
-        not_a_string()
+        not_a_string("i8")
     }
 
     fn serialize_i16(self, _: i16) -> Result<String> {
-        not_a_string()
+        not_a_string("i16")
     }
 
     fn serialize_i32(self, _: i32) -> Result<String> {
-        not_a_string()
+        not_a_string("i32")
     }
 
     fn serialize_i64(self, _: i64) -> Result<String> {
-        not_a_string()
+        not_a_string("i64")
     }
 
     fn serialize_u8(self, _: u8) -> Result<String> {
-        not_a_string()
+        not_a_string("u8")
     }
 
     fn serialize_u16(self, _: u16) -> Result<String> {
-        not_a_string()
+        not_a_string("u16")
     }
 
     fn serialize_u32(self, _: u32) -> Result<String> {
-        not_a_string()
+        not_a_string("u32")
     }
 
     fn serialize_u64(self, _: u64) -> Result<String> {
-        not_a_string()
+        not_a_string("u64")
     }
 
     fn serialize_f32(self, _: f32) -> Result<String> {
-        not_a_string()
+        not_a_string("f32")
     }
 
     fn serialize_f64(self, _: f64) -> Result<String> {
-        not_a_string()
+        not_a_string("f64")
     }
 
     fn serialize_char(self, _: char) -> Result<String> {
-        not_a_string()
+        not_a_string("char")
     }
 
     fn serialize_str(self, value: &str) -> Result<String> {
@@ -412,27 +537,27 @@ impl Serializer for StringSerializer {
     }
 
     fn serialize_bytes(self, _: &[u8]) -> Result<String> {
-        not_a_string()
+        not_a_string("bytes")
     }
 
     fn serialize_none(self) -> Result<String> {
-        not_a_string()
+        not_a_string("none")
     }
 
     fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<String> {
-        not_a_string()
+        not_a_string("option")
     }
 
     fn serialize_unit(self) -> Result<String> {
-        not_a_string()
+        not_a_string("unit")
     }
 
     fn serialize_unit_struct(self, _: &'static str) -> Result<String> {
-        not_a_string()
+        not_a_string("unit struct")
     }
 
     fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<String> {
-        not_a_string()
+        not_a_string("unit variant")
     }
 
     fn serialize_newtype_struct<T: Serialize + ?Sized>(
@@ -440,7 +565,7 @@ impl Serializer for StringSerializer {
         _: &'static str,
         _: &T,
     ) -> Result<String> {
-        not_a_string()
+        not_a_string("newtype struct")
     }
 
     fn serialize_newtype_variant<T: Serialize + ?Sized>(
@@ -450,15 +575,15 @@ impl Serializer for StringSerializer {
         _: &'static str,
         _: &T,
     ) -> Result<String> {
-        not_a_string()
+        not_a_string("newtype variant")
     }
 
     fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
-        not_a_string()
+        not_a_string("sequence")
     }
 
     fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple> {
-        not_a_string()
+        not_a_string("tuple")
     }
 
     fn serialize_tuple_struct(
@@ -466,7 +591,7 @@ impl Serializer for StringSerializer {
         _: &'static str,
         _: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        not_a_string()
+        not_a_string("tuple struct")
     }
 
     fn serialize_tuple_variant(
@@ -476,15 +601,15 @@ impl Serializer for StringSerializer {
         _: &'static str,
         _: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        not_a_string()
+        not_a_string("tuple variant")
     }
 
     fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
-        not_a_string()
+        not_a_string("map")
     }
 
     fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
-        not_a_string()
+        not_a_string("struct")
     }
 
     fn serialize_struct_variant(
@@ -494,7 +619,7 @@ impl Serializer for StringSerializer {
         _: &'static str,
         _: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        not_a_string()
+        not_a_string("struct variant")
     }
 }
 
@@ -689,6 +814,100 @@ mod when_serializing_to_value {
         )
     }
 
+    #[test]
+    fn should_return_custom_error() {
+        assert_serialization_error(Invalid, "", "invalid value");
+    }
+
+    #[test]
+    fn should_return_field_path_of_nested_error() {
+        #[derive(Serialize)]
+        struct Customer {
+            id: Invalid,
+        }
+
+        #[derive(Serialize)]
+        struct Order {
+            customer: Customer,
+        }
+
+        #[derive(Serialize)]
+        struct Data {
+            orders: Vec<Order>,
+        }
+
+        assert_serialization_error(
+            Data {
+                orders: vec![
+                    Order {
+                        customer: Customer { id: Invalid },
+                    },
+                    Order {
+                        customer: Customer { id: Invalid },
+                    },
+                ],
+            },
+            "orders[0].customer.id",
+            "invalid value at orders[0].customer.id",
+        );
+    }
+
+    #[test]
+    fn should_return_field_path_in_enum_variants() {
+        #[derive(Serialize)]
+        enum TestEnum {
+            Tuple(u64, Invalid),
+            Struct { value: Invalid },
+        }
+
+        assert_serialization_error(TestEnum::Tuple(5, Invalid), "Tuple[1]", "");
+        assert_serialization_error(TestEnum::Struct { value: Invalid }, "Struct.value", "");
+    }
+
+    #[test]
+    fn should_return_error_for_unsupported_map_key() {
+        let map = BTreeMap::from([("items", BTreeMap::from([(5u32, "value")]))]);
+
+        assert_serialization_error(map, "items", "map keys must be strings, found u32 at items");
+    }
+
+    #[test]
+    fn should_return_error_for_u128_overflow() {
+        assert_serialization_error(
+            vec![1u128, u128::MAX],
+            "[1]",
+            "integer 340282366920938463463374607431768211455 does not fit in a Value::Int at [1]",
+        );
+    }
+
+    #[test]
+    fn should_serialize_u128_in_range() {
+        assert_serialized(5u128, Value::Int(5));
+    }
+
+    /// Type that always fails to serialize, like a validating Serialize impl
+    struct Invalid;
+
+    impl Serialize for Invalid {
+        fn serialize<S: Serializer>(&self, _serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            Err(S::Error::custom("invalid value"))
+        }
+    }
+
+    /// Helper method that tests if serializing a value fails at the expected
+    /// path and optionally with the expected message
+    fn assert_serialization_error(value: impl Serialize, path: &str, message: &str) {
+        match value.serialize(ValueSerializer) {
+            Err(Error::ValueSerializationError(error)) => {
+                assert_eq!(error.path().to_string(), path);
+                if !message.is_empty() {
+                    assert_eq!(error.to_string(), message);
+                }
+            }
+            result => panic!("Expected serialization error, got {result:?}"),
+        }
+    }
+
     /// Helper method that tests if serializing a value yields the expected result
     fn assert_serialized(value: impl Serialize, expected: Value) {
         assert_eq!(value.serialize(ValueSerializer).unwrap(), expected);
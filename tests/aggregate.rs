@@ -0,0 +1,178 @@
+use crate::common::{eval_expr, try_eval_expr, try_eval_rule};
+use chrono::{TimeDelta, TimeZone, Utc};
+use reval::{prelude::*, Error};
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+const LINES: &str = "[{amount: d10.50, quantity: i2}, {amount: d4.25, quantity: i1}]";
+
+fn dates() -> BTreeMap<&'static str, Value> {
+    BTreeMap::from([(
+        "dates",
+        Value::Vec(vec![
+            Value::DateTime(Utc.with_ymd_and_hms(2024, 1, 3, 0, 0, 0).unwrap()),
+            Value::DateTime(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+        ]),
+    )])
+}
+
+fn durations() -> BTreeMap<&'static str, Value> {
+    BTreeMap::from([(
+        "durations",
+        Value::Vec(vec![
+            Value::Duration(TimeDelta::hours(1)),
+            Value::Duration(TimeDelta::hours(2)),
+        ]),
+    )])
+}
+
+#[tokio::test]
+async fn should_fold_list_into_value() {
+    assert_eq!(
+        eval_expr(
+            &format!(
+                "for line in {LINES} fold total = d0 with total + line.amount * line.quantity"
+            ),
+            ()
+        )
+        .await,
+        Value::Decimal(Decimal::new(2525, 2))
+    );
+}
+
+#[tokio::test]
+async fn should_give_initial_value_when_folding_empty_list() {
+    assert_eq!(
+        eval_expr("for x in [] fold acc = i7 with acc + x", ()).await,
+        7.into()
+    );
+    assert_eq!(
+        eval_expr("for x in none fold acc = i7 with acc + x", ()).await,
+        Value::None
+    );
+}
+
+#[tokio::test]
+async fn should_read_fields_named_like_keywords() {
+    let facts = json!({"order": {"with": 2, "fold": [1, 2]}, "with": 3});
+
+    assert_eq!(eval_expr("order.with + facts.with", &facts).await, 5.into());
+    assert_eq!(
+        eval_expr("for x in order!.fold fold acc = i0 with acc + x", &facts).await,
+        3.into()
+    );
+}
+
+#[tokio::test]
+async fn should_sum_numbers() {
+    assert_eq!(eval_expr("sum([i4, i1, i3])", ()).await, 8.into());
+    assert_eq!(
+        eval_expr(&format!("sum(for line in {LINES} map line.amount)"), ()).await,
+        Value::Decimal(Decimal::new(1475, 2))
+    );
+}
+
+#[tokio::test]
+async fn should_promote_numbers_like_add() {
+    assert_eq!(
+        eval_expr("sum([i1, f2.5, none])", ()).await,
+        Value::Float(3.5)
+    );
+    assert!(matches!(
+        try_eval_rule(
+            "sum([i1, f2.5, none])",
+            Value::None,
+            ruleset().with_numeric_policy(NumericPolicy::Strict)
+        )
+        .await
+        .unwrap_err()
+        .without_span(),
+        Error::InvalidType { .. }
+    ));
+}
+
+#[tokio::test]
+async fn should_sum_durations() {
+    assert_eq!(
+        try_eval_expr("sum(durations)", durations()).await.unwrap(),
+        Value::Duration(TimeDelta::hours(3))
+    );
+}
+
+#[tokio::test]
+async fn should_count_items_that_are_not_none() {
+    assert_eq!(eval_expr("count([i4, i1, i3])", ()).await, 3.into());
+    assert_eq!(eval_expr("count([i1, f2.5, none])", ()).await, 2.into());
+}
+
+#[tokio::test]
+async fn should_find_min_and_max() {
+    assert_eq!(eval_expr("min([i4, i1, i3])", ()).await, 1.into());
+    assert_eq!(eval_expr("max([i4, i1, i3])", ()).await, 4.into());
+    assert_eq!(
+        eval_expr("max([i1, f2.5, none])", ()).await,
+        Value::Float(2.5)
+    );
+    assert_eq!(
+        try_eval_expr("min(dates)", dates()).await.unwrap(),
+        Value::DateTime(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+    );
+    assert_eq!(
+        try_eval_expr("max(durations)", durations()).await.unwrap(),
+        Value::Duration(TimeDelta::hours(2))
+    );
+}
+
+#[tokio::test]
+async fn should_average_numbers_dates_and_durations() {
+    assert_eq!(
+        eval_expr("avg([i4, i1, i3])", ()).await,
+        Value::Decimal(Decimal::from(8) / Decimal::from(3))
+    );
+    assert_eq!(
+        eval_expr("avg([i1, f2.5, none])", ()).await,
+        Value::Float(1.75)
+    );
+    assert_eq!(
+        try_eval_expr("avg(dates)", dates()).await.unwrap(),
+        Value::DateTime(Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap())
+    );
+    assert_eq!(
+        try_eval_expr("avg(durations)", durations()).await.unwrap(),
+        Value::Duration(TimeDelta::minutes(90))
+    );
+}
+
+#[tokio::test]
+async fn should_give_none_for_empty_or_missing_lists() {
+    for source in [
+        "sum([])",
+        "min([none])",
+        "avg([])",
+        "max(none)",
+        "count(none)",
+    ] {
+        assert_eq!(eval_expr(source, ()).await, Value::None, "{source}");
+    }
+    assert_eq!(eval_expr("sum([]) ?? i0", ()).await, 0.into());
+    assert_eq!(eval_expr("count([])", ()).await, 0.into());
+}
+
+#[tokio::test]
+async fn should_fail_for_items_that_can_not_be_aggregated() {
+    for source in [
+        "sum(dates)",
+        r#"avg(["a"])"#,
+        r#"min([i1, "a"])"#,
+        "sum(i1)",
+    ] {
+        assert!(
+            matches!(
+                try_eval_expr(source, dates()).await.unwrap_err(),
+                Error::InvalidType { .. }
+            ),
+            "{source}"
+        );
+    }
+}
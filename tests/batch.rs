@@ -0,0 +1,104 @@
+use crate::common::Slow;
+use futures_util::{stream, StreamExt};
+use reval::{prelude::*, ruleset::Outcome};
+use std::{collections::BTreeMap, sync::atomic::Ordering};
+
+fn ruleset_with(slow: Slow, batch_concurrency: usize) -> RuleSet {
+    ruleset()
+        .with_rule(Rule::parse("// double\nslow(value) * i2").unwrap())
+        .unwrap()
+        .with_function(slow)
+        .unwrap()
+        .with_batch_concurrency(batch_concurrency)
+        .build()
+}
+
+fn records(count: i64) -> Vec<BTreeMap<&'static str, i64>> {
+    (0..count)
+        .map(|value| BTreeMap::from([("value", value)]))
+        .collect()
+}
+
+/// The value of the first outcome for every record
+fn values(outcomes: Vec<reval::Result<Vec<Outcome<'_>>>>) -> Vec<Value> {
+    outcomes
+        .into_iter()
+        .map(|outcomes| outcomes.unwrap().remove(0).value.unwrap())
+        .collect()
+}
+
+fn doubled(count: i128) -> Vec<Value> {
+    (0..count).map(|value| Value::Int(value * 2)).collect()
+}
+
+#[tokio::test]
+async fn should_evaluate_batch_in_record_order() {
+    let ruleset = ruleset_with(Slow::default(), 4);
+
+    let outcomes = ruleset.evaluate_batch(records(10)).collect().await;
+
+    assert_eq!(values(outcomes), doubled(10));
+}
+
+#[tokio::test]
+async fn should_evaluate_records_one_after_another_by_default() {
+    let slow = Slow::default();
+    let ruleset = ruleset()
+        .with_rule(Rule::parse("// double\nslow(value) * i2").unwrap())
+        .unwrap()
+        .with_function(slow.clone())
+        .unwrap()
+        .build();
+
+    let _: Vec<_> = ruleset.evaluate_batch(records(4)).collect().await;
+
+    assert_eq!(slow.max_running.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn should_limit_concurrent_records() {
+    let slow = Slow::default();
+    let ruleset = ruleset_with(slow.clone(), 3);
+
+    let _: Vec<_> = ruleset.evaluate_batch(records(10)).collect().await;
+
+    assert_eq!(slow.max_running.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn should_evaluate_stream_of_records() {
+    let ruleset = ruleset_with(Slow::default(), 2);
+
+    let outcomes = ruleset
+        .evaluate_stream(stream::iter(records(5)))
+        .collect()
+        .await;
+
+    assert_eq!(values(outcomes), doubled(5));
+}
+
+#[tokio::test]
+async fn should_return_errors_per_record() {
+    let ruleset = ruleset_with(Slow::default(), 2);
+    let records = [Some(BTreeMap::from([("value", 1)])), None];
+
+    let outcomes: Vec<_> = ruleset.evaluate_batch(records).collect().await;
+
+    assert_eq!(
+        outcomes[0].as_ref().unwrap()[0].value.as_ref().unwrap(),
+        &2.into()
+    );
+    assert!(outcomes[1].as_ref().unwrap()[0].value.is_err());
+}
+
+#[test]
+fn should_evaluate_batch_without_runtime_in_record_order() {
+    let ruleset = ruleset()
+        .with_rule(Rule::parse("// double\nvalue * i2").unwrap())
+        .unwrap()
+        .build();
+
+    let outcomes = ruleset.evaluate_batch_blocking(records(1000));
+
+    assert_eq!(values(outcomes), doubled(1000));
+}
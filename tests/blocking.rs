@@ -0,0 +1,147 @@
+use reval::prelude::*;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Future that is pending once before it completes, like a user-function
+/// waiting for io
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+struct Slow;
+
+#[async_trait::async_trait]
+impl SingleArgFunction for Slow {
+    async fn call(&self, param: Value) -> FunctionResult {
+        YieldOnce(false).await;
+        Ok(param)
+    }
+
+    fn name(&self) -> &'static str {
+        "slow"
+    }
+}
+
+#[test]
+fn should_evaluate_ruleset_without_runtime() {
+    let ruleset = ruleset()
+        .with_rule(Rule::parse("// adult\nage >= i18").unwrap())
+        .unwrap()
+        .with_rule(Rule::parse("// name\nuppercase(name)").unwrap())
+        .unwrap()
+        .build();
+
+    let facts = serde_json::json!({"age": 21, "name": "reval"});
+    let outcomes = ruleset.evaluate_blocking(&facts).unwrap();
+
+    assert_eq!(outcomes[0].value.as_ref().unwrap(), &true.into());
+    assert_eq!(outcomes[1].value.as_ref().unwrap(), &"REVAL".into());
+}
+
+#[test]
+fn should_call_async_user_functions_without_runtime() {
+    let ruleset = ruleset()
+        .with_rule(Rule::parse("// slow\nslow(i5) + i1").unwrap())
+        .unwrap()
+        .with_function(Slow)
+        .unwrap()
+        .build();
+
+    let outcomes = ruleset.evaluate_value_blocking(&Value::None).unwrap();
+
+    assert_eq!(outcomes[0].value.as_ref().unwrap(), &6.into());
+}
+
+#[test]
+fn should_call_typed_functions_without_runtime() {
+    let ruleset = ruleset()
+        .with_rule(Rule::parse("// double\ndouble(i21)").unwrap())
+        .unwrap()
+        .with_fn("double", |value: i64| Ok::<_, anyhow::Error>(value * 2))
+        .unwrap()
+        .build();
+
+    let outcomes = ruleset.evaluate_blocking(&()).unwrap();
+
+    assert_eq!(outcomes[0].value.as_ref().unwrap(), &42.into());
+}
+
+#[test]
+fn should_evaluate_expression_without_runtime() {
+    assert_eq!(
+        Expr::parse("i1 + i2")
+            .unwrap()
+            .evaluate_blocking(&Value::None)
+            .unwrap(),
+        3.into()
+    );
+}
+
+/// Evaluate rules with and without a runtime and return the blocking outcomes
+/// after checking they are the same
+fn assert_same_outcomes(builder: Builder, rules: &[&str]) -> Vec<String> {
+    let ruleset = rules
+        .iter()
+        .fold(builder, |builder, rule| {
+            builder.with_rule(Rule::parse(rule).unwrap()).unwrap()
+        })
+        .build();
+    let facts = BTreeMap::from([("items", vec![1, 2, 3])]);
+
+    let blocking = ruleset.evaluate_blocking(&facts).unwrap();
+    let asynchronous = tokio_test::block_on(ruleset.evaluate(&facts)).unwrap();
+
+    assert_eq!(blocking.len(), rules.len());
+    blocking
+        .iter()
+        .zip(asynchronous.iter())
+        .map(|(blocking, asynchronous)| {
+            let blocking = format!("{:?}", blocking.value);
+            assert_eq!(blocking, format!("{:?}", asynchronous.value));
+            blocking
+        })
+        .collect()
+}
+
+#[test]
+fn should_return_same_outcomes_as_async_evaluation() {
+    assert_same_outcomes(
+        ruleset(),
+        &[
+            "// a\nfor x in items map x * i2",
+            "// b\nmissing + i1",
+            "// c\nlet total = for x in items fold acc = i0 with acc + x in total * i2",
+            "// d\nfor x in items filter x > i1 and x / i0 > i1",
+        ],
+    );
+}
+
+#[test]
+fn should_apply_limits_without_runtime() {
+    let outcomes = assert_same_outcomes(
+        ruleset().with_limits(EvalLimits::new().with_max_steps(8)),
+        &[
+            "// short\ni1 + i2",
+            "// long\nfor x in items map x * i2 + x",
+        ],
+    );
+
+    assert_eq!(outcomes[0], "Ok(Int(3))");
+    assert!(outcomes[1].contains("LimitExceeded"), "{}", outcomes[1]);
+}
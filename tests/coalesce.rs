@@ -0,0 +1,126 @@
+use crate::common::{eval_expr, facts, try_eval_rule};
+use reval::{prelude::*, Error};
+use serde_json::json;
+
+#[tokio::test]
+async fn should_use_default_for_none() {
+    let facts = json!({"order": {"discount": null}, "fallback": 7});
+
+    assert_eq!(
+        eval_expr("order.discount ?? d0", &facts).await,
+        Value::Decimal(0.into())
+    );
+    assert_eq!(
+        eval_expr("order.missing ?? fallback", &facts).await,
+        7.into()
+    );
+    assert_eq!(
+        eval_expr("order.missing.deeper ?? i1", &facts).await,
+        1.into()
+    );
+}
+
+#[tokio::test]
+async fn should_keep_values_that_are_not_none() {
+    assert_eq!(eval_expr("i100 ?? i0", ()).await, 100.into());
+    assert_eq!(eval_expr("false ?? true", ()).await, false.into());
+}
+
+#[tokio::test]
+async fn should_only_evaluate_right_side_for_none() {
+    let failing = || {
+        ruleset()
+            .with_fn("fail", |_: Value| {
+                Err::<Value, _>(anyhow::anyhow!("right side was evaluated"))
+            })
+            .unwrap()
+    };
+
+    assert_eq!(
+        try_eval_rule("i100 ?? fail(i1)", Value::None, failing())
+            .await
+            .unwrap(),
+        100.into()
+    );
+    assert!(try_eval_rule("none ?? fail(i1)", Value::None, failing())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn should_chain_coalescing() {
+    let facts = json!({"order": {"discount": null}, "fallback": 7});
+
+    assert_eq!(
+        eval_expr("order.discount ?? order.missing ?? fallback", &facts).await,
+        7.into()
+    );
+    assert_eq!(
+        eval_expr("order.discount ?? none", &facts).await,
+        Value::None
+    );
+}
+
+#[tokio::test]
+async fn should_compare_coalesced_value() {
+    assert_eq!(eval_expr("none ?? d0 > d5", ()).await, false.into());
+    assert_eq!(eval_expr("i100 ?? i0 >= i100", ()).await, true.into());
+}
+
+#[tokio::test]
+async fn should_index_strictly() {
+    let facts = json!({"order": {"discount": null, "total": 100, "items": ["book"]}});
+
+    assert_eq!(eval_expr("order!.total", &facts).await, 100.into());
+    assert_eq!(eval_expr("order!.items!.0", &facts).await, "book".into());
+    assert_eq!(eval_expr("order!.discount", &facts).await, Value::None);
+}
+
+#[tokio::test]
+async fn should_fail_strict_index_on_missing_key() {
+    let order = facts(json!({"order": {"items": ["book"]}}));
+
+    let error = try_eval_rule("order!.missing", order.clone(), ruleset())
+        .await
+        .unwrap_err();
+    assert!(matches!(error.without_span(), Error::UnknownIndex(index) if index == "missing"));
+    assert_eq!(error.to_string(), "Unknown index missing at 8..22");
+
+    let error = try_eval_rule("order.items!.3", order.clone(), ruleset())
+        .await
+        .unwrap_err();
+    assert!(matches!(error.without_span(), Error::UnknownIndex(index) if index == "3"));
+
+    let error = try_eval_rule("order.missing!.deeper", order, ruleset())
+        .await
+        .unwrap_err();
+    assert!(matches!(error.without_span(), Error::UnknownIndex(index) if index == "deeper"));
+}
+
+#[tokio::test]
+async fn should_fail_strict_index_on_other_values() {
+    let error = try_eval_rule("fallback!.field", facts(json!({"fallback": 7})), ruleset())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.without_span().to_string(),
+        "Cannot apply `!.field` to Int"
+    );
+}
+
+#[tokio::test]
+async fn should_coalesce_strict_index_errors_only_for_none() {
+    let order = facts(json!({"order": {"discount": null}}));
+
+    assert!(
+        try_eval_rule("order!.missing ?? i0", order.clone(), ruleset())
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        try_eval_rule("order!.discount ?? i0", order, ruleset())
+            .await
+            .unwrap(),
+        0.into()
+    );
+}
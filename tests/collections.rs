@@ -0,0 +1,180 @@
+use crate::common::{eval_expr, strings, try_eval_expr, try_eval_rule};
+use reval::{prelude::*, Error};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+#[tokio::test]
+async fn should_get_length_of_strings_vecs_and_maps() {
+    assert_eq!(eval_expr(r#"len("reval")"#, ()).await, 5.into());
+    assert_eq!(eval_expr(r#"len("héllo")"#, ()).await, 5.into());
+    assert_eq!(
+        eval_expr("len(numbers)", json!({"numbers": [3, 1, 2, 3, 1]})).await,
+        5.into()
+    );
+    assert_eq!(eval_expr("len({a: i1, b: i2})", ()).await, 2.into());
+    assert_eq!(eval_expr("len(none)", ()).await, Value::None);
+}
+
+#[tokio::test]
+async fn should_sort_items() {
+    assert_eq!(
+        eval_expr("sort([i3, i1, i2, i3, i1])", ()).await,
+        vec![1, 1, 2, 3, 3].into()
+    );
+    assert_eq!(
+        eval_expr(r#"sort(["b", "c", "a"])"#, ()).await,
+        strings(&["a", "b", "c"])
+    );
+}
+
+#[tokio::test]
+async fn should_sort_items_by_key_keeping_order_of_equal_keys() {
+    let facts = json!({"products": [
+        {"name": "pear", "price": 5},
+        {"name": "apple", "price": 3},
+        {"name": "plum", "price": 5},
+    ]});
+
+    assert_eq!(
+        eval_expr(
+            "for p in (for p in products sort_by p.price) map p.name",
+            &facts
+        )
+        .await,
+        strings(&["apple", "pear", "plum"])
+    );
+    assert_eq!(
+        eval_expr(
+            "for p in (for p in products sort_by -p.price) map p.name",
+            &facts
+        )
+        .await,
+        strings(&["pear", "plum", "apple"])
+    );
+}
+
+#[tokio::test]
+async fn should_sort_by_field_named_sort_by() {
+    assert_eq!(
+        eval_expr(
+            "for p in products sort_by p.sort_by",
+            json!({"products": [{"sort_by": 2}, {"sort_by": 1}]})
+        )
+        .await,
+        Value::Vec(vec![
+            Value::from(BTreeMap::from([("sort_by", Value::Int(1))])),
+            Value::from(BTreeMap::from([("sort_by", Value::Int(2))])),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn should_remove_duplicates_keeping_first() {
+    assert_eq!(
+        eval_expr("distinct([i3, i1, i2, i3, i1])", ()).await,
+        vec![3, 1, 2].into()
+    );
+    assert_eq!(
+        eval_expr("distinct([i1, f1.0, d1, [i2], [f2.0]])", ()).await,
+        Value::Vec(vec![Value::Int(1), vec![2].into()])
+    );
+    assert_eq!(
+        try_eval_rule(
+            "distinct([i1, f1.0, i1])",
+            Value::None,
+            ruleset().with_numeric_policy(NumericPolicy::Strict)
+        )
+        .await
+        .unwrap(),
+        Value::Vec(vec![Value::Int(1), Value::Float(1.0)])
+    );
+}
+
+#[tokio::test]
+async fn should_flatten_one_level() {
+    assert_eq!(
+        eval_expr("flatten([[i1, i2], i3, [[i4]]])", ()).await,
+        Value::Vec(vec![1.into(), 2.into(), 3.into(), vec![4].into()])
+    );
+}
+
+#[tokio::test]
+async fn should_reverse_vecs_and_strings() {
+    assert_eq!(
+        eval_expr("reverse([i3, i1, i2, i3, i1])", ()).await,
+        vec![1, 3, 2, 1, 3].into()
+    );
+    assert_eq!(eval_expr(r#"reverse("reval")"#, ()).await, "laver".into());
+}
+
+#[tokio::test]
+async fn should_concatenate_vecs() {
+    assert_eq!(eval_expr("[i1, i2] + [i3]", ()).await, vec![1, 2, 3].into());
+    assert_eq!(
+        eval_expr("concat([i1], [i2, i3], [])", ()).await,
+        vec![1, 2, 3].into()
+    );
+    assert_eq!(eval_expr("concat([i1], none)", ()).await, Value::None);
+}
+
+#[tokio::test]
+async fn should_merge_maps() {
+    assert_eq!(
+        eval_expr("merge({a: i1, b: i2}, {b: i3, c: i4})", ()).await,
+        Value::from(BTreeMap::from([
+            ("a", Value::Int(1)),
+            ("b", Value::Int(3)),
+            ("c", Value::Int(4)),
+        ]))
+    );
+}
+
+#[tokio::test]
+async fn should_get_first_and_last_items() {
+    assert_eq!(eval_expr("first([i3, i1, i2])", ()).await, 3.into());
+    assert_eq!(
+        eval_expr(
+            "last(products).name",
+            json!({"products": [{"name": "pear"}, {"name": "plum"}]})
+        )
+        .await,
+        "plum".into()
+    );
+    assert_eq!(eval_expr("first([])", ()).await, Value::None);
+}
+
+#[tokio::test]
+async fn should_slice_vecs_and_strings() {
+    let facts = json!({"numbers": [3, 1, 2, 3, 1], "name": "reval", "nothing": null});
+
+    assert_eq!(eval_expr("numbers[1..3]", &facts).await, vec![1, 2].into());
+    assert_eq!(eval_expr("numbers[3..]", &facts).await, vec![3, 1].into());
+    assert_eq!(eval_expr("numbers[..i-3]", &facts).await, vec![3, 1].into());
+    assert_eq!(eval_expr("name[1..i10]", &facts).await, "eval".into());
+    assert_eq!(
+        eval_expr("numbers[4..2]", &facts).await,
+        Value::Vec(Vec::new())
+    );
+    assert_eq!(eval_expr("nothing[1..]", &facts).await, Value::None);
+}
+
+#[tokio::test]
+async fn should_fail_for_values_that_are_not_collections() {
+    for source in [
+        "len(i1)",
+        "sort(i1)",
+        "i1[1..]",
+        r#"numbers["a"..]"#,
+        "merge([])",
+    ] {
+        assert!(
+            matches!(
+                try_eval_expr(source, BTreeMap::from([("numbers", vec![3, 1, 2])]))
+                    .await
+                    .unwrap_err(),
+                Error::InvalidType { .. }
+            ),
+            "{source}"
+        );
+    }
+}
@@ -3,6 +3,13 @@
 use chrono::prelude::*;
 use reval::{prelude::*, value::ser::ValueSerializer};
 use serde::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 pub fn simple_event() -> Event {
     Event::Simple(SimpleEvent {
@@ -38,13 +45,55 @@ pub struct SimpleEvent {
     pub id: u32,
 }
 
+/// Serialize an event into facts for the `try_eval` helpers
+pub fn facts<E: Serialize>(event: E) -> Value {
+    event.serialize(ValueSerializer).unwrap()
+}
+
 /// Evaluate a simple expression against an event
 pub async fn eval_expr<E: Serialize>(expr: &str, event: E) -> Value {
-    let event = event.serialize(ValueSerializer).unwrap();
+    try_eval_expr(expr, facts(event)).await.unwrap()
+}
 
+/// Evaluate a simple expression against facts that are already values, returns
+/// the evaluation error instead of panicking
+pub async fn try_eval_expr(expr: &str, facts: impl Into<Value>) -> reval::Result<Value> {
     let expr = Expr::parse(expr).unwrap();
 
-    expr.evaluate(&event).await.unwrap()
+    expr.evaluate(&facts.into()).await
+}
+
+/// Evaluate a simple expression that is expected to fail, returns the error
+/// message
+pub async fn eval_expr_err(expr: &str, facts: impl Into<Value>) -> String {
+    try_eval_expr(expr, facts).await.unwrap_err().to_string()
+}
+
+/// Build a ruleset from the builder with a single rule called `rule`, the
+/// rule source starts at byte 8 after the `// rule` name line
+pub fn ruleset_with_rule(source: &str, builder: Builder) -> RuleSet {
+    builder
+        .with_rule(Rule::parse(&format!("// rule\n{source}")).unwrap())
+        .unwrap()
+        .build()
+}
+
+/// Evaluate a single rule in a ruleset from the builder against facts,
+/// returns the result of the rule
+pub async fn try_eval_rule(
+    source: &str,
+    facts: impl Into<Value>,
+    builder: Builder,
+) -> reval::Result<Value> {
+    let ruleset = ruleset_with_rule(source, builder);
+
+    let mut outcomes = ruleset.evaluate_value(&facts.into()).await.unwrap();
+    outcomes.remove(0).value
+}
+
+/// A vec of string values
+pub fn strings(items: &[&str]) -> Value {
+    Value::Vec(items.iter().map(|item| Value::from(*item)).collect())
 }
 
 pub fn check_float(value: Value, expected: f64) {
@@ -53,3 +102,28 @@ pub fn check_float(value: Value, expected: f64) {
         assert!(value - expected < 0.0000001);
     }
 }
+
+/// User-function that waits a bit and keeps track of how many calls are in
+/// progress at the same time
+#[derive(Clone, Default)]
+pub struct Slow {
+    pub running: Arc<AtomicUsize>,
+    pub max_running: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl SingleArgFunction for Slow {
+    async fn call(&self, param: Value) -> FunctionResult {
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_running.fetch_max(running, Ordering::SeqCst);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        Ok(param)
+    }
+
+    fn name(&self) -> &'static str {
+        "slow"
+    }
+}
@@ -0,0 +1,607 @@
+//! Differential tests that check compiled rules give exactly the same results
+//! and errors as evaluating the rule expression directly
+
+use reval::{prelude::*, value::ser::ValueSerializer};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+fn facts() -> Value {
+    serde_json::from_str::<serde_json::Value>(
+        r#"{
+            "a": 5,
+            "b": 2.5,
+            "s": "Some String ",
+            "flag": true,
+            "list": [1, 2, 3, 4],
+            "bools": [true, false, true],
+            "words": ["one", "two", "three"],
+            "nested": {"inner": [{"x": 1}, {"x": 2}], "empty": []},
+            "dict": {"x": 1, "y": 2, "z": 3},
+            "none": null
+        }"#,
+    )
+    .unwrap()
+    .serialize(ValueSerializer)
+    .unwrap()
+}
+
+/// Future that is pending once before it completes
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+fn ruleset_for(rule: Rule) -> RuleSet {
+    ruleset_with(rule, |builder| builder)
+}
+
+fn ruleset_with(rule: Rule, configure: impl FnOnce(Builder) -> Builder) -> RuleSet {
+    let builder = ruleset()
+        .with_rule(rule)
+        .unwrap()
+        .with_fn("double", |value: i64| Ok::<_, anyhow::Error>(value * 2))
+        .unwrap()
+        .with_fn("pair", |left: Value, right: Value| {
+            Ok::<_, anyhow::Error>(Value::Vec(vec![left, right]))
+        })
+        .unwrap()
+        .with_fn("fail", |_: Value| {
+            Err::<Value, _>(anyhow::anyhow!("failed"))
+        })
+        .unwrap()
+        .with_async_fn("slow", |value: Value| async move {
+            YieldOnce(false).await;
+            Ok::<_, anyhow::Error>(value)
+        })
+        .unwrap()
+        .with_symbol("limit", Value::Int(3));
+
+    configure(builder).build()
+}
+
+/// Evaluate a rule directly and compiled and check both give the same result,
+/// returns the result
+async fn assert_same(rule: Rule, facts: &Value) -> String {
+    assert_same_with(rule, facts, |builder| builder).await
+}
+
+async fn assert_same_with(
+    rule: Rule,
+    facts: &Value,
+    configure: impl FnOnce(Builder) -> Builder,
+) -> String {
+    let compiled = rule.compile();
+    let ruleset = ruleset_with(rule, configure);
+
+    let expected = describe(&ruleset.evaluate_value(facts).await.unwrap()[0].value);
+    let actual = describe(&compiled.evaluate(&ruleset, facts).await);
+
+    assert_eq!(
+        actual,
+        expected,
+        "Compiled rule `{}` gives a different result",
+        compiled.rule().source().unwrap_or_default()
+    );
+
+    expected
+}
+
+/// Describe a result including the location of errors, user-function errors
+/// can contain backtraces that differ between evaluators so they are left out
+fn describe(result: &reval::Result<Value>) -> String {
+    match result {
+        Ok(value) => format!("{value:?}"),
+        Err(error) => format!("Error({error}, {:?})", error.span()),
+    }
+}
+
+async fn assert_same_source(source: &str) -> String {
+    assert_same(
+        Rule::parse(&format!("// rule\n{source}")).unwrap(),
+        &facts(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn should_evaluate_values_and_references() {
+    for source in [
+        "i5",
+        r#""string""#,
+        "none",
+        "a",
+        "facts",
+        "facts.a",
+        "nested.inner.1.x",
+        "nested.inner.5.x",
+        "none.a.0",
+        "missing",
+        "missing.a",
+        "a.b",
+        "list.x",
+        ":limit",
+        ":unknown",
+        "[a, b, s]",
+        "{one: a, two: {three: list.0}}",
+        "[i1, i2, i3].1",
+        "{x: a}.x",
+        "(if flag then dict else list).y",
+    ] {
+        assert_same_source(source).await;
+    }
+}
+
+#[tokio::test]
+async fn should_evaluate_operators() {
+    for source in [
+        "a + i1 * i2 - i3 / i2 % i2",
+        "b * f2.0 - f0.5",
+        "d1.5 + dec(a)",
+        "a / i0",
+        "a * i170141183460469231731687303715884105727",
+        "-i-170141183460469231731687303715884105728",
+        "a + b",
+        "-a",
+        "-s",
+        "!flag",
+        "!a",
+        "a == i5",
+        "a != i5",
+        "none == none",
+        "none != none",
+        "none == missing",
+        "a == missing",
+        "a > i4 and b < f3.0",
+        "[a, b] < [f5.0, d3]",
+        "a >= s",
+        "a <= i5 or missing",
+        "flag and a",
+        "!flag and missing",
+        "flag or missing",
+        "a or flag",
+        "none and false",
+        "none or flag",
+        "!flag or none",
+        "none and a",
+        "flag ^ none",
+        "a ^ flag",
+        "missing ?? a",
+        "none ?? nested.inner ?? a",
+        "a ?? unknown(a)",
+        "nested!.inner!.1!.x",
+        "nested!.inner!.5",
+        "dict!.w ?? a",
+        "a!.b",
+        "0b0110 & 0b0011 | 0b1000 ^ 0b0001",
+        "flag & a",
+        "list contains i3",
+        "i3 in list",
+        "f3.0 in list",
+        r#"s contains "String""#,
+        r#"dict contains "x""#,
+        r#"s starts "Some" and s ends " ""#,
+        "a starts s",
+    ] {
+        assert_same_source(source).await;
+    }
+}
+
+#[tokio::test]
+async fn should_evaluate_builtin_functions() {
+    for source in [
+        "int(b) + int(\"12\")",
+        "float(a)",
+        "int(s)",
+        "dec(b)",
+        "is_some(a) and is_none(none)",
+        "uppercase(trim(s)) + lowercase(s)",
+        "uppercase(a)",
+        "round(b) + floor(b) + fract(b)",
+        "round(a)",
+        r#"year(datetime("2015-07-30T03:26:13Z")) + month(datetime(i1438226773))"#,
+        "day(datetime(i1438226773)) + hour(duration(i3600)) + minute(i5)",
+        "week(i2) + duration(i10)",
+        "second(s)",
+        "any(bools) and all(bools)",
+        "any(list)",
+        "all(a)",
+    ] {
+        assert_same_source(source).await;
+    }
+}
+
+#[tokio::test]
+async fn should_evaluate_user_functions() {
+    for source in [
+        "double(a)",
+        "double(s)",
+        "pair(a, slow(b))",
+        "slow(slow(list)).2",
+        "fail(a)",
+        "unknown(a)",
+        "pair(a)",
+        "for x in list map slow(x) * double(x)",
+    ] {
+        assert_same_source(source).await;
+    }
+}
+
+#[tokio::test]
+async fn should_evaluate_control_flow() {
+    for source in [
+        "if flag then a else missing",
+        "if !flag then missing else b",
+        "if a then i1 else i2",
+        "if missing then i1 else i2",
+        "if flag then if a > i3 then s else none else i0",
+        "let x = a * i2 in x + x",
+        "let x = list in for y in x map y * a",
+        "for y in list map let z = y * y in z + y",
+        "let x = missing in x",
+        "let x = nested.inner in x.1.x",
+        "let a = b in a",
+    ] {
+        assert_same_source(source).await;
+    }
+}
+
+#[tokio::test]
+async fn should_evaluate_loops() {
+    for source in [
+        "for x in list map x * i2",
+        "for x in list filter x % i2 == i0",
+        "for x in dict map x + a",
+        "for x in dict filter x > i1",
+        "for x in nested.inner map x.x",
+        "for x in nested.empty map x",
+        "for x in none map x",
+        "for x in a map x",
+        "for x in s filter x",
+        "for x in list map for y in list filter y > x",
+        "for x in list map for x in words map x",
+        "for a in list map a + b",
+        "for facts in list map facts",
+        "for x in list map x + missing",
+        "for x in list map y",
+        "[for x in list map x, x]",
+        "for x in list filter x",
+        "for w in words map pair(w, slow(w))",
+        "for x in list map {value: x, double: double(x), items: list}",
+        "for k, v in dict map k + v",
+        "for k, v in dict filter v > i1",
+        "for i, x in list map i * x",
+        "for i, x in list filter i < i2",
+        "for k, v in none map v",
+        "for k, v in a map v",
+        "for k, v in dict map for i, x in list filter x == v",
+        "for k, v in dict map k + missing",
+        "keys(dict)",
+        "values(list)",
+        "entries(nested.inner)",
+        "keys(none)",
+        "keys(a)",
+        "keys(list, list)",
+        "for x in list fold acc = i0 with acc + x",
+        "for x in dict fold acc = [] with [x, acc]",
+        "for x in list fold acc = x with acc",
+        "for x in none fold acc = missing with acc",
+        "for x in a fold acc = i0 with acc",
+        "for x in list fold acc = i0 with acc + s",
+        "for x in list fold x = i1 with x * i2",
+        "for x in list fold acc = i0 with for y in list fold acc = acc with acc + x * y",
+        "for x in list map for y in list fold acc = x with acc + y",
+        "let acc = i10 in for x in list fold acc = acc with acc - x",
+        "sum(list)",
+        "sum([i1, b, none])",
+        "sum(words)",
+        "count(list)",
+        "min(words)",
+        "max([i1, b, a])",
+        "avg(list)",
+        "avg([])",
+        "avg(dict)",
+        "min([i1, s])",
+        "for x in list sort_by -x",
+        "for x in dict sort_by x",
+        "for x in nested.inner sort_by x.x",
+        "for x in words sort_by x + a",
+        "for x in none sort_by x",
+        "list[1..3]",
+        "list[i-2..]",
+        "s[..i4]",
+        "list[a..missing]",
+        "list[s..]",
+        "a[..]",
+        "list + list",
+        "len(s)",
+        "sort(words)",
+        "distinct(list + list)",
+        "flatten(nested.inner)",
+        "reverse(list)",
+        "concat(list, words)",
+        "merge(dict, {x: s})",
+        "first(words)",
+        "last(none)",
+    ] {
+        assert_same_source(source).await;
+    }
+}
+
+#[tokio::test]
+async fn should_locate_errors_at_the_same_expression() {
+    let result = assert_same_source("for x in list map if x > i2 then x + s else x").await;
+    assert!(result.contains("Some(Span"));
+}
+
+#[tokio::test]
+async fn should_evaluate_rules_without_source() {
+    let rule = Rule::new(
+        "rule",
+        BTreeMap::new(),
+        Expr::add(Expr::reff("a"), Expr::reff("s")),
+    );
+
+    assert!(assert_same(rule, &facts()).await.ends_with("None)"));
+}
+
+#[tokio::test]
+async fn should_evaluate_against_other_facts() {
+    let rule = Rule::parse("// rule\nfacts.0 + a").unwrap();
+
+    for facts in [
+        Value::None,
+        Value::Int(5),
+        Value::Vec(vec![Value::Int(1)]),
+        Value::Map(BTreeMap::from([("a".to_string(), Value::Int(1))])),
+    ] {
+        assert_same(rule.clone(), &facts).await;
+    }
+}
+
+#[test]
+fn should_evaluate_compiled_rules_without_runtime() {
+    let rule = Rule::parse("// rule\nfor x in list map slow(x) + double(x)").unwrap();
+    let compiled = rule.compile();
+
+    assert_eq!(
+        compiled
+            .evaluate_blocking(&ruleset_for(rule), &facts())
+            .unwrap(),
+        Value::Vec(vec![3.into(), 6.into(), 9.into(), 12.into()])
+    );
+}
+
+/// Generate random rules from a small grammar and compare the results. Leaves
+/// and operators are picked to produce both valid results and all kinds of
+/// errors
+#[tokio::test]
+async fn should_evaluate_generated_rules() {
+    let mut random = Random(0x5eed_1234_abcd_ef01);
+    let facts = facts();
+
+    for _ in 0..2000 {
+        let source = generate(&mut random, 4, &mut Vec::new());
+        let rule = Rule::parse(&format!("// generated\n{source}"))
+            .unwrap_or_else(|error| panic!("Generated rule `{source}` should parse; {error}"));
+
+        assert_same(rule, &facts).await;
+    }
+}
+
+#[tokio::test]
+async fn should_evaluate_strict_numbers() {
+    for source in [
+        "a + b",
+        "a == f5.0",
+        "a != d5",
+        "d1.5 * a",
+        "b > a",
+        "[a] < [f5.0]",
+    ] {
+        let rule = Rule::parse(&format!("// rule\n{source}")).unwrap();
+        let result = assert_same_with(rule, &facts(), |builder| {
+            builder.with_numeric_policy(NumericPolicy::Strict)
+        })
+        .await;
+        assert!(result.starts_with("Error") || result == "Bool(false)" || result == "Bool(true)");
+    }
+}
+
+#[tokio::test]
+async fn should_evaluate_none_as_false() {
+    for source in [
+        "none and flag",
+        "none or flag",
+        "!flag or none",
+        "!none",
+        "none ^ flag",
+        "none and a",
+    ] {
+        let rule = Rule::parse(&format!("// rule\n{source}")).unwrap();
+        let result = assert_same_with(rule, &facts(), |builder| {
+            builder.with_logic_policy(LogicPolicy::NoneIsFalse)
+        })
+        .await;
+        assert!(result != "None");
+    }
+}
+
+/// Limits have to be hit at the same expression, generated rules are
+/// evaluated with every step count up to the steps they need
+#[tokio::test]
+async fn should_enforce_limits_on_generated_rules() {
+    let mut random = Random(0x1147_5eed_0000_0001);
+    let facts = facts();
+    let mut exceeded = 0;
+
+    for _ in 0..300 {
+        let source = generate(&mut random, 4, &mut Vec::new());
+        let rule = Rule::parse(&format!("// generated\n{source}")).unwrap();
+
+        for max in 0..40 {
+            let limits = EvalLimits::new().with_max_steps(max);
+            let result =
+                assert_same_with(rule.clone(), &facts, |builder| builder.with_limits(limits)).await;
+            exceeded += result.contains("exceeded the limit") as usize;
+        }
+
+        for max in 0..6 {
+            for limits in [
+                EvalLimits::new().with_max_depth(max),
+                EvalLimits::new().with_max_collection_len(max),
+                EvalLimits::new().with_max_string_len(max),
+                EvalLimits::new().with_max_function_calls(max),
+            ] {
+                let result =
+                    assert_same_with(rule.clone(), &facts, |builder| builder.with_limits(limits))
+                        .await;
+                exceeded += result.contains("exceeded the limit") as usize;
+            }
+        }
+    }
+
+    assert!(exceeded > 1000);
+}
+
+/// Xorshift random number generator, keeps the generated rules reproducible
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, max: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % max as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+const LEAVES: [&str; 22] = [
+    "i1",
+    "i3",
+    "i-2",
+    "f1.5",
+    "d2.25",
+    "true",
+    "false",
+    "none",
+    r#""text""#,
+    "a",
+    "b",
+    "s",
+    "flag",
+    "list",
+    "bools",
+    "words",
+    "dict",
+    "nested.inner",
+    "dict!.y",
+    "missing",
+    ":limit",
+    "facts",
+];
+
+const BINARY: [&str; 21] = [
+    "+", "-", "*", "/", "%", "==", "!=", ">", ">=", "<", "<=", "and", "or", "&", "|", "^",
+    "contains", "in", "starts", "ends", "??",
+];
+
+const UNARY: [&str; 14] = [
+    "int",
+    "float",
+    "dec",
+    "is_some",
+    "is_none",
+    "uppercase",
+    "trim",
+    "round",
+    "fract",
+    "any",
+    "all",
+    "year",
+    "double",
+    "slow",
+];
+
+fn generate(random: &mut Random, depth: usize, bound: &mut Vec<String>) -> String {
+    if depth == 0 {
+        return match bound.len() {
+            0 => random.pick(&LEAVES).to_string(),
+            len => match random.below(3) {
+                0 => bound[random.below(len)].clone(),
+                _ => random.pick(&LEAVES).to_string(),
+            },
+        };
+    }
+
+    let depth = depth - 1;
+    match random.below(12) {
+        0 | 1 => generate(random, 0, bound),
+        2..=4 => format!(
+            "({} {} {})",
+            generate(random, depth, bound),
+            random.pick(&BINARY),
+            generate(random, depth, bound)
+        ),
+        5 => format!(
+            "{}({})",
+            random.pick(&UNARY),
+            generate(random, depth, bound)
+        ),
+        6 => format!(
+            "({}({}))",
+            random.pick(&["-", "!"]),
+            generate(random, depth, bound)
+        ),
+        7 => format!(
+            "(if {} then {} else {})",
+            generate(random, depth, bound),
+            generate(random, depth, bound),
+            generate(random, depth, bound)
+        ),
+        8 => {
+            let list = generate(random, depth, bound);
+            let name = random.pick(&["x", "y", "a"]).to_string();
+            bound.push(name.clone());
+            let body = generate(random, depth, bound);
+            bound.pop();
+
+            let kind = random.pick(&["map", "filter"]);
+            format!("(for {name} in {list} {kind} {body})")
+        }
+        9 => format!(
+            "[{}, {}]",
+            generate(random, depth, bound),
+            generate(random, depth, bound)
+        ),
+        10 => format!(
+            "{{k: {}, l: {}}}",
+            generate(random, depth, bound),
+            generate(random, depth, bound)
+        ),
+        _ => format!(
+            "({}).{}",
+            generate(random, depth, bound),
+            random.pick(&["0", "1", "x", "inner"])
+        ),
+    }
+}
@@ -0,0 +1,80 @@
+use crate::common::Slow;
+use reval::prelude::*;
+use std::sync::atomic::Ordering;
+
+fn ruleset_with(slow: Slow, rules: i128, concurrency: usize) -> RuleSet {
+    ruleset()
+        .with_rules(
+            (0..rules)
+                .map(|index| Rule::parse(&format!("// rule {index}\nslow(i{index})")).unwrap()),
+        )
+        .unwrap()
+        .with_function(slow)
+        .unwrap()
+        .with_concurrency(concurrency)
+        .build()
+}
+
+#[tokio::test]
+async fn should_evaluate_rules_one_after_another_by_default() {
+    let slow = Slow::default();
+    let ruleset = ruleset()
+        .with_rules(
+            (0..4).map(|index| Rule::parse(&format!("// rule {index}\nslow(i{index})")).unwrap()),
+        )
+        .unwrap()
+        .with_function(slow.clone())
+        .unwrap()
+        .build();
+
+    ruleset.evaluate(&()).await.unwrap();
+
+    assert_eq!(slow.max_running.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn should_evaluate_rules_concurrently() {
+    let slow = Slow::default();
+    let ruleset = ruleset_with(slow.clone(), 4, 4);
+
+    ruleset.evaluate(&()).await.unwrap();
+
+    assert_eq!(slow.max_running.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn should_limit_concurrent_rules() {
+    let slow = Slow::default();
+    let ruleset = ruleset_with(slow.clone(), 8, 3);
+
+    ruleset.evaluate(&()).await.unwrap();
+
+    assert_eq!(slow.max_running.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn should_return_outcomes_in_rule_order() {
+    let ruleset = ruleset_with(Slow::default(), 8, 3);
+
+    let outcomes = ruleset.evaluate(&()).await.unwrap();
+
+    for (index, outcome) in outcomes.iter().enumerate() {
+        assert_eq!(outcome.rule.name(), format!("rule {index}"));
+        assert_eq!(outcome.value.as_ref().unwrap(), &(index as i128).into());
+    }
+}
+
+#[test]
+fn should_evaluate_concurrently_without_runtime() {
+    let ruleset = ruleset()
+        .with_rules(
+            (0..4).map(|index| Rule::parse(&format!("// rule {index}\ni{index} * i2")).unwrap()),
+        )
+        .unwrap()
+        .with_concurrency(2)
+        .build();
+
+    let outcomes = ruleset.evaluate_blocking(&()).unwrap();
+
+    assert_eq!(outcomes[3].value.as_ref().unwrap(), &6.into());
+}
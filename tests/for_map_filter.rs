@@ -1,4 +1,6 @@
 use crate::common::eval_expr;
+use reval::prelude::*;
+use std::collections::BTreeMap;
 
 #[tokio::test]
 async fn should_evaluate_for_map() {
@@ -40,6 +42,48 @@ async fn should_evaluate_nested_for_filter() {
     );
 }
 
+#[tokio::test]
+async fn should_see_outer_binding_in_inner_loop() {
+    assert_eq!(
+        eval_expr("for x in [i1, i2] map for y in [i10, i20] map x + y", ()).await,
+        vec![Value::from(vec![11, 21]), Value::from(vec![12, 22])].into()
+    );
+}
+
+#[tokio::test]
+async fn should_shadow_outer_binding_in_inner_loop() {
+    assert_eq!(
+        eval_expr(
+            "for x in [i1, i2] map for x in [i10, i20] filter x > i10",
+            ()
+        )
+        .await,
+        vec![Value::from(vec![20]), Value::from(vec![20])].into()
+    );
+}
+
+#[tokio::test]
+async fn should_restore_outer_binding_after_inner_loop() {
+    assert_eq!(
+        eval_expr("for x in [i1, i2] map [for x in [i5] map x, x]", ()).await,
+        vec![
+            Value::from(vec![Value::from(vec![5]), 1.into()]),
+            Value::from(vec![Value::from(vec![5]), 2.into()])
+        ]
+        .into()
+    );
+}
+
+#[tokio::test]
+async fn should_evaluate_nested_loops_over_large_lists() {
+    let facts = BTreeMap::from([("items", (0..300).collect::<Vec<i64>>())]);
+
+    assert_eq!(
+        eval_expr("for x in items map any(for y in items map y == x)", facts).await,
+        vec![true; 300].into()
+    );
+}
+
 #[tokio::test]
 async fn should_evaluate_any() {
     assert_eq!(
@@ -0,0 +1,101 @@
+use crate::common::{eval_expr, facts, try_eval_rule};
+use reval::prelude::*;
+use serde_json::json;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+#[tokio::test]
+async fn should_bind_value_in_body() {
+    assert_eq!(
+        try_eval_rule(
+            "let total = order.total in total * :vat_rate",
+            facts(json!({"order": {"total": 200}})),
+            ruleset().with_symbol("vat_rate", Value::Int(2)),
+        )
+        .await
+        .unwrap(),
+        400.into()
+    );
+}
+
+#[tokio::test]
+async fn should_evaluate_bound_expression_once() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let lookup = ruleset()
+        .with_fn("lookup", move |value: i128| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, anyhow::Error>(value * 10)
+        })
+        .unwrap();
+
+    let result = try_eval_rule(
+        "let price = lookup(i4) in price + price * price",
+        Value::None,
+        lookup,
+    )
+    .await;
+
+    assert_eq!(result.unwrap(), 1640.into());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn should_shadow_facts_and_outer_bindings() {
+    let facts = json!({"x": "fact"});
+
+    assert_eq!(eval_expr("let x = i1 in x", &facts).await, 1.into());
+    assert_eq!(
+        eval_expr("let x = i1 in let x = x + i1 in x", &facts).await,
+        2.into()
+    );
+}
+
+#[tokio::test]
+async fn should_restore_outer_scope_after_body() {
+    assert_eq!(
+        eval_expr("[let x = i1 in x, x]", json!({"x": "fact"})).await,
+        Value::Vec(vec![1.into(), "fact".into()])
+    );
+}
+
+#[tokio::test]
+async fn should_bind_inside_loops() {
+    assert_eq!(
+        eval_expr(
+            "for item in order.items map let double = item * i2 in double + item",
+            json!({"order": {"items": [1, 2, 3]}})
+        )
+        .await,
+        Value::Vec(vec![3.into(), 6.into(), 9.into()])
+    );
+}
+
+#[tokio::test]
+async fn should_use_bindings_in_loops() {
+    assert_eq!(
+        eval_expr(
+            "let total = order.total in for item in order.items filter item * i100 < total",
+            json!({"order": {"total": 200, "items": [1, 2, 3]}})
+        )
+        .await,
+        Value::Vec(vec![1.into()])
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_error_in_bound_expression() {
+    let result = try_eval_rule(
+        "let x = order.total + true in i1",
+        facts(json!({"order": {"total": 200}})),
+        ruleset(),
+    )
+    .await;
+
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Cannot apply `+` to Int and Bool at 16..34"
+    );
+}
@@ -0,0 +1,161 @@
+use reval::{limits::Limit, prelude::*, Error};
+use serde_json::json;
+
+async fn evaluate(source: &str, limits: EvalLimits) -> reval::Result<Value> {
+    let ruleset = ruleset()
+        .with_rule(Rule::parse(&format!("// limited\n{source}")).unwrap())
+        .unwrap()
+        .with_fn("double", |value: i64| Ok::<_, anyhow::Error>(value * 2))
+        .unwrap()
+        .with_limits(limits)
+        .build();
+
+    let facts = json!({"numbers": (0..1000).collect::<Vec<_>>(), "name": "reval"});
+    let mut outcomes = ruleset.evaluate(&facts).await.unwrap();
+    outcomes.remove(0).value
+}
+
+fn exceeded(result: reval::Result<Value>) -> (Limit, usize) {
+    match result.unwrap_err().without_span() {
+        Error::LimitExceeded { limit, max } => (*limit, *max),
+        error => panic!("Expected a limit error, got {error}"),
+    }
+}
+
+#[tokio::test]
+async fn should_not_limit_evaluation_by_default() {
+    let result = evaluate("for x in numbers map x * i2", EvalLimits::new()).await;
+
+    assert!(matches!(result, Ok(Value::Vec(items)) if items.len() == 1000));
+}
+
+#[tokio::test]
+async fn should_limit_evaluation_steps() {
+    let limits = EvalLimits::new().with_max_steps(100);
+
+    assert_eq!(
+        exceeded(evaluate("for x in numbers map x * i2", limits).await),
+        (Limit::Steps, 100)
+    );
+    assert!(evaluate("for x in [i1, i2] map x * i2", limits)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn should_limit_nesting_depth() {
+    let limits = EvalLimits::new().with_max_depth(3);
+
+    assert_eq!(
+        exceeded(evaluate("[[[[i1]]]]", limits).await),
+        (Limit::Depth, 3)
+    );
+    assert!(evaluate("[[i1]]", limits).await.is_ok());
+}
+
+#[tokio::test]
+async fn should_limit_collection_length() {
+    let limits = EvalLimits::new().with_max_collection_len(10);
+
+    assert_eq!(
+        exceeded(evaluate("for x in numbers map x", limits).await),
+        (Limit::CollectionLen, 10)
+    );
+    assert_eq!(
+        exceeded(evaluate("for x in numbers filter x > i5", limits).await),
+        (Limit::CollectionLen, 10)
+    );
+    assert!(evaluate("for x in numbers filter x < i5", limits)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn should_count_nested_collection_items() {
+    let limits = EvalLimits::new().with_max_collection_len(10);
+    let doubling = "let a = [i1, i1] in let b = [a, a] in let c = [b, b] in [c, c]";
+
+    assert_eq!(
+        exceeded(evaluate(doubling, limits).await),
+        (Limit::CollectionLen, 10)
+    );
+    assert_eq!(
+        exceeded(evaluate("for x in [i1, i2, i3, i4] map [x, x]", limits).await),
+        (Limit::CollectionLen, 10)
+    );
+    assert!(evaluate("let a = [i1, i1] in [a, a]", limits).await.is_ok());
+}
+
+#[tokio::test]
+async fn should_limit_strings_nested_in_collections() {
+    let limits = EvalLimits::new().with_max_string_len(4);
+
+    assert_eq!(
+        exceeded(evaluate("{user: {name: name}}", limits).await),
+        (Limit::StringLen, 4)
+    );
+    assert_eq!(
+        exceeded(evaluate("[[name]]", limits).await),
+        (Limit::StringLen, 4)
+    );
+}
+
+#[tokio::test]
+async fn should_not_limit_facts_that_are_read() {
+    let limits = EvalLimits::new().with_max_collection_len(10);
+
+    assert!(evaluate("numbers", limits).await.is_ok());
+    assert!(evaluate("i5 in numbers", limits).await.is_ok());
+}
+
+#[tokio::test]
+async fn should_limit_string_length() {
+    let limits = EvalLimits::new().with_max_string_len(4);
+
+    assert_eq!(
+        exceeded(evaluate("uppercase(name)", limits).await),
+        (Limit::StringLen, 4)
+    );
+    assert!(evaluate(r#"uppercase("abc")"#, limits).await.is_ok());
+}
+
+#[tokio::test]
+async fn should_limit_function_calls() {
+    let limits = EvalLimits::new().with_max_function_calls(3);
+
+    assert_eq!(
+        exceeded(evaluate("for x in numbers map double(x)", limits).await),
+        (Limit::FunctionCalls, 3)
+    );
+    assert!(evaluate("double(double(double(i1)))", limits).await.is_ok());
+}
+
+#[tokio::test]
+async fn should_limit_each_rule_separately() {
+    let ruleset = ruleset()
+        .with_rule(Rule::parse("// one\nfor x in [i1, i2, i3] map x").unwrap())
+        .unwrap()
+        .with_rule(Rule::parse("// two\nfor x in [i1, i2, i3] map x").unwrap())
+        .unwrap()
+        .with_limits(EvalLimits::new().with_max_steps(10))
+        .build();
+
+    for outcome in ruleset.evaluate(&()).await.unwrap() {
+        assert!(outcome.value.is_ok());
+    }
+}
+
+#[tokio::test]
+async fn should_locate_limit_errors() {
+    let error = evaluate(
+        "i1 + (for x in numbers map x)",
+        EvalLimits::new().with_max_collection_len(10),
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Evaluation exceeded the limit of 10 collection items at 17..39"
+    );
+}
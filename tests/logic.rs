@@ -0,0 +1,119 @@
+use crate::common::{eval_expr, facts, try_eval_rule};
+use reval::{prelude::*, Error};
+use serde_json::json;
+
+fn none_is_false() -> Builder {
+    ruleset().with_logic_policy(LogicPolicy::NoneIsFalse)
+}
+
+#[tokio::test]
+async fn should_propagate_none_through_and() {
+    let facts = json!({"approved": null, "verified": true, "review": {}});
+
+    assert_eq!(
+        eval_expr("approved and verified", &facts).await,
+        Value::None
+    );
+    assert_eq!(
+        eval_expr("verified and approved", &facts).await,
+        Value::None
+    );
+    assert_eq!(
+        eval_expr("approved and approved", &facts).await,
+        Value::None
+    );
+    assert_eq!(
+        eval_expr("review.approved and verified", &facts).await,
+        Value::None
+    );
+}
+
+#[tokio::test]
+async fn should_decide_and_with_false() {
+    let facts = json!({"approved": null, "blocked": false});
+
+    assert_eq!(
+        eval_expr("approved and blocked", &facts).await,
+        false.into()
+    );
+    assert_eq!(
+        eval_expr("blocked and approved", &facts).await,
+        false.into()
+    );
+}
+
+#[tokio::test]
+async fn should_propagate_none_through_or() {
+    let facts = json!({"approved": null, "blocked": false, "review": {}});
+
+    assert_eq!(eval_expr("approved or blocked", &facts).await, Value::None);
+    assert_eq!(eval_expr("blocked or approved", &facts).await, Value::None);
+    assert_eq!(
+        eval_expr("blocked or review.approved", &facts).await,
+        Value::None
+    );
+}
+
+#[tokio::test]
+async fn should_decide_or_with_true() {
+    let facts = json!({"approved": null, "verified": true});
+
+    assert_eq!(eval_expr("approved or verified", &facts).await, true.into());
+    assert_eq!(eval_expr("verified or approved", &facts).await, true.into());
+}
+
+#[tokio::test]
+async fn should_propagate_none_through_not_and_xor() {
+    let facts = json!({"approved": null, "verified": true, "blocked": false});
+
+    assert_eq!(eval_expr("!approved", &facts).await, Value::None);
+    assert_eq!(eval_expr("approved ^ verified", &facts).await, Value::None);
+    assert_eq!(eval_expr("verified ^ blocked", &facts).await, true.into());
+}
+
+#[tokio::test]
+async fn should_not_evaluate_right_side_when_left_side_decides() {
+    let facts = json!({"verified": true, "blocked": false, "age": 30});
+
+    // The right side would fail because age is not a boolean
+    assert_eq!(eval_expr("blocked and age", &facts).await, false.into());
+    assert_eq!(eval_expr("verified or age", &facts).await, true.into());
+}
+
+#[tokio::test]
+async fn should_treat_none_as_false() {
+    let facts = facts(json!({"approved": null, "verified": true, "blocked": false, "review": {}}));
+
+    for (source, expected) in [
+        ("approved and verified", false),
+        ("approved or blocked", false),
+        ("approved or verified", true),
+        ("!approved", true),
+        ("approved ^ verified", true),
+        ("!review.approved", true),
+    ] {
+        assert_eq!(
+            try_eval_rule(source, facts.clone(), none_is_false())
+                .await
+                .unwrap(),
+            expected.into(),
+            "{source}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn should_fail_for_values_that_are_not_booleans() {
+    let error = try_eval_rule(
+        "approved and age",
+        facts(json!({"approved": null, "age": 30})),
+        ruleset(),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        error.without_span(),
+        Error::InvalidType { operation, types } if operation == "and" && *types == ["Int"]
+    ));
+}
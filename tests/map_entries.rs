@@ -0,0 +1,149 @@
+use crate::common::{eval_expr, facts, strings, try_eval_expr, try_eval_rule};
+use reval::{prelude::*, Error};
+use serde_json::{json, Value as Json};
+use std::collections::BTreeMap;
+
+fn prices() -> Json {
+    json!({"prices": {"apple": 3, "pear": 5, "plum": 8}})
+}
+
+#[tokio::test]
+async fn should_map_map_entries_to_vec() {
+    assert_eq!(
+        eval_expr("for name, price in prices map [name, price * i2]", prices()).await,
+        Value::Vec(vec![
+            Value::Vec(vec!["apple".into(), 6.into()]),
+            Value::Vec(vec!["pear".into(), 10.into()]),
+            Value::Vec(vec!["plum".into(), 16.into()]),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn should_filter_map_entries_to_map() {
+    assert_eq!(
+        eval_expr(
+            r#"for name, price in prices filter price > i4 and name != "plum""#,
+            prices()
+        )
+        .await,
+        Value::from(BTreeMap::from([("pear", Value::Int(5))]))
+    );
+}
+
+#[tokio::test]
+async fn should_bind_positions_of_vec_items() {
+    assert_eq!(
+        eval_expr("for index, item in [i10, i20, i30] map index * item", ()).await,
+        vec![0, 20, 60].into()
+    );
+    assert_eq!(
+        eval_expr("for index, item in [i10, i20, i30] filter index != i1", ()).await,
+        vec![10, 30].into()
+    );
+}
+
+#[tokio::test]
+async fn should_give_none_for_keyed_loop_over_none() {
+    assert_eq!(
+        eval_expr(
+            "for key, item in nothing map item",
+            json!({"nothing": null})
+        )
+        .await,
+        Value::None
+    );
+}
+
+#[tokio::test]
+async fn should_get_keys_values_and_entries_of_map() {
+    assert_eq!(
+        eval_expr("keys(prices)", prices()).await,
+        strings(&["apple", "pear", "plum"])
+    );
+    assert_eq!(
+        eval_expr("values(prices)", prices()).await,
+        vec![3, 5, 8].into()
+    );
+    assert_eq!(
+        eval_expr("entries(prices)", prices()).await,
+        Value::Vec(vec![
+            Value::Vec(vec!["apple".into(), 3.into()]),
+            Value::Vec(vec!["pear".into(), 5.into()]),
+            Value::Vec(vec!["plum".into(), 8.into()]),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn should_get_keys_values_and_entries_of_vec() {
+    assert_eq!(
+        eval_expr("keys([i10, i20, i30])", ()).await,
+        vec![0, 1, 2].into()
+    );
+    assert_eq!(
+        eval_expr("values([i10, i20, i30])", ()).await,
+        vec![10, 20, 30].into()
+    );
+    assert_eq!(
+        eval_expr("entries([i10, i20, i30])", ()).await,
+        Value::Vec(vec![
+            vec![0, 10].into(),
+            vec![1, 20].into(),
+            vec![2, 30].into()
+        ])
+    );
+}
+
+#[tokio::test]
+async fn should_feed_map_values_to_list_pipelines() {
+    assert_eq!(
+        eval_expr("for price in values(prices) filter price > i4", prices()).await,
+        vec![5, 8].into()
+    );
+}
+
+#[tokio::test]
+async fn should_give_none_for_keys_of_none() {
+    assert_eq!(eval_expr("keys(none)", ()).await, Value::None);
+}
+
+#[tokio::test]
+async fn should_fail_for_invalid_builtin_arguments() {
+    assert!(matches!(
+        try_eval_expr("keys(i5)", Value::None).await.unwrap_err(),
+        Error::InvalidType { .. }
+    ));
+    assert!(matches!(
+        try_eval_expr("values([i1], [i1])", Value::None).await.unwrap_err(),
+        Error::InvalidFunctionArgs { function, .. } if function == "values"
+    ));
+}
+
+#[tokio::test]
+async fn should_prefer_user_function_over_builtin() {
+    let builder = ruleset()
+        .with_fn("keys", |_: Value| {
+            Ok::<_, anyhow::Error>(Value::from("user"))
+        })
+        .unwrap();
+
+    assert_eq!(
+        try_eval_rule("keys(prices)", facts(prices()), builder)
+            .await
+            .unwrap(),
+        "user".into()
+    );
+}
+
+#[tokio::test]
+async fn should_not_count_builtins_as_function_calls() {
+    let builder = ruleset().with_limits(EvalLimits::new().with_max_function_calls(0));
+
+    assert_eq!(
+        try_eval_rule("values([i10, i20, i30])", Value::None, builder)
+            .await
+            .unwrap(),
+        vec![10, 20, 30].into()
+    );
+}
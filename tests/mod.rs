@@ -1,9 +1,28 @@
+mod aggregate;
+mod batch;
+mod blocking;
 mod builtin;
+mod coalesce;
+mod collections;
 mod common;
+mod compiled;
+mod concurrency;
 mod datetime;
 mod for_map_filter;
 mod iif;
+mod let_binding;
+mod limits;
+mod logic;
+mod map_entries;
+mod ordering;
+mod overflow;
+mod promotion;
+mod serde;
+mod spans;
 mod symbols;
+mod timeouts;
+mod type_errors;
+mod user_function;
 
 use crate::common::eval_expr;
 use chrono::{prelude::*, TimeDelta};
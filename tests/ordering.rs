@@ -0,0 +1,75 @@
+use crate::common::{eval_expr, try_eval_expr};
+use reval::{prelude::*, Error};
+
+#[tokio::test]
+async fn should_compare_strings_lexicographically() {
+    assert_eq!(eval_expr(r#""Mendelt" < "N""#, ()).await, true.into());
+    assert_eq!(
+        eval_expr(r#""Mendelt" >= "Mendelt""#, ()).await,
+        true.into()
+    );
+    assert_eq!(
+        eval_expr(r#""Mendelt" > "mendelt""#, ()).await,
+        false.into()
+    );
+    assert_eq!(eval_expr(r#""" < "a""#, ()).await, true.into());
+}
+
+#[tokio::test]
+async fn should_compare_iso_date_strings() {
+    assert_eq!(
+        eval_expr(r#""2024-03-01" > "2023-12-31""#, ()).await,
+        true.into()
+    );
+    assert_eq!(
+        eval_expr(r#""2024-03-01" <= "2024-03-01""#, ()).await,
+        true.into()
+    );
+}
+
+#[tokio::test]
+async fn should_order_false_before_true() {
+    assert_eq!(eval_expr("false < true", ()).await, true.into());
+    assert_eq!(eval_expr("true >= false", ()).await, true.into());
+    assert_eq!(eval_expr("true < true", ()).await, false.into());
+}
+
+#[tokio::test]
+async fn should_compare_vecs_element_by_element() {
+    assert_eq!(eval_expr("[i1, i4, i2] < [i1, i10]", ()).await, true.into());
+    assert_eq!(eval_expr("[i1, i4, i2] > [i1, i4]", ()).await, true.into());
+    assert_eq!(
+        eval_expr("[i1, i4, i2] >= [i1, i4, i2]", ()).await,
+        true.into()
+    );
+    assert_eq!(
+        eval_expr("[i1, i4, i2] > [i1, i4, i2]", ()).await,
+        false.into()
+    );
+    assert_eq!(
+        eval_expr(r#"["a", "b"] < ["a", "c"]"#, ()).await,
+        true.into()
+    );
+}
+
+#[tokio::test]
+async fn should_not_order_none() {
+    assert_eq!(eval_expr(r#"none < "a""#, ()).await, false.into());
+    assert_eq!(eval_expr("[i1, none] < [i1, i2]", ()).await, false.into());
+}
+
+#[tokio::test]
+async fn should_fail_for_values_of_different_types() {
+    let error = try_eval_expr(r#""Mendelt" < i5"#, Value::None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::InvalidType { operation, types } if operation == "<" && types == ["String", "Int"]
+    ));
+
+    let error = try_eval_expr(r#"[i1, "a"] < [i1, true]"#, Value::None)
+        .await
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Cannot apply `<` to String and Bool");
+}
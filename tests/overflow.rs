@@ -0,0 +1,233 @@
+use crate::common::{eval_expr_err, try_eval_expr};
+use chrono::{DateTime, TimeDelta, Utc};
+use reval::prelude::*;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+const MAX: &str = "i170141183460469231731687303715884105727";
+const MIN: &str = "i-170141183460469231731687303715884105728";
+
+#[tokio::test]
+async fn should_fail_on_int_add_overflow() {
+    assert_eq!(
+        eval_expr_err(&format!("{MAX} + i1"), Value::None).await,
+        "Numeric overflow applying `+` to Int and Int"
+    );
+    assert_eq!(
+        try_eval_expr(&format!("{MAX} + i-1"), Value::None)
+            .await
+            .unwrap(),
+        (i128::MAX - 1).into()
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_int_sub_overflow() {
+    assert_eq!(
+        eval_expr_err(&format!("{MIN} - i1"), Value::None).await,
+        "Numeric overflow applying `-` to Int and Int"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_int_mult_overflow() {
+    assert_eq!(
+        eval_expr_err(&format!("{MAX} * i2"), Value::None).await,
+        "Numeric overflow applying `*` to Int and Int"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_int_div_overflow() {
+    assert_eq!(
+        eval_expr_err(&format!("{MIN} / i-1"), Value::None).await,
+        "Numeric overflow applying `/` to Int and Int"
+    );
+    assert_eq!(
+        eval_expr_err(&format!("{MIN} / i0"), Value::None).await,
+        "Division by zero"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_int_rem_overflow() {
+    assert_eq!(
+        eval_expr_err(&format!("{MIN} % i-1"), Value::None).await,
+        "Numeric overflow applying `%` to Int and Int"
+    );
+    assert_eq!(
+        eval_expr_err(&format!("{MIN} % i0"), Value::None).await,
+        "Division by zero"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_int_neg_overflow() {
+    assert_eq!(
+        eval_expr_err(&format!("-{MIN}"), Value::None).await,
+        "Numeric overflow applying `-` to Int"
+    );
+    assert_eq!(
+        try_eval_expr(&format!("-{MAX}"), Value::None)
+            .await
+            .unwrap(),
+        (-i128::MAX).into()
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_decimal_add_overflow() {
+    assert_eq!(
+        eval_expr_err(
+            "dec_max + d1",
+            BTreeMap::from([("dec_max", Value::Decimal(Decimal::MAX))])
+        )
+        .await,
+        "Numeric overflow applying `+` to Decimal and Decimal"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_decimal_sub_overflow() {
+    assert_eq!(
+        eval_expr_err(
+            "dec_min - d1",
+            BTreeMap::from([("dec_min", Value::Decimal(Decimal::MIN))])
+        )
+        .await,
+        "Numeric overflow applying `-` to Decimal and Decimal"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_decimal_mult_overflow() {
+    assert_eq!(
+        eval_expr_err(
+            "dec_max * d2",
+            BTreeMap::from([("dec_max", Value::Decimal(Decimal::MAX))])
+        )
+        .await,
+        "Numeric overflow applying `*` to Decimal and Decimal"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_decimal_div_overflow() {
+    assert_eq!(
+        eval_expr_err(
+            "dec_max / d0.5",
+            BTreeMap::from([("dec_max", Value::Decimal(Decimal::MAX))])
+        )
+        .await,
+        "Numeric overflow applying `/` to Decimal and Decimal"
+    );
+    assert_eq!(
+        eval_expr_err(
+            "dec_max / d0",
+            BTreeMap::from([("dec_max", Value::Decimal(Decimal::MAX))])
+        )
+        .await,
+        "Division by zero"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_float_to_int_overflow() {
+    assert_eq!(
+        eval_expr_err("int(huge)", BTreeMap::from([("huge", Value::Float(1e40))])).await,
+        "Numeric overflow applying `int` to Float"
+    );
+    assert!(eval_expr_err(
+        "int(nan)",
+        BTreeMap::from([("nan", Value::Float(f64::NAN))])
+    )
+    .await
+    .starts_with("Cannot cast"));
+    assert_eq!(
+        try_eval_expr("int(f-2.7)", Value::None).await.unwrap(),
+        (-2).into()
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_int_to_decimal_overflow() {
+    assert_eq!(
+        eval_expr_err(&format!("dec({MAX})"), Value::None).await,
+        "Numeric overflow applying `dec` to Int"
+    );
+}
+
+#[tokio::test]
+async fn should_not_truncate_ints_cast_to_durations() {
+    // 2^64 would wrap around to 0 when truncated to 64 bits
+    assert!(
+        eval_expr_err("duration(i18446744073709551616)", Value::None)
+            .await
+            .starts_with("Cannot cast")
+    );
+    assert!(
+        eval_expr_err("datetime(i18446744073709551616)", Value::None)
+            .await
+            .starts_with("Cannot cast")
+    );
+    assert!(eval_expr_err("week(i18446744073709551616)", Value::None)
+        .await
+        .contains("out of bounds"));
+    assert!(eval_expr_err("second(i18446744073709551616)", Value::None)
+        .await
+        .contains("out of bounds"));
+}
+
+#[tokio::test]
+async fn should_fail_on_datetime_add_overflow() {
+    assert_eq!(
+        eval_expr_err(
+            "date_max + one_day",
+            BTreeMap::from([
+                ("date_max", Value::DateTime(DateTime::<Utc>::MAX_UTC)),
+                ("one_day", Value::Duration(TimeDelta::days(1)))
+            ])
+        )
+        .await,
+        "Numeric overflow applying `+` to DateTime and Duration"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_datetime_sub_overflow() {
+    assert_eq!(
+        eval_expr_err(
+            "date_min - one_day",
+            BTreeMap::from([
+                ("date_min", Value::DateTime(DateTime::<Utc>::MIN_UTC)),
+                ("one_day", Value::Duration(TimeDelta::days(1)))
+            ])
+        )
+        .await,
+        "Numeric overflow applying `-` to DateTime and Duration"
+    );
+}
+
+#[tokio::test]
+async fn should_fail_on_duration_sub_overflow() {
+    assert_eq!(
+        eval_expr_err(
+            "dur_min - dur_max",
+            BTreeMap::from([
+                ("dur_max", Value::Duration(TimeDelta::MAX)),
+                ("dur_min", Value::Duration(TimeDelta::MIN))
+            ])
+        )
+        .await,
+        "Numeric overflow applying `-` to Duration and Duration"
+    );
+    assert_eq!(
+        try_eval_expr(
+            "dur_max - dur_max",
+            BTreeMap::from([("dur_max", Value::Duration(TimeDelta::MAX))])
+        )
+        .await
+        .unwrap(),
+        TimeDelta::zero().into()
+    );
+}
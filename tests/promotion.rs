@@ -0,0 +1,163 @@
+use crate::common::{eval_expr, try_eval_rule};
+use reval::{prelude::*, Error};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+fn dec(value: i64, scale: u32) -> Value {
+    Value::Decimal(Decimal::new(value, scale))
+}
+
+#[tokio::test]
+async fn should_promote_int_to_decimal() {
+    assert_eq!(eval_expr("i5 + d1.5", ()).await, dec(65, 1));
+    assert_eq!(eval_expr("i5 - d1.5", ()).await, dec(35, 1));
+    assert_eq!(eval_expr("i5 * d1.5", ()).await, dec(75, 1));
+    assert_eq!(eval_expr("i5 / d2", ()).await, dec(25, 1));
+    assert_eq!(eval_expr("i5 % d1.5", ()).await, dec(5, 1));
+}
+
+#[tokio::test]
+async fn should_promote_int_to_float() {
+    assert_eq!(eval_expr("i5 + f0.5", ()).await, Value::Float(5.5));
+    assert_eq!(eval_expr("f0.5 - i5", ()).await, Value::Float(-4.5));
+    assert_eq!(eval_expr("i5 * f0.5", ()).await, Value::Float(2.5));
+    assert_eq!(eval_expr("i5 / f2.0", ()).await, Value::Float(2.5));
+    assert_eq!(eval_expr("i5 % f2.0", ()).await, Value::Float(1.0));
+}
+
+#[tokio::test]
+async fn should_promote_decimal_to_float() {
+    assert_eq!(eval_expr("d4.5 + f0.5", ()).await, Value::Float(5.0));
+    assert_eq!(eval_expr("f0.5 * d4.5", ()).await, Value::Float(2.25));
+}
+
+#[tokio::test]
+async fn should_compare_promoted_numbers() {
+    assert_eq!(eval_expr("i5 > d4.5", ()).await, true.into());
+    assert_eq!(eval_expr("i5 >= d5", ()).await, true.into());
+    assert_eq!(eval_expr("i5 < f5.5", ()).await, true.into());
+    assert_eq!(eval_expr("f0.5 <= i5", ()).await, true.into());
+}
+
+#[tokio::test]
+async fn should_order_lists_of_promoted_numbers() {
+    assert_eq!(eval_expr("[i1] < [f2.0]", ()).await, true.into());
+    assert_eq!(eval_expr("[i1, d2.5] > [f1.0, i2]", ()).await, true.into());
+    assert_eq!(eval_expr("[d1, i2] <= [f1.0, f2.0]", ()).await, true.into());
+    assert_eq!(eval_expr("[[i3]] >= [[f3.5]]", ()).await, false.into());
+    assert!(try_eval_rule(
+        "[i1] < [f2.0]",
+        Value::None,
+        ruleset().with_numeric_policy(NumericPolicy::Strict)
+    )
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn should_compare_promoted_numbers_for_equality() {
+    assert_eq!(eval_expr("i5 == d5.00", ()).await, true.into());
+    assert_eq!(eval_expr("i5 == f5.0", ()).await, true.into());
+    assert_eq!(eval_expr("d4.5 != f4.5", ()).await, false.into());
+    assert_eq!(eval_expr(r#"i5 == "5""#, ()).await, false.into());
+}
+
+#[tokio::test]
+async fn should_compare_promoted_numbers_in_collections() {
+    assert_eq!(eval_expr("[i1, f2.0] == [f1.0, i2]", ()).await, true.into());
+    assert_eq!(
+        eval_expr("{a: [i1], b: d2} == {a: [f1.0], b: i2}", ()).await,
+        true.into()
+    );
+    assert_eq!(eval_expr("[i1, f2.0] != [f1.0, i3]", ()).await, true.into());
+    assert_eq!(
+        try_eval_rule(
+            "[i1] == [f1.0]",
+            Value::None,
+            ruleset().with_numeric_policy(NumericPolicy::Strict)
+        )
+        .await
+        .unwrap(),
+        false.into()
+    );
+}
+
+#[tokio::test]
+async fn should_find_promoted_numbers_in_lists() {
+    assert_eq!(eval_expr("[i1, i2] contains f2.0", ()).await, true.into());
+    assert_eq!(eval_expr("d1 in [f1.0]", ()).await, true.into());
+    assert_eq!(eval_expr("[[i1]] contains [f1.0]", ()).await, true.into());
+    assert_eq!(
+        try_eval_rule(
+            "[i1, i2] contains f2.0",
+            Value::None,
+            ruleset().with_numeric_policy(NumericPolicy::Strict)
+        )
+        .await
+        .unwrap(),
+        false.into()
+    );
+}
+
+#[tokio::test]
+async fn should_fail_when_int_does_not_fit_decimal() {
+    let error = try_eval_rule(
+        "i170141183460469231731687303715884105727 + d1",
+        Value::None,
+        ruleset(),
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+        error.without_span().to_string(),
+        "Numeric overflow applying `+` to Int and Decimal"
+    );
+}
+
+#[tokio::test]
+async fn should_not_promote_in_strict_mode() {
+    let error = try_eval_rule(
+        "i5 + d1.5",
+        Value::None,
+        ruleset().with_numeric_policy(NumericPolicy::Strict),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        error.without_span(),
+        Error::InvalidType { operation, types } if operation == "+" && types == &["Int", "Decimal"]
+    ));
+
+    assert!(try_eval_rule(
+        "i5 > f0.5",
+        Value::None,
+        ruleset().with_numeric_policy(NumericPolicy::Strict)
+    )
+    .await
+    .is_err());
+    assert_eq!(
+        try_eval_rule(
+            "i5 == d5",
+            Value::None,
+            ruleset().with_numeric_policy(NumericPolicy::Strict)
+        )
+        .await
+        .unwrap(),
+        false.into()
+    );
+}
+
+#[tokio::test]
+async fn should_promote_when_evaluating_rules() {
+    assert_eq!(
+        try_eval_rule(
+            "count + ratio",
+            BTreeMap::from([("count", Value::Int(5)), ("ratio", Value::Float(0.5))]),
+            ruleset()
+        )
+        .await
+        .unwrap(),
+        Value::Float(5.5)
+    );
+}
@@ -0,0 +1,129 @@
+//! Test round-tripping values, expressions and rules through serde formats
+
+use chrono::{prelude::*, TimeDelta};
+use reval::{expr::Index, prelude::*};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::BTreeMap, fmt::Debug};
+
+#[test]
+fn should_roundtrip_values() {
+    for value in [
+        Value::String("string \" value".to_owned()),
+        Value::Int(i128::MAX),
+        Value::Int(-5),
+        Value::Float(5.25),
+        Value::Decimal(Decimal::new(-50125, 3)),
+        Value::Bool(true),
+        Value::DateTime(Utc.with_ymd_and_hms(2015, 7, 30, 3, 26, 13).unwrap()),
+        Value::Duration(TimeDelta::milliseconds(-1500)),
+        Value::Vec(vec![Value::Int(1), Value::None]),
+        Value::Map(BTreeMap::from([("key".to_owned(), Value::Bool(false))])),
+        Value::None,
+    ] {
+        assert_roundtrip(&value);
+    }
+}
+
+#[test]
+fn should_roundtrip_index() {
+    assert_roundtrip(&Index::Map("field".to_owned()));
+    assert_roundtrip(&Index::Vec(4));
+}
+
+#[test]
+fn should_roundtrip_parsed_exprs() {
+    for expr in [
+        r#"if a.b > i5 then "big" else "small""#,
+        "for item in list.0 filter item % i2 == i0",
+        "for item in [i1, d2.5, f3.5] map -item * i2",
+        "{one: :symbol, two: user_function(none)}",
+        r#"!(is_some(a) and b contains "x") or c in [true, false]"#,
+        "year(datetime(i1438226773)) + week(duration(i3600))",
+        r#"any(["abc" starts "a", trim(" a ") ends "b"])"#,
+    ] {
+        assert_roundtrip(&Expr::parse(expr).unwrap());
+    }
+}
+
+#[test]
+fn should_roundtrip_parsed_rule() {
+    let rule = Rule::parse(
+        r#"
+@name: "rule name";
+@tags: ["one", "two"];
+@weight: d1.5;
+age >= i21 and country == "NL"
+"#,
+    )
+    .unwrap();
+
+    assert_roundtrip(&rule);
+}
+
+#[test]
+fn should_serialize_value_to_stable_json() {
+    assert_eq!(
+        serde_json::to_string(&Value::Vec(vec![
+            Value::Int(5),
+            Value::Decimal(Decimal::new(525, 2)),
+            Value::None
+        ]))
+        .unwrap(),
+        r#"{"Vec":[{"Int":5},{"Decimal":"5.25"},"None"]}"#
+    );
+}
+
+#[test]
+fn should_serialize_expr_to_stable_json() {
+    assert_eq!(
+        serde_json::to_string(&Expr::parse("a.b + i1").unwrap()).unwrap(),
+        r#"{"Add":[{"Index":[{"Reference":"a"},{"Map":"b"}]},{"Value":{"Int":1}}]}"#
+    );
+}
+
+#[test]
+fn should_serialize_rule_to_stable_json() {
+    let rule = Rule::parse("// rule name\ni1").unwrap();
+
+    assert_eq!(
+        serde_json::to_string(&rule).unwrap(),
+        r#"{"name":"rule name","metadata":{},"expr":{"Value":{"Int":1}},"source":"// rule name\ni1","spans":[{"start":13,"end":15}]}"#
+    );
+}
+
+#[test]
+fn should_deserialize_rule_without_source() {
+    let rule: Rule =
+        serde_json::from_str(r#"{"name":"rule name","metadata":{},"expr":{"Value":{"Int":1}}}"#)
+            .unwrap();
+
+    assert_eq!(
+        rule,
+        Rule::new("rule name", BTreeMap::new(), Expr::value(1))
+    );
+    assert_eq!(rule.source(), None);
+}
+
+#[tokio::test]
+async fn should_evaluate_deserialized_rule() {
+    let rule = Rule::parse("// rule name\nage >= i21").unwrap();
+    let rule: Rule = serde_json::from_str(&serde_json::to_string(&rule).unwrap()).unwrap();
+
+    let ruleset = ruleset().with_rule(rule).unwrap().build();
+    let outcome = ruleset
+        .evaluate(&BTreeMap::from([("age", 22)]))
+        .await
+        .unwrap();
+
+    assert_eq!(outcome[0].value.as_ref().unwrap(), &true.into());
+}
+
+/// Round-trip through json and bincode and check the result is unchanged
+fn assert_roundtrip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let json = serde_json::to_string(value).unwrap();
+    assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+
+    let bytes = bincode::serialize(value).unwrap();
+    assert_eq!(&bincode::deserialize::<T>(&bytes).unwrap(), value);
+}
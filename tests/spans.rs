@@ -0,0 +1,80 @@
+use reval::{parse::Span, prelude::*, Error};
+use std::collections::BTreeMap;
+
+async fn evaluate_rule(rule: &str) -> Error {
+    let ruleset = ruleset()
+        .with_rule(Rule::parse(rule).unwrap())
+        .unwrap()
+        .build();
+
+    let mut outcomes = ruleset
+        .evaluate(&BTreeMap::from([("count", 5)]))
+        .await
+        .unwrap();
+
+    outcomes.remove(0).value.unwrap_err()
+}
+
+#[tokio::test]
+async fn should_locate_unknown_reference() {
+    let error = evaluate_rule("// rule\ncount > i2 and missing > i1").await;
+
+    assert_eq!(error.span(), Some(Span::new(23, 30)));
+    assert!(matches!(error.without_span(), Error::UnknownRef(name) if name == "missing"));
+}
+
+#[tokio::test]
+async fn should_locate_innermost_failing_expression() {
+    let error = evaluate_rule("// rule\n[i1, count + \"a\", i3]").await;
+
+    assert_eq!(error.span(), Some(Span::new(13, 24)));
+    assert!(matches!(error.without_span(), Error::InvalidType { .. }));
+}
+
+#[tokio::test]
+async fn should_locate_error_in_map_item() {
+    let error = evaluate_rule("// rule\n{b: :missing, a: count}").await;
+
+    assert_eq!(error.span(), Some(Span::new(12, 20)));
+}
+
+#[tokio::test]
+async fn should_locate_error_inside_loop() {
+    let error = evaluate_rule("// rule\nfor x in [i1, i2] map x / (count - i5)").await;
+
+    assert_eq!(error.span(), Some(Span::new(30, 46)));
+    assert!(matches!(error.without_span(), Error::DivisionByZero));
+}
+
+#[tokio::test]
+async fn should_render_error_against_rule_source() {
+    let rule = Rule::parse("// rule\ncount +\n    \"a\"").unwrap();
+    let ruleset = ruleset().with_rule(rule).unwrap().build();
+
+    let outcomes = ruleset
+        .evaluate(&BTreeMap::from([("count", 5)]))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        outcomes[0].error_snippet().unwrap(),
+        "  |\n2 | count +\n  | ^^^^^^^"
+    );
+}
+
+#[tokio::test]
+async fn should_not_locate_errors_in_rules_without_source() {
+    let rule = Rule::new("rule", BTreeMap::new(), Expr::reff("missing"));
+    let ruleset = ruleset().with_rule(rule).unwrap().build();
+
+    let outcomes = ruleset
+        .evaluate(&BTreeMap::from([("count", 5)]))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        outcomes[0].value.as_ref().unwrap_err(),
+        Error::UnknownRef(_)
+    ));
+    assert_eq!(outcomes[0].error_snippet(), None);
+}
@@ -0,0 +1,190 @@
+use reval::{prelude::*, ruleset::Outcome, Error};
+use std::{
+    future::pending,
+    time::{Duration, Instant},
+};
+
+fn ruleset_with(rules: &[&str]) -> Builder {
+    ruleset()
+        .with_rules(
+            rules
+                .iter()
+                .enumerate()
+                .map(|(index, source)| Rule::parse(&format!("// rule {index}\n{source}")).unwrap()),
+        )
+        .unwrap()
+        .with_async_fn("hang", |_: Value| pending::<anyhow::Result<Value>>())
+        .unwrap()
+        .with_async_fn("sleep", |millis: u64| async move {
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            Ok::<_, anyhow::Error>(Value::Int(millis as i128))
+        })
+        .unwrap()
+}
+
+fn is_timeout(outcome: &Outcome) -> bool {
+    matches!(
+        outcome.value.as_ref().map_err(Error::without_span),
+        Err(Error::Timeout)
+    )
+}
+
+fn is_cancelled(outcome: &Outcome) -> bool {
+    matches!(
+        outcome.value.as_ref().map_err(Error::without_span),
+        Err(Error::Cancelled)
+    )
+}
+
+#[tokio::test]
+async fn should_time_out_hanging_rule() {
+    let ruleset = ruleset_with(&["hang(i1)", "i42"])
+        .with_rule_timeout(Duration::from_millis(20))
+        .build();
+
+    let outcomes = ruleset.evaluate(&()).await.unwrap();
+
+    assert!(is_timeout(&outcomes[0]));
+    assert_eq!(outcomes[1].value.as_ref().unwrap(), &42.into());
+}
+
+#[tokio::test]
+async fn should_give_every_rule_its_own_timeout() {
+    let ruleset = ruleset_with(&["sleep(i30)", "sleep(i30)", "hang(i1)"])
+        .with_rule_timeout(Duration::from_millis(200))
+        .build();
+
+    let outcomes = ruleset.evaluate(&()).await.unwrap();
+
+    assert_eq!(outcomes[0].value.as_ref().unwrap(), &30.into());
+    assert_eq!(outcomes[1].value.as_ref().unwrap(), &30.into());
+    assert!(is_timeout(&outcomes[2]));
+}
+
+#[tokio::test]
+async fn should_time_out_remaining_rules_after_evaluation_timeout() {
+    let ruleset = ruleset_with(&["i1", "hang(i1)", "i3"])
+        .with_timeout(Duration::from_millis(20))
+        .build();
+
+    let start = Instant::now();
+    let outcomes = ruleset.evaluate(&()).await.unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(outcomes[0].value.as_ref().unwrap(), &1.into());
+    assert!(is_timeout(&outcomes[1]));
+    assert!(is_timeout(&outcomes[2]));
+}
+
+#[tokio::test]
+async fn should_time_out_concurrent_rules() {
+    let ruleset = ruleset_with(&["hang(i1)", "hang(i2)", "sleep(i5)"])
+        .with_concurrency(3)
+        .with_timeout(Duration::from_millis(50))
+        .build();
+
+    let outcomes = ruleset.evaluate(&()).await.unwrap();
+
+    assert!(is_timeout(&outcomes[0]));
+    assert!(is_timeout(&outcomes[1]));
+    assert_eq!(outcomes[2].value.as_ref().unwrap(), &5.into());
+}
+
+#[tokio::test]
+async fn should_time_out_long_running_expressions() {
+    let ruleset = ruleset_with(&["for x in xs map for y in xs map x * y"])
+        .with_rule_timeout(Duration::ZERO)
+        .build();
+
+    let facts = serde_json::json!({"xs": (0..1000).collect::<Vec<_>>()});
+    let outcomes = ruleset.evaluate(&facts).await.unwrap();
+
+    assert!(is_timeout(&outcomes[0]));
+}
+
+#[tokio::test]
+async fn should_cancel_evaluation() {
+    let ruleset = ruleset_with(&["hang(i1)", "i2"]).build();
+    let token = CancellationToken::new();
+
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+    });
+
+    let outcomes = ruleset.evaluate_cancellable(&(), &token).await.unwrap();
+
+    assert!(is_cancelled(&outcomes[0]));
+    assert!(is_cancelled(&outcomes[1]));
+}
+
+#[tokio::test]
+async fn should_finish_evaluation_that_is_not_cancelled() {
+    let ruleset = ruleset_with(&["sleep(i20)"]).build();
+    let token = CancellationToken::new();
+
+    let outcomes = ruleset.evaluate_cancellable(&(), &token).await.unwrap();
+
+    assert_eq!(outcomes[0].value.as_ref().unwrap(), &20.into());
+}
+
+#[test]
+fn should_time_out_blocking_evaluation() {
+    let ruleset = ruleset_with(&["hang(i1)", "i2"])
+        .with_rule_timeout(Duration::from_millis(20))
+        .build();
+
+    let outcomes = ruleset.evaluate_blocking(&()).unwrap();
+
+    assert!(is_timeout(&outcomes[0]));
+    assert_eq!(outcomes[1].value.as_ref().unwrap(), &2.into());
+}
+
+#[tokio::test]
+async fn should_time_out_compiled_rule() {
+    let rule = Rule::parse("// hangs\nhang(i1)").unwrap();
+    let compiled = rule.compile();
+    let ruleset = ruleset_with(&[])
+        .with_rule_timeout(Duration::from_millis(20))
+        .build();
+
+    let error = compiled.evaluate(&ruleset, &Value::None).await.unwrap_err();
+
+    assert!(matches!(error.without_span(), Error::Timeout));
+    assert!(error.span().is_some());
+}
+
+#[tokio::test]
+async fn should_cancel_compiled_rule() {
+    let compiled = Rule::parse("// hangs\nhang(i1)").unwrap().compile();
+    let ruleset = ruleset_with(&[]).build();
+    let token = CancellationToken::new();
+
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+    });
+
+    let error = compiled
+        .evaluate_cancellable(&ruleset, &Value::None, &token)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error.without_span(), Error::Cancelled));
+    assert!(error.span().is_some());
+}
+
+#[tokio::test]
+async fn should_finish_compiled_rule_that_is_not_cancelled() {
+    let compiled = Rule::parse("// sleeps\nsleep(i20)").unwrap().compile();
+    let ruleset = ruleset_with(&[]).build();
+
+    let value = compiled
+        .evaluate_cancellable(&ruleset, &Value::None, &CancellationToken::new())
+        .await
+        .unwrap();
+
+    assert_eq!(value, 20.into());
+}
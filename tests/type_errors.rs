@@ -0,0 +1,107 @@
+use reval::{prelude::*, Error};
+use std::collections::BTreeMap;
+
+/// Evaluate an expression that is expected to fail and return the error text
+async fn type_error(expr: &str) -> String {
+    let facts = Value::from(BTreeMap::from([
+        ("name", Value::from("reval")),
+        ("count", Value::from(5)),
+        ("items", Value::Vec(vec![1.into(), 2.into()])),
+    ]));
+
+    Expr::parse(expr)
+        .unwrap()
+        .evaluate(&facts)
+        .await
+        .unwrap_err()
+        .to_string()
+}
+
+#[tokio::test]
+async fn should_name_binary_operator_and_operand_types() {
+    assert_eq!(
+        type_error("name > count").await,
+        "Cannot apply `>` to String and Int"
+    );
+    assert_eq!(
+        type_error("count + true").await,
+        "Cannot apply `+` to Int and Bool"
+    );
+    assert_eq!(
+        type_error("items * i2").await,
+        "Cannot apply `*` to Vec and Int"
+    );
+    assert_eq!(
+        type_error("true & i1").await,
+        "Cannot apply `&` to Bool and Int"
+    );
+}
+
+#[tokio::test]
+async fn should_name_unary_operator_and_operand_type() {
+    assert_eq!(type_error("!count").await, "Cannot apply `!` to Int");
+    assert_eq!(type_error("-name").await, "Cannot apply `-` to String");
+    assert_eq!(
+        type_error("year(count)").await,
+        "Cannot apply `year` to Int"
+    );
+    assert_eq!(
+        type_error("uppercase(items)").await,
+        "Cannot apply `uppercase` to Vec"
+    );
+}
+
+#[tokio::test]
+async fn should_name_string_operators() {
+    assert_eq!(
+        type_error("name contains i1").await,
+        "Cannot apply `contains` to String and Int"
+    );
+    assert_eq!(
+        type_error("name starts i1").await,
+        "Cannot apply `starts` to String and Int"
+    );
+}
+
+#[tokio::test]
+async fn should_name_logic_operators() {
+    assert_eq!(
+        type_error("true and count").await,
+        "Cannot apply `and` to Int"
+    );
+    assert_eq!(
+        type_error("name or true").await,
+        "Cannot apply `or` to String"
+    );
+    assert_eq!(
+        type_error("if count then i1 else i2").await,
+        "Cannot apply `if` to Int"
+    );
+}
+
+#[tokio::test]
+async fn should_name_index_and_collection_operations() {
+    assert_eq!(
+        type_error("count.field").await,
+        "Cannot apply `.field` to Int"
+    );
+    assert_eq!(type_error("any(items)").await, "Cannot apply `any` to Int");
+    assert_eq!(
+        type_error("for x in count map x").await,
+        "Cannot apply `for .. map` to Int"
+    );
+}
+
+#[tokio::test]
+async fn should_expose_operation_and_types() {
+    let error = Expr::parse("name > count")
+        .unwrap()
+        .evaluate(&BTreeMap::from([("name", Value::from("a")), ("count", 1.into())]).into())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        Error::InvalidType { operation, types } if operation == ">" && types == ["String", "Int"]
+    ));
+}
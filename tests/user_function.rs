@@ -0,0 +1,228 @@
+use reval::prelude::*;
+
+/// Returns the arguments it was called with as a vec
+struct Args;
+
+#[async_trait::async_trait]
+impl UserFunction for Args {
+    async fn call_args(&self, args: &[Value]) -> FunctionResult {
+        Ok(Value::Vec(args.to_vec()))
+    }
+
+    fn name(&self) -> &'static str {
+        "args"
+    }
+}
+
+/// Single parameter function that implements `SingleArgFunction`
+struct Param;
+
+#[async_trait::async_trait]
+impl SingleArgFunction for Param {
+    async fn call(&self, param: Value) -> FunctionResult {
+        Ok(param)
+    }
+
+    fn name(&self) -> &'static str {
+        "param"
+    }
+}
+
+async fn eval_rule(expr: &str) -> Value {
+    let ruleset = ruleset()
+        .with_rule(Rule::new(
+            "rule",
+            Default::default(),
+            Expr::parse(expr).unwrap(),
+        ))
+        .unwrap()
+        .with_function(Args)
+        .unwrap()
+        .with_function(Param)
+        .unwrap()
+        .build();
+
+    let mut outcomes = ruleset.evaluate(&()).await.unwrap();
+    outcomes.remove(0).value.unwrap()
+}
+
+#[tokio::test]
+async fn should_pass_multiple_arguments_in_order() {
+    assert_eq!(
+        eval_rule(r#"args(i1, "two", i1 + i2)"#).await,
+        Value::Vec(vec![1.into(), "two".into(), 3.into()])
+    );
+}
+
+#[tokio::test]
+async fn should_call_function_without_arguments() {
+    assert_eq!(eval_rule("args()").await, Value::Vec(Vec::new()));
+}
+
+#[tokio::test]
+async fn should_pass_single_argument_to_call() {
+    assert_eq!(eval_rule("param(i5)").await, 5.into());
+}
+
+#[tokio::test]
+async fn should_pack_multiple_arguments_for_single_parameter_function() {
+    assert_eq!(
+        eval_rule("param(i1, i2)").await,
+        Value::Vec(vec![1.into(), 2.into()])
+    );
+    assert_eq!(
+        eval_rule("param([i1, i2])").await,
+        eval_rule("param(i1, i2)").await
+    );
+}
+
+#[tokio::test]
+async fn should_pass_single_argument_to_call_args() {
+    assert_eq!(eval_rule("args(i5)").await, Value::Vec(vec![5.into()]));
+}
+
+async fn eval_with(builder: Builder, expr: &str) -> reval::Result<Value> {
+    let ruleset = builder
+        .with_rule(Rule::new(
+            "rule",
+            Default::default(),
+            Expr::parse(expr).unwrap(),
+        ))
+        .unwrap()
+        .build();
+
+    let mut outcomes = ruleset.evaluate(&()).await.unwrap();
+    outcomes.remove(0).value
+}
+
+fn typed_functions() -> Builder {
+    ruleset()
+        .with_fn(
+            "allowed",
+            |age: i64, country: String| -> anyhow::Result<bool> {
+                Ok(age >= if country == "US" { 21 } else { 18 })
+            },
+        )
+        .unwrap()
+        .with_fn("answer", || Ok::<_, anyhow::Error>(42))
+        .unwrap()
+        .with_fn("total", |values: Vec<i64>| {
+            Ok::<_, anyhow::Error>(values.iter().sum::<i64>())
+        })
+        .unwrap()
+        .with_fn("fails", |_: Value| -> anyhow::Result<Value> {
+            Err(anyhow::anyhow!("always fails"))
+        })
+        .unwrap()
+        .with_async_fn("delayed", |value: String| async move {
+            tokio::task::yield_now().await;
+            Ok::<_, anyhow::Error>(value.to_uppercase())
+        })
+        .unwrap()
+}
+
+#[tokio::test]
+async fn should_call_typed_function() {
+    assert_eq!(
+        eval_with(typed_functions(), r#"allowed(i19, "NL")"#)
+            .await
+            .unwrap(),
+        true.into()
+    );
+    assert_eq!(
+        eval_with(typed_functions(), r#"allowed(i19, "US")"#)
+            .await
+            .unwrap(),
+        false.into()
+    );
+}
+
+#[tokio::test]
+async fn should_call_typed_function_without_arguments() {
+    assert_eq!(
+        eval_with(typed_functions(), "answer()").await.unwrap(),
+        42.into()
+    );
+}
+
+#[tokio::test]
+async fn should_convert_collection_arguments() {
+    assert_eq!(
+        eval_with(typed_functions(), "total([i1, i2, i3])")
+            .await
+            .unwrap(),
+        6.into()
+    );
+}
+
+#[tokio::test]
+async fn should_call_async_typed_function() {
+    assert_eq!(
+        eval_with(typed_functions(), r#"delayed("abc")"#)
+            .await
+            .unwrap(),
+        "ABC".into()
+    );
+}
+
+#[tokio::test]
+async fn should_name_function_for_wrong_argument_count() {
+    let error = eval_with(typed_functions(), "allowed(i19)")
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Invalid arguments for user function allowed; expected 2 arguments, found 1"
+    );
+}
+
+#[tokio::test]
+async fn should_name_function_and_argument_for_wrong_argument_type() {
+    let error = eval_with(typed_functions(), r#"allowed("19", "NL")"#)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        &error,
+        reval::Error::InvalidFunctionArgs { function, reason }
+            if function == "allowed" && reason.starts_with("argument 1 is not valid")
+    ));
+}
+
+#[tokio::test]
+async fn should_check_async_function_arguments() {
+    let error = eval_with(typed_functions(), "delayed(i1)")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        reval::Error::InvalidFunctionArgs { function, .. } if function == "delayed"
+    ));
+}
+
+#[tokio::test]
+async fn should_return_typed_function_errors_as_user_function_errors() {
+    let error = eval_with(typed_functions(), "fails(i1)").await.unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Error executing user function fails; always fails"
+    );
+}
+
+#[tokio::test]
+async fn should_register_typed_functions_with_runtime_names() {
+    let builder = ["low", "high"]
+        .into_iter()
+        .enumerate()
+        .try_fold(ruleset(), |builder, (level, name)| {
+            builder.with_fn(format!("{name}_level"), move || {
+                Ok::<_, anyhow::Error>(level as i64)
+            })
+        })
+        .unwrap();
+
+    assert_eq!(eval_with(builder, "high_level()").await.unwrap(), 1.into());
+}